@@ -0,0 +1,32 @@
+//! gamemode generates the large script that the parse benchmark measures,
+//! which the tests also use to exercise a realistically sized source.
+
+/// gamemode generates a script in the style of a large SA-MP gamemode, with
+/// many functions sharing the same natives, tags and variable names.
+pub fn gamemode(functions: usize) -> String {
+    let mut source = String::from(
+        "#pragma semicolon 1\n\nenum E_PLAYER {\n    E_NAME[24],\n    Float:E_HEALTH,\n    E_SCORE\n}\n\nnew gPlayers[MAX_PLAYERS][E_PLAYER];\n\n",
+    );
+    for i in 0..functions {
+        source.push_str(&format!(
+            "stock Float:GetHealth{i}(playerid, Float:offset = 0.0) {{
+    new Float:health, name[MAX_PLAYER_NAME];
+    GetPlayerHealth(playerid, health);
+    GetPlayerName(playerid, name, sizeof name);
+    if (health > offset && IsPlayerConnected(playerid)) {{
+        health = floatsub(health, offset);
+        gPlayers[playerid][E_HEALTH] = health;
+    }} else {{
+        SendClientMessage(playerid, 0xFF0000FF, \"not connected\");
+    }}
+    for (new j = 0; j < {i} % 8; j++) {{
+        gPlayers[playerid][E_SCORE] += j * 2;
+    }}
+    return health;
+}}
+
+"
+        ));
+    }
+    source
+}
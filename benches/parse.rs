@@ -0,0 +1,105 @@
+//! parse measures lexing and parsing a large generated gamemode: how many
+//! allocations each makes, how long each takes, and how parsing scales when
+//! several sources are parsed on separate threads. Run it with
+//! `cargo bench --bench parse`.
+
+mod gamemode;
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use rook::ast::Parser;
+use rook::lexer::Lexer;
+
+use crate::gamemode::gamemode;
+
+/// FUNCTIONS is how many functions the gamemode has, about 64k lines.
+const FUNCTIONS: usize = 4000;
+
+/// THREADS is how many copies of the gamemode are parsed at once.
+const THREADS: usize = 4;
+
+/// Counting counts the allocations made by each thread. A count shared by
+/// every thread would itself be contended when parsing in parallel.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+/// measure runs `f` and returns its result, how long it took and how many
+/// allocations it made on this thread.
+fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration, usize) {
+    let allocations = ALLOCATIONS.with(|a| a.get());
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    (result, elapsed, ALLOCATIONS.with(|a| a.get()) - allocations)
+}
+
+fn main() {
+    let source = gamemode(FUNCTIONS);
+    println!("{} lines, {} bytes", source.lines().count(), source.len());
+
+    let (tokens, lexing, lex_allocations) = measure(|| Lexer::new(&source).lex());
+    let count = tokens.len();
+    println!(
+        "lex:   {:>8.2?} {:>9} allocations for {} tokens",
+        lexing, lex_allocations, count
+    );
+
+    let (result, parsing, parse_allocations) = measure(|| {
+        let mut p = Parser::new(tokens);
+        p.parse().map(|_| p.unit)
+    });
+    assert!(result.is_ok(), "the generated gamemode should parse");
+    println!(
+        "parse: {:>8.2?} {:>9} allocations, {:.2} per token",
+        parsing,
+        parse_allocations,
+        parse_allocations as f64 / count as f64
+    );
+    let (_, dropping, _) = measure(|| drop(result));
+    println!("drop:  {:>8.2?}", dropping);
+
+    // each session interns names on its own, so threads don't contend
+    let parse = |source: &str| {
+        let mut p = Parser::new(Lexer::new(source).lex());
+        p.parse().map(|_| p.unit).is_ok()
+    };
+    let (_, sequential, _) = measure(|| (0..THREADS).all(|_| parse(&source)));
+    let (_, parallel, _) = measure(|| {
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..THREADS).map(|_| s.spawn(|| parse(&source))).collect();
+            handles.into_iter().all(|h| h.join().unwrap())
+        })
+    });
+    println!(
+        "{} sources: {:>8.2?} one after another, {:>8.2?} on {} threads",
+        THREADS, sequential, parallel, THREADS
+    );
+}
@@ -1,150 +1,3118 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::ErrorCode;
+use crate::error::ParseError;
+use crate::intern::Interner;
+use crate::intern::Name;
+use crate::opcode;
+use crate::opcode::Opcode;
+use crate::opcode::OperandKind;
+use crate::token::Position;
+use crate::token::Span;
 use crate::token::Token;
 use crate::token::TokenType;
+use crate::token::TokenValue;
+
+pub mod print;
+pub mod visit;
+pub mod visit_mut;
+
+/// CompilationUnit is a whole parsed script.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CompilationUnit {
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+/// Item is anything that can appear at the top level of a script.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Item {
+    Declaration(Declaration),
+    Function(Box<FunctionDecl>),
+    Operator(Box<OperatorDecl>),
+    Enum(Box<EnumDecl>),
+    Emit(Emit),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Stmt {
+    Block(Block),
+    Empty(Span),
+    Declaration(Declaration),
+    Expr(Expr),
+    If(Box<If>),
+    While(Box<While>),
+    DoWhile(Box<DoWhile>),
+    For(Box<For>),
+    Switch(Box<Switch>),
+    Break(Span),
+    Continue(Span),
+    Return(Return),
+    Exit(Exit),
+    Sleep(Sleep),
+    Assert(Assert),
+    Label(Label),
+    Goto(Goto),
+    State(State),
+    Emit(Emit),
+}
 
-#[derive(Debug)]
-pub enum Expression {
-    GlobalScope,
-    Variable(Box<Variable>),
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Expr {
+    Literal(Box<Literal>),
+    Symbol(Box<Symbol>),
+    ArrayInitializer(Box<ArrayInitializer>),
     BinaryOperation(Box<BinaryOperation>),
+    UnaryOperation(Box<UnaryOperation>),
+    PostfixOperation(Box<PostfixOperation>),
+    TernaryOperation(Box<TernaryOperation>),
+    TagOverride(Box<TagOverride>),
+    ArrayIndex(Box<ArrayIndex>),
+    Call(Box<Call>),
+    Sizeof(Box<Sizeof>),
+    Tagof(Box<Tagof>),
+    Char(Box<Char>),
+    Defined(Box<Defined>),
+    Nameof(Box<Nameof>),
+    Addressof(Box<Addressof>),
+}
+
+/// Tag is a tag name such as `Float` in `Float:x`. `_` is the explicit
+/// untagged tag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: Name,
+    pub span: Span,
+}
+
+/// Declaration is a `new`, `static`, `stock` or `const` declaration of one or
+/// more comma-separated declarators.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Declaration {
+    pub storage: StorageClass,
+    pub constant: bool,
+    pub scope: Scope,
+    pub declarators: Vec<Declarator>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageClass {
+    New,         // new
+    Static,      // static
+    Stock,       // stock
+    StaticStock, // static stock
+    Const,       // const, a named constant with no storage
+}
+
+/// Scope is where a declared symbol is visible from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Global, // every file
+    File,   // the declaring file only, for file-level `static`
+    Local,  // the enclosing block
+}
+
+/// Declarator is one declared variable or array such as
+/// `a[MAX_PLAYERS][E_DATA]`. A scalar has no dimensions, and an empty
+/// dimension, `a[]`, is sized from the initializer.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Declarator {
+    pub name: Name,
+    pub tag: Option<Tag>,
+    pub dimensions: Vec<Option<Expr>>,
+    pub initializer: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct If {
+    pub condition: Expr,
+    pub then: Stmt,
+    pub otherwise: Option<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct While {
+    pub condition: Expr,
+    pub body: Stmt,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DoWhile {
+    pub body: Stmt,
+    pub condition: Expr,
+    pub span: Span,
+}
+
+/// For is a `for` loop. `init` is either a local declaration or an
+/// expression statement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct For {
+    pub init: Option<Stmt>,
+    pub condition: Option<Expr>,
+    pub increment: Option<Expr>,
+    pub body: Stmt,
+    pub span: Span,
+}
+
+/// Switch is a `switch` statement. Pawn cases never fall through, so each
+/// case owns exactly one statement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Switch {
+    pub value: Expr,
+    pub cases: Vec<Case>,
+    pub default: Option<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Case {
+    pub values: Vec<CaseValue>,
+    pub body: Stmt,
+    pub span: Span,
+}
+
+/// CaseValue is a single `case` value or an inclusive `low..high` range.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum CaseValue {
+    Value(Expr),
+    Range(Expr, Expr),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Return {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exit {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sleep {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Assert {
+    pub condition: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Label {
+    pub name: Name,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Goto {
+    pub label: Name,
+    pub span: Span,
+}
+
+/// State is a `state (condition) automaton:name;` statement, which switches
+/// an automaton to a new state. The default automaton has no name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct State {
+    pub automaton: Option<Name>,
+    pub name: Name,
+    pub condition: Option<Expr>,
+    pub span: Span,
+}
+
+/// EnumDecl is an enumeration such as
+/// `enum E_PLAYER (+= 1) { E_NAME[24], Float:E_HEALTH }`. Offsets and the
+/// total size are computed at parse time where every value involved is a
+/// constant the parser can see, and are None otherwise.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnumDecl {
+    pub name: Option<Name>,
+    pub tag: Option<Tag>,
+    pub increment: EnumIncrement,
+    pub members: Vec<EnumMember>,
+    pub size: Option<i32>,
+    pub span: Span,
+}
+
+/// EnumIncrement is the `(op value)` step between members, `+= 1` by default.
+/// The operator is one of `+=`, `*=` and `<<=`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnumIncrement {
+    pub operator: TokenType,
+    pub value: Expr,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnumMember {
+    pub name: Name,
+    pub tag: Option<Tag>,
+    pub size: Option<Expr>,
+    pub value: Option<Expr>,
+    pub offset: Option<i32>,
+    pub span: Span,
+}
+
+/// FunctionDecl is a function definition, `forward` prototype or `native`
+/// declaration. Only a definition has a body.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FunctionDecl {
+    pub name: Name,
+    pub tag: Option<Tag>,
+    pub qualifiers: Vec<Qualifier>,
+    pub parameters: Vec<Parameter>,
+    pub native: Option<NativeBinding>,
+    pub states: Option<StateSpec>,
+    pub body: Option<Block>,
+    pub span: Span,
+}
+
+/// OperatorDecl is a user-defined operator such as
+/// `native Float:operator+(Float:oper1, Float:oper2) = floatadd;`. `tag` is
+/// the result tag. Like FunctionDecl, only a definition has a body.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OperatorDecl {
+    pub operator: TokenType,
+    pub tag: Option<Tag>,
+    pub qualifiers: Vec<Qualifier>,
+    pub parameters: Vec<Parameter>,
+    pub native: Option<NativeBinding>,
+    pub body: Option<Block>,
+    pub span: Span,
+}
+
+/// StateSpec is a function's `<automaton:state, ...>` specifier, which makes
+/// it one implementation of a state-dispatched function. An empty list, `<>`,
+/// is the fallback used in any state without its own implementation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateSpec {
+    pub automaton: Option<Name>,
+    pub states: Vec<Name>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Qualifier {
+    Public,  // public
+    Static,  // static
+    Stock,   // stock
+    Forward, // forward
+    Native,  // native
+}
+
+/// NativeBinding is the `= ...` part of a native declaration, which either
+/// binds the native to a fixed index or aliases another native's name.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum NativeBinding {
+    Index(i32),
+    Name(Name),
+}
+
+/// Parameter is a function parameter. `tags` holds every accepted tag, so
+/// `{Float,_}:x` has two and an untagged parameter none. A variadic `...`
+/// parameter has no name.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: Name,
+    pub tags: Vec<Tag>,
+    pub reference: bool,
+    pub constant: bool,
+    pub dimensions: Vec<Option<Expr>>,
+    pub default: Option<Expr>,
+    pub variadic: bool,
+    pub span: Span,
 }
 
-#[derive(Debug)]
-pub struct Variable {
-    pub name: String,
-    pub tag: String,
+/// ArrayInitializer is a brace-enclosed list such as `{1, 2, 3}`. Elements may
+/// themselves be initializers for the next dimension. When `fill` is set the
+/// list ended in `...` and the remaining cells continue the progression of
+/// the last two elements, or repeat the last element if there is only one.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArrayInitializer {
+    pub elements: Vec<Expr>,
+    pub fill: bool,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BinaryOperation {
-    pub operator: Token,
-    pub left: Box<Expression>,
-    pub right: Box<Expression>,
+    pub operator: TokenType,
+    pub left: Expr,
+    pub right: Expr,
+    pub span: Span,
+}
+
+/// UnaryOperation is a prefix operator: `-`, `!`, `~`, `++` or `--`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnaryOperation {
+    pub operator: TokenType,
+    pub operand: Expr,
+    pub span: Span,
+}
+
+/// PostfixOperation is a postfix operator: `++` or `--`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostfixOperation {
+    pub operator: TokenType,
+    pub operand: Expr,
+    pub span: Span,
+}
+
+/// TernaryOperation is `condition ? then : otherwise`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TernaryOperation {
+    pub condition: Expr,
+    pub then: Expr,
+    pub otherwise: Expr,
+    pub span: Span,
+}
+
+/// TagOverride is `Tag:operand`, which retags the operand's value.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagOverride {
+    pub tag: Tag,
+    pub operand: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArrayIndex {
+    pub array: Expr,
+    pub index: Expr,
+    pub span: Span,
+}
+
+/// Sizeof is `sizeof a`, the number of elements in an array, or the size of
+/// an enum for `sizeof E_DATA`. Each bracket after the name steps into the
+/// next dimension, so `sizeof a[]` and `sizeof a[0][]` are the sizes of the
+/// second and third, and an enum field such as `sizeof a[E_NAME]` is the
+/// size of that field. The operand may be bracketed, as in `sizeof(a)`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sizeof {
+    pub symbol: Symbol,
+    pub dimensions: Vec<Option<Expr>>,
+    pub span: Span,
+}
+
+/// Tagof is `tagof a`, the id of a variable's tag, or `tagof(Float:)`, the
+/// id of a tag named outright. A variable takes brackets and enum fields as
+/// in Sizeof.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tagof {
+    pub operand: TagofOperand,
+    pub dimensions: Vec<Option<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum TagofOperand {
+    Symbol(Symbol),
+    Tag(Tag),
+}
+
+/// Char is `n char`, the number of cells that hold n packed characters.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Char {
+    pub operand: Expr,
+    pub span: Span,
+}
+
+/// Defined is `defined a`, which is 1 if `a` has been declared and 0
+/// otherwise.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Defined {
+    pub symbol: Symbol,
+    pub span: Span,
+}
+
+/// Nameof is `__nameof(a)`, the symbol's name as a string.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Nameof {
+    pub symbol: Symbol,
+    pub span: Span,
+}
+
+/// Addressof is `__addressof(a)`, the address of a function or variable,
+/// which isn't known until the script is compiled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Addressof {
+    pub symbol: Symbol,
+    pub span: Span,
+}
+
+/// Call is a call to a named function. Arguments may be positional or named,
+/// as in `SetTimerEx(.funcname = "Foo", .interval = 1000)`, but positional
+/// arguments must come first. Parameters passed `_` or left off the end take
+/// their default values.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Call {
+    pub function: Symbol,
+    pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+/// Argument is one argument of a Call. `name` is set for `.name = value` and
+/// `value` is None for `_`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Argument {
+    pub name: Option<Name>,
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: Name,
+    pub span: Span,
+}
+
+/// Literal is a scalar or string constant with its optional tag override and
+/// sign, as in `Float:-5`. Untagged floats and booleans carry their implicit
+/// `Float` and `bool` tags with `implicit_tag` set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Literal {
+    pub value: LiteralValue,
+    pub tag: Option<Tag>,
+    pub implicit_tag: bool,
+    pub sign: Option<Sign>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum LiteralValue {
+    Integer(i32),
+    Float(#[serde(with = "crate::json::float")] f32),
+    Character(char),
+    String(String),
+    PackedString(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sign {
+    Positive,
+    Negative,
+}
+
+/// Emit is inline assembly: one `#emit` instruction, or every instruction of
+/// an `__emit`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Emit {
+    pub instructions: Vec<EmitInstruction>,
+    pub span: Span,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmitInstruction {
+    pub opcode: Opcode,
+    pub operands: Vec<EmitOperand>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum EmitOperand {
+    Number(i32),
+    Symbol(Symbol),
+    Label(Name),
+    Relative(i32), // `.`, `.+n` or `.-n`, an offset from the instruction
+}
+
+impl Item {
+    pub fn span(&self) -> Span {
+        match self {
+            Item::Declaration(d) => d.span,
+            Item::Function(f) => f.span,
+            Item::Operator(o) => o.span,
+            Item::Enum(e) => e.span,
+            Item::Emit(e) => e.span,
+        }
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block(b) => b.span,
+            Stmt::Empty(span) | Stmt::Break(span) | Stmt::Continue(span) => *span,
+            Stmt::Declaration(d) => d.span,
+            Stmt::Expr(e) => e.span(),
+            Stmt::If(i) => i.span,
+            Stmt::While(w) => w.span,
+            Stmt::DoWhile(d) => d.span,
+            Stmt::For(f) => f.span,
+            Stmt::Switch(s) => s.span,
+            Stmt::Return(r) => r.span,
+            Stmt::Exit(e) => e.span,
+            Stmt::Sleep(s) => s.span,
+            Stmt::Assert(a) => a.span,
+            Stmt::Label(l) => l.span,
+            Stmt::Goto(g) => g.span,
+            Stmt::State(s) => s.span,
+            Stmt::Emit(e) => e.span,
+        }
+    }
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal(l) => l.span,
+            Expr::Symbol(s) => s.span,
+            Expr::ArrayInitializer(a) => a.span,
+            Expr::BinaryOperation(b) => b.span,
+            Expr::UnaryOperation(u) => u.span,
+            Expr::PostfixOperation(p) => p.span,
+            Expr::TernaryOperation(t) => t.span,
+            Expr::TagOverride(t) => t.span,
+            Expr::ArrayIndex(a) => a.span,
+            Expr::Call(c) => c.span,
+            Expr::Sizeof(s) => s.span,
+            Expr::Tagof(t) => t.span,
+            Expr::Char(c) => c.span,
+            Expr::Defined(d) => d.span,
+            Expr::Nameof(n) => n.span,
+            Expr::Addressof(a) => a.span,
+        }
+    }
+
+    /// constant_value evaluates an integer constant expression, resolving
+    /// symbols, sizes and tags through `constants`. Returns None for anything
+    /// that isn't a constant known to the caller.
+    pub fn constant_value<C: Constants + ?Sized>(&self, constants: &C) -> Option<i32> {
+        match self {
+            Expr::Literal(l) => {
+                let value = match &l.value {
+                    LiteralValue::Integer(v) => *v,
+                    LiteralValue::Character(c) => *c as i32,
+                    LiteralValue::Bool(b) => *b as i32,
+                    _ => return None,
+                };
+                Some(signed(value, l.sign == Some(Sign::Negative)))
+            }
+            Expr::Symbol(s) => constants.value(&s.name),
+            Expr::TagOverride(t) => t.operand.constant_value(constants),
+            Expr::UnaryOperation(u) => {
+                let v = u.operand.constant_value(constants)?;
+                match u.operator {
+                    TokenType::Minus => v.checked_neg(),
+                    TokenType::Tilde => Some(!v),
+                    TokenType::Bang => Some((v == 0) as i32),
+                    _ => None,
+                }
+            }
+            // cells are 4 bytes, so `n char` is the cells needed for n bytes
+            Expr::Char(c) => {
+                let v = c.operand.constant_value(constants)?;
+                Some(v.checked_add(3)? / 4)
+            }
+            Expr::Sizeof(s) => match s.dimensions.last() {
+                Some(Some(Expr::Symbol(field))) => constants.size(&field.name, 0),
+                _ => constants.size(&s.symbol.name, s.dimensions.len()),
+            },
+            Expr::Tagof(t) => {
+                let tag = match (&t.operand, t.dimensions.last()) {
+                    (TagofOperand::Tag(tag), _) => tag.name.clone(),
+                    (_, Some(Some(Expr::Symbol(field)))) => constants.tag_of(&field.name)?,
+                    (TagofOperand::Symbol(s), _) => constants.tag_of(&s.name)?,
+                };
+                constants.tag(&tag)
+            }
+            Expr::Defined(d) => constants.defined(&d.symbol.name).map(|d| d as i32),
+            Expr::BinaryOperation(b) => {
+                let l = b.left.constant_value(constants)?;
+                let r = b.right.constant_value(constants)?;
+                match b.operator {
+                    TokenType::Plus => l.checked_add(r),
+                    TokenType::Minus => l.checked_sub(r),
+                    TokenType::Asterisk => l.checked_mul(r),
+                    TokenType::Slash => l.checked_div(r),
+                    TokenType::Percent => l.checked_rem(r),
+                    TokenType::BitLeft => l.checked_shl(r as u32),
+                    TokenType::BitRight => l.checked_shr(r as u32),
+                    TokenType::BitRightUnsigned => {
+                        (l as u32).checked_shr(r as u32).map(|v| v as i32)
+                    }
+                    TokenType::BitAnd => Some(l & r),
+                    TokenType::BitOr => Some(l | r),
+                    TokenType::BitXor => Some(l ^ r),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Constants is what constant_value knows about the symbols an expression
+/// refers to. Only named values are required, which a map from names to
+/// values provides; the rest default to unknown.
+pub trait Constants {
+    /// value returns the value of a constant, such as an enum member.
+    fn value(&self, name: &Name) -> Option<i32>;
+
+    /// size returns the length of a dimension of an array, counting from 0,
+    /// or the size of an enum or enum field, as `sizeof` gives it.
+    fn size(&self, _name: &Name, _dimension: usize) -> Option<i32> {
+        None
+    }
+
+    /// tag returns the id of a tag, as `tagof` gives it.
+    fn tag(&self, _tag: &Name) -> Option<i32> {
+        None
+    }
+
+    /// tag_of returns the tag of a variable or enum field, `_` if it has
+    /// none.
+    fn tag_of(&self, _name: &Name) -> Option<Name> {
+        None
+    }
+
+    /// defined reports whether a symbol has been declared.
+    fn defined(&self, _name: &Name) -> Option<bool> {
+        None
+    }
+}
+
+impl Constants for HashMap<Name, i32> {
+    fn value(&self, name: &Name) -> Option<i32> {
+        self.get(name).copied()
+    }
+}
+
+impl OperatorDecl {
+    /// operand_tags returns the tag of each operand, `_` when untagged,
+    /// which is what overload resolution matches on.
+    pub fn operand_tags(&self) -> Vec<&str> {
+        OperatorDecl::tags_of(&self.parameters)
+    }
+
+    fn tags_of(parameters: &[Parameter]) -> Vec<&str> {
+        parameters
+            .iter()
+            .map(|p| match p.tags.first() {
+                Some(tag) => tag.name.as_str(),
+                None => "_",
+            })
+            .collect()
+    }
+}
+
+impl Call {
+    /// bind_arguments matches the arguments against the callee's parameters.
+    /// It returns one entry per fixed parameter in order, None where the
+    /// default is used, followed by any extra arguments passed to `...`.
+    /// Errors point at the offending argument, or at the call when an
+    /// argument is missing, and relate it to the parameter list.
+    pub fn bind_arguments<'a>(
+        &'a self,
+        parameters: &[Parameter],
+    ) -> Result<Vec<Option<&'a Expr>>, ParseError> {
+        let fixed = parameters.iter().take_while(|p| !p.variadic).count();
+        let variadic = fixed < parameters.len();
+        let declared = match (parameters.first(), parameters.last()) {
+            (Some(first), Some(last)) => Some(first.span.to(last.span)),
+            _ => None,
+        };
+        let error = |span: Span, message: String| {
+            let error = ParseError::new(ErrorCode::InvalidArgument, span, message);
+            match declared {
+                Some(list) => error.with_related(list, "parameters declared here"),
+                None => error,
+            }
+        };
+
+        let mut slots: Vec<Option<&Argument>> = vec![None; fixed];
+        let mut extra = vec![];
+        for (i, argument) in self.arguments.iter().enumerate() {
+            let slot = match &argument.name {
+                Some(name) => match parameters[..fixed].iter().position(|p| p.name == *name) {
+                    Some(slot) => slot,
+                    None => {
+                        return Err(error(
+                            argument.span,
+                            format!("`{}` has no parameter named `{}`", self.function.name, name),
+                        ))
+                    }
+                },
+                None if i < fixed => i,
+                None if variadic => {
+                    extra.push(argument);
+                    continue;
+                }
+                None => {
+                    return Err(error(
+                        argument.span,
+                        format!(
+                            "too many arguments to `{}`, expected at most {}",
+                            self.function.name, fixed
+                        ),
+                    ))
+                }
+            };
+            if let Some(first) = slots[slot] {
+                return Err(error(
+                    argument.span,
+                    format!(
+                        "parameter `{}` of `{}` is given more than once",
+                        parameters[slot].name, self.function.name
+                    ),
+                )
+                .with_related(first.span, "first given here"));
+            }
+            slots[slot] = Some(argument);
+        }
+
+        let mut bound = vec![];
+        for (parameter, slot) in parameters.iter().zip(slots) {
+            match slot.and_then(|a| a.value.as_ref()) {
+                Some(value) => bound.push(Some(value)),
+                None if parameter.default.is_some() => bound.push(None),
+                None => {
+                    // a `_` is its own argument, otherwise the call is missing one
+                    let span = slot.map_or(self.span, |a| a.span);
+                    return Err(error(
+                        span,
+                        format!(
+                            "parameter `{}` of `{}` has no default value",
+                            parameter.name, self.function.name
+                        ),
+                    ));
+                }
+            }
+        }
+        for argument in extra {
+            match &argument.value {
+                Some(value) => bound.push(Some(value)),
+                None => {
+                    return Err(error(
+                        argument.span,
+                        format!(
+                            "`_` can't be passed to the variadic arguments of `{}`",
+                            self.function.name
+                        ),
+                    ))
+                }
+            }
+        }
+
+        Ok(bound)
+    }
+}
+
+impl EnumDecl {
+    /// compute_offsets assigns each member its value and returns the enum's
+    /// total size. A member's value is its explicit `= value`, or else the
+    /// running counter. The increment is applied once per cell, so an array
+    /// member of size n steps the counter n times. Once anything can't be
+    /// evaluated, the remaining offsets and the size are unknown. A counter
+    /// that overflows a cell is unknown too, and only an error if a member
+    /// takes its value from it.
+    fn compute_offsets(&mut self) -> Result<Option<i32>, ParseError> {
+        let mut constants = HashMap::new();
+        let increment = self.increment.value.constant_value(&constants);
+        let operator = self.increment.operator;
+
+        let mut counter = Some(0);
+        let mut overflowed: Option<Name> = None;
+        for member in &mut self.members {
+            if let Some(value) = &member.value {
+                counter = value.constant_value(&constants);
+            } else if let Some(previous) = overflowed {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidDeclaration,
+                    member.span,
+                    format!("enum counter overflows after `{}`", previous),
+                ));
+            }
+            overflowed = None;
+            member.offset = counter;
+            if let Some(offset) = counter {
+                constants.insert(member.name.clone(), offset);
+            }
+
+            let size = match &member.size {
+                Some(size) => size.constant_value(&constants),
+                None => Some(1),
+            };
+            counter = match (counter, increment, size) {
+                (Some(c), Some(i), Some(n)) => {
+                    let next = step(operator, c, i, n);
+                    if next.is_none() {
+                        overflowed = Some(member.name.clone());
+                    }
+                    next
+                }
+                _ => None,
+            };
+        }
+
+        Ok(counter)
+    }
 }
 
-/// Node represents an actual AST node on the syntax graph.
-#[derive(Debug)]
-pub struct Node {
-    pub expr: Expression,
-    pub tokens: Vec<Token>,
-    pub children: Vec<Node>,
+/// signed applies a `-` read before an integer literal. The magnitude
+/// 2147483648 only fits a cell as i32::MIN, which negates to itself.
+fn signed(magnitude: i32, negative: bool) -> i32 {
+    if negative {
+        magnitude.wrapping_neg()
+    } else {
+        magnitude
+    }
+}
+
+/// step applies an enum increment to `counter` once for each of `cells`, in
+/// one go rather than cell by cell. Returns None if the counter overflows,
+/// which for a shift means set bits are shifted out of the cell.
+fn step(operator: TokenType, counter: i32, by: i32, cells: i32) -> Option<i32> {
+    if cells <= 0 {
+        return Some(counter);
+    }
+    let (c, by, n) = (i64::from(counter), i64::from(by), cells as u32);
+    let next = match operator {
+        TokenType::PlusAssign => c + by * i64::from(n),
+        TokenType::AsteriskAssign if c == 0 => 0,
+        TokenType::AsteriskAssign => c.checked_mul(by.checked_pow(n)?)?,
+        // shifts work on the cell's bits, so `1 << 31` is still a value
+        _ if c == 0 => 0,
+        _ => {
+            let shift = u32::try_from(by).ok()?.checked_mul(n).filter(|s| *s < 32)?;
+            let bits = u64::from(counter as u32) << shift;
+            return u32::try_from(bits).ok().map(|bits| bits as i32);
+        }
+    };
+    i32::try_from(next).ok()
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    pub root: Node,
+    allow_tags: bool,
+    end: Token,
+    semicolons_required: bool,
+    pub unit: CompilationUnit,
+    errors: Vec<ParseError>,
+    names: Interner, // the implicit `Float` and `bool` tags of literals
+}
+
+// Binding powers, from loosest to tightest. Pawn puts the bitwise operators
+// above the relational ones, unlike C.
+const BP_COMMA: u8 = 1;
+const BP_ASSIGN: u8 = 3;
+const BP_TERNARY: u8 = 5;
+const BP_PREFIX: u8 = 27;
+const BP_POSTFIX: u8 = 29;
+
+/// infix_binding_power returns the left and right binding power of a binary
+/// operator. Right-associative operators bind tighter on their left.
+fn infix_binding_power(t: TokenType) -> Option<(u8, u8)> {
+    let bp = match t {
+        TokenType::Comma => (BP_COMMA, BP_COMMA + 1),
+        TokenType::Assign
+        | TokenType::PlusAssign
+        | TokenType::MinusAssign
+        | TokenType::AsteriskAssign
+        | TokenType::SlashAssign
+        | TokenType::PercentAssign
+        | TokenType::BitAndAssign
+        | TokenType::BitOrAssign
+        | TokenType::BitXorAssign
+        | TokenType::BitLeftAssign
+        | TokenType::BitRightAssign
+        | TokenType::BitRightUnsignedAssign => (BP_ASSIGN + 1, BP_ASSIGN),
+        TokenType::Question => (BP_TERNARY + 1, BP_TERNARY),
+        TokenType::Or => (7, 8),
+        TokenType::And => (9, 10),
+        TokenType::Equal | TokenType::NotEqual => (11, 12),
+        TokenType::LowerThan
+        | TokenType::LowerThanEqual
+        | TokenType::GreaterThan
+        | TokenType::GreaterThanEqual => (13, 14),
+        TokenType::BitOr => (15, 16),
+        TokenType::BitXor => (17, 18),
+        TokenType::BitAnd => (19, 20),
+        TokenType::BitLeft | TokenType::BitRight | TokenType::BitRightUnsigned => (21, 22),
+        TokenType::Plus | TokenType::Minus => (23, 24),
+        TokenType::Asterisk | TokenType::Slash | TokenType::Percent => (25, 26),
+        _ => return None,
+    };
+    Some(bp)
+}
+
+/// is_item_keyword reports whether a token can only start a top-level item,
+/// which makes it a safe place to resume after an error.
+fn is_item_keyword(t: TokenType) -> bool {
+    matches!(
+        t,
+        TokenType::Public
+            | TokenType::Stock
+            | TokenType::Forward
+            | TokenType::Native
+            | TokenType::Enum
+    )
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
+    pub fn new(mut tokens: Vec<Token>) -> Parser {
+        // comments and whitespace carry no syntax
+        tokens.retain(|t| !matches!(t.token_type, TokenType::Comment | TokenType::Whitespace));
+        let position = match tokens.last() {
+            Some(token) => token.span.end,
+            None => Position { line: 1, column: 1 },
+        };
+        let end = Token {
+            token_type: TokenType::End,
+            value: TokenValue::None,
+            span: Span {
+                start: position,
+                end: position,
+            },
+        };
+
         Parser {
-            tokens: tokens,
+            tokens,
             current: 0,
-            root: Node {
-                expr: Expression::GlobalScope,
-                tokens: vec![],
-                children: vec![],
-            },
+            allow_tags: true,
+            end,
+            semicolons_required: false,
+            unit: CompilationUnit::default(),
+            errors: vec![],
+            names: Interner::default(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), String> {
+    /// require_semicolons sets whether every statement must end in a
+    /// semicolon, as `#pragma semicolon 1` does. By default, as in pawncc, a
+    /// statement may also end at the end of a line.
+    pub fn require_semicolons(&mut self, required: bool) {
+        self.semicolons_required = required;
+    }
+
+    /// parse parses the whole input into `unit`. Syntax errors don't stop
+    /// the parse: each is recorded, the parser skips ahead to a point where
+    /// it can resume, and every error is returned at the end alongside
+    /// whatever could be parsed.
+    pub fn parse(&mut self) -> Result<(), Vec<ParseError>> {
         while self.current < self.tokens.len() {
-            let token = &self.tokens[self.current];
+            let start = self.current;
+            match self.parse_item() {
+                Ok(Some(item)) => self.unit.items.push(item),
+                Ok(None) => (),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize(start);
+                }
+            }
+        }
+        if let (Some(first), Some(last)) = (self.tokens.first(), self.tokens.last()) {
+            self.unit.span = first.span.to(last.span);
+        }
 
-            let node = match token.token_type {
-                TokenType::New => Some(self.parse_declaration()?),
-                _ => None,
-            };
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(std::mem::take(&mut self.errors)),
+        }
+    }
 
-            if let Some(node) = node {
-                self.root.children.push(node);
+    fn parse_item(&mut self) -> Result<Option<Item>, ParseError> {
+        let item = match self.peek_type() {
+            _ if self.is_function_ahead() => self.parse_function()?,
+            TokenType::New | TokenType::Static | TokenType::Stock | TokenType::Const => {
+                Item::Declaration(self.parse_declaration(false)?)
+            }
+            TokenType::Public | TokenType::Forward | TokenType::Native => {
+                let what = format!("function after `{}`", self.peek_type());
+                self.current += 1;
+                return Err(self.unexpected(&what, &[TokenType::Symbol]));
+            }
+            TokenType::Enum => Item::Enum(Box::new(self.parse_enum()?)),
+            TokenType::Directive => return Ok(self.parse_directive()?.map(Item::Emit)),
+            TokenType::Emit => Item::Emit(self.parse_inline_emit()?),
+            TokenType::Semicolon => {
+                self.current += 1;
+                return Ok(None);
+            }
+            _ => return Err(self.unexpected("declaration or function", &[])),
+        };
+        Ok(Some(item))
+    }
+
+    /// synchronize skips past a top-level syntax error, up to and including
+    /// the next `;` or `}` outside of any braces it skips, or up to the next
+    /// keyword that starts a declaration. It always moves past the token at
+    /// `start` so the parse can't stall on it. A directive is skipped up to
+    /// the end of its line.
+    fn synchronize(&mut self, start: usize) {
+        if self.synchronize_directive(start) {
+            return;
+        }
+        if self.current == start {
+            self.current += 1;
+        }
+        let mut depth = 0;
+        while self.current < self.tokens.len() {
+            match self.peek_type() {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth > 1 => depth -= 1,
+                TokenType::RightBrace => {
+                    self.current += 1;
+                    return;
+                }
+                TokenType::Semicolon if depth == 0 => {
+                    self.current += 1;
+                    return;
+                }
+                t if is_item_keyword(t) => return,
+                TokenType::Static | TokenType::New | TokenType::Const | TokenType::Directive
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => (),
             }
+            self.current += 1;
+        }
+    }
 
+    /// synchronize_statement skips past a syntax error in a block, up to and
+    /// including the next `;` or the `}` of a block it skipped into, or up to
+    /// a `}` closing the enclosing block or a keyword that can only start a
+    /// top-level item. A directive is skipped up to the end of its line.
+    fn synchronize_statement(&mut self, start: usize) {
+        if self.synchronize_directive(start) {
+            return;
+        }
+        if self.current == start {
+            self.current += 1;
+        }
+        let mut depth = 0;
+        loop {
+            match self.peek_type() {
+                TokenType::End => return,
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth > 1 => depth -= 1,
+                TokenType::RightBrace if depth == 1 => {
+                    self.current += 1;
+                    return;
+                }
+                TokenType::RightBrace => return,
+                TokenType::Semicolon if depth == 0 => {
+                    self.current += 1;
+                    return;
+                }
+                t if is_item_keyword(t) => return,
+                _ => (),
+            }
             self.current += 1;
         }
+    }
 
-        Ok(())
+    /// synchronize_directive skips past a syntax error in a directive that
+    /// starts at `start`, up to the end of its line, as that is where the
+    /// directive ends. Returns false if there is no directive at `start`.
+    fn synchronize_directive(&mut self, start: usize) -> bool {
+        let line = match self.tokens.get(start) {
+            Some(token) if token.token_type == TokenType::Directive => token.span.start.line,
+            _ => return false,
+        };
+        self.current = start + 1;
+        while self.current < self.tokens.len() && self.peek().span.start.line == line {
+            self.current += 1;
+        }
+        true
     }
 
     fn next(&mut self) -> &Token {
-        self.current += 1;
-        &self.tokens[self.current]
+        if self.current < self.tokens.len() {
+            self.current += 1;
+            &self.tokens[self.current - 1]
+        } else {
+            &self.end
+        }
     }
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+        self.tokens.get(self.current).unwrap_or(&self.end)
     }
 
-    fn parse_declaration(&mut self) -> Result<Node, String> {
-        let token = match self.next().token_type {
-            // TODO: deal with new const, new static, new stock etc
-            TokenType::Symbol => TokenType::Symbol,
-            token @ _ => return Err(format!("expected symbol, found {:?}", token)),
-        };
-
-        let mut node = self.parse_symbol()?;
-
-        if self.peek().token_type == TokenType::Equal {
-            self.current += 1;
-            node.children.push(self.parse_expression()?);
-        };
-
-        self.expect_semicolon()?;
+    fn peek_type(&self) -> TokenType {
+        self.peek().token_type
+    }
 
-        Ok(node)
+    fn peek_type_at(&self, offset: usize) -> TokenType {
+        match self.tokens.get(self.current + offset) {
+            Some(token) => token.token_type,
+            None => TokenType::End,
+        }
     }
 
-    fn expect_symbol(&mut self) -> Result<TokenType, String> {
-        match self.next().token_type {
-            TokenType::Symbol => Ok(TokenType::Symbol),
-            token @ _ => Err(format!("expected symbol, found {:?}", token)),
+    /// peek_integer_at returns the integer literal `offset` tokens ahead as
+    /// written, without its sign. After a unary `-` that includes
+    /// 2147483648, read as i32::MIN for `signed` to apply the `-` to.
+    fn peek_integer_at(&self, offset: usize, negative: bool) -> Option<i32> {
+        let token = self.tokens.get(self.current + offset)?;
+        match (token.token_type, &token.value) {
+            (TokenType::Integer, TokenValue::Integer(v)) => Some(*v),
+            (TokenType::Illegal, TokenValue::OutOfRange(number))
+                if negative && number.parse() == Ok(i32::MIN.unsigned_abs()) =>
+            {
+                Some(i32::MIN)
+            }
+            _ => None,
         }
     }
 
-    fn expect_semicolon(&mut self) -> Result<TokenType, String> {
-        match self.next().token_type {
-            TokenType::Semicolon => Ok(TokenType::Semicolon),
-            token @ _ => Err(format!("expected semicolon, found {:?}", token)),
+    /// span returns the span of the next token, or an empty span just past
+    /// the last token at the end of input.
+    fn span(&self) -> Span {
+        self.peek().span
+    }
+
+    /// previous_span returns the span of the token last consumed.
+    fn previous_span(&self) -> Span {
+        match self.current {
+            0 => self.span(),
+            i => self.tokens[i - 1].span,
         }
     }
 
-    fn parse_expression(&mut self) -> Result<Node, String> {
-        Err(String::from("not implemented"))
+    /// unexpected reports the next token as unexpected. `what` describes
+    /// what should have been there and `expected` lists the tokens that
+    /// would have been accepted.
+    fn unexpected(&self, what: &str, expected: &[TokenType]) -> ParseError {
+        let found = self.peek_type();
+        let mut e = match (found, &self.peek().value) {
+            (TokenType::Illegal, TokenValue::Malformed(number)) => ParseError::new(
+                ErrorCode::InvalidLiteral,
+                self.span(),
+                format!("malformed number `{}`", number),
+            ),
+            (TokenType::Illegal, TokenValue::OutOfRange(number)) => ParseError::new(
+                ErrorCode::InvalidLiteral,
+                self.span(),
+                format!("number `{}` is out of range", number),
+            ),
+            (TokenType::End, _) => ParseError::new(
+                ErrorCode::UnexpectedEnd,
+                self.span(),
+                format!("expected {}, found {:?}", what, found),
+            ),
+            _ => ParseError::new(
+                ErrorCode::UnexpectedToken,
+                self.span(),
+                format!("expected {}, found {:?}", what, found),
+            ),
+        };
+        e.expected = expected.to_vec();
+        e.found = Some(found);
+        e
     }
 
-    fn parse_symbol(&mut self) -> Result<Node, String> {
-        // (tag:)ident([<expr>])
-        let token = self.expect_symbol()?;
+    fn parse_storage_class(&mut self) -> Result<(StorageClass, bool), ParseError> {
+        // new (const) | static (stock) (const) | stock (static) (const) | const
+        let storage = match (self.peek_type(), self.peek_type_at(1)) {
+            (TokenType::New, _) => StorageClass::New,
+            (TokenType::Const, _) => {
+                self.current += 1;
+                return Ok((StorageClass::Const, true));
+            }
+            (TokenType::Static, TokenType::Stock) | (TokenType::Stock, TokenType::Static) => {
+                self.current += 1;
+                StorageClass::StaticStock
+            }
+            (TokenType::Static, _) => StorageClass::Static,
+            (TokenType::Stock, _) => StorageClass::Stock,
+            _ => {
+                return Err(self.unexpected(
+                    "declaration",
+                    &[
+                        TokenType::New,
+                        TokenType::Static,
+                        TokenType::Stock,
+                        TokenType::Const,
+                    ],
+                ))
+            }
+        };
+        self.current += 1;
 
-        if self.peek().token_type == TokenType::Colon {
+        let constant = self.peek_type() == TokenType::Const;
+        if constant {
             self.current += 1;
+        }
 
-            let tag = &token;
-            let symbol = self.expect_symbol()?;
+        Ok((storage, constant))
+    }
 
-            return Ok(Node {
-                expr: Expression::Variable(Box::new(Variable {
-                    name: symbol.to_string(),
-                    tag: tag.to_string(),
-                })),
-                tokens: vec![],
-                children: vec![],
-            });
-        } else {
-            return Ok(Node {
-                expr: Expression::Variable(Box::new(Variable {
-                    name: token.to_string(),
-                    tag: String::from("_"),
-                })),
-                tokens: vec![],
-                children: vec![],
-            });
-        }
+    /// parse_declaration parses a declaration with one or more comma-separated
+    /// declarators, such as `new a, b = 2, Float:c;`.
+    fn parse_declaration(&mut self, local: bool) -> Result<Declaration, ParseError> {
+        let start = self.span();
+        let mut declaration = self.parse_declarators(local)?;
+        self.expect_end_of_statement()?;
+        declaration.span = start.to(self.previous_span());
+        Ok(declaration)
     }
 
-    fn parse_literal_scalar(&mut self) -> Result<Node, String> {
-        // (tag:)(+|-)literal
-        Err(String::from("not implemented"))
+    /// parse_declarators parses a declaration up to, but not including, its
+    /// terminating semicolon.
+    fn parse_declarators(&mut self, local: bool) -> Result<Declaration, ParseError> {
+        let start = self.span();
+        let (storage, constant) = self.parse_storage_class()?;
+
+        let scope = match (local, storage) {
+            (true, _) => Scope::Local,
+            (false, StorageClass::Static) | (false, StorageClass::StaticStock) => Scope::File,
+            (false, _) => Scope::Global,
+        };
+
+        let mut declarators = vec![];
+        loop {
+            declarators.push(self.parse_declarator(constant)?);
+
+            if self.peek_type() != TokenType::Comma {
+                break;
+            }
+            self.current += 1;
+        }
+
+        Ok(Declaration {
+            storage,
+            constant,
+            scope,
+            declarators,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn parse_declarator(&mut self, constant: bool) -> Result<Declarator, ParseError> {
+        // (tag:)name([dim])* (= initializer)
+        let start = self.span();
+        let tag = self.parse_tag()?;
+        let name = self.expect_symbol()?;
+        let dimensions = self.parse_dimensions()?;
+        let span = start.to(self.previous_span());
+
+        let mut declarator = Declarator {
+            name,
+            tag,
+            dimensions,
+            initializer: None,
+            span,
+        };
+        if self.peek_type() == TokenType::Assign {
+            self.current += 1;
+            declarator.initializer = Some(self.parse_initializer()?);
+            declarator.span = start.to(self.previous_span());
+            return Ok(declarator);
+        }
+
+        let message = if constant {
+            format!("constant `{}` must be initialized", declarator.name)
+        } else if declarator.dimensions.iter().any(|d| d.is_none()) {
+            format!(
+                "array `{}` has an unsized dimension and no initializer",
+                declarator.name
+            )
+        } else {
+            return Ok(declarator);
+        };
+        Err(ParseError::new(
+            ErrorCode::InvalidDeclaration,
+            span,
+            message,
+        ))
+    }
+
+    /// parse_tag parses an optional `Tag:` prefix.
+    fn parse_tag(&mut self) -> Result<Option<Tag>, ParseError> {
+        if self.peek_type() != TokenType::Symbol || self.peek_type_at(1) != TokenType::Colon {
+            return Ok(None);
+        }
+        let span = self.span();
+        let name = self.expect_symbol()?;
+        self.current += 1;
+        Ok(Some(Tag { name, span }))
+    }
+
+    fn parse_enum(&mut self) -> Result<EnumDecl, ParseError> {
+        // enum (tag:)(name) ((op value)) { member, ... } (;)
+        let start = self.span();
+        self.current += 1;
+
+        let tag = self.parse_tag()?;
+        let mut name = None;
+        if self.peek_type() == TokenType::Symbol {
+            name = Some(self.expect_symbol()?);
+        }
+
+        let increment = match self.peek_type() {
+            TokenType::LeftBracket => {
+                self.current += 1;
+                let operator = self.peek_type();
+                if !matches!(
+                    operator,
+                    TokenType::PlusAssign | TokenType::AsteriskAssign | TokenType::BitLeftAssign
+                ) {
+                    return Err(self.unexpected(
+                        "`+=`, `*=` or `<<=` in enum increment",
+                        &[
+                            TokenType::PlusAssign,
+                            TokenType::AsteriskAssign,
+                            TokenType::BitLeftAssign,
+                        ],
+                    ));
+                }
+                self.current += 1;
+                let value = self.parse_bracketed(TokenType::RightBracket)?;
+                EnumIncrement { operator, value }
+            }
+            _ => EnumIncrement {
+                operator: TokenType::PlusAssign,
+                value: Expr::Literal(Box::new(Literal {
+                    value: LiteralValue::Integer(1),
+                    tag: None,
+                    implicit_tag: false,
+                    sign: None,
+                    // not in the source, so it covers no text
+                    span: Span {
+                        start: self.previous_span().end,
+                        end: self.previous_span().end,
+                    },
+                })),
+            },
+        };
+
+        self.expect(TokenType::LeftBrace)?;
+        let mut members = vec![];
+        while self.peek_type() != TokenType::RightBrace {
+            members.push(self.parse_enum_member()?);
+            match self.peek_type() {
+                TokenType::Comma => self.current += 1,
+                TokenType::RightBrace => (),
+                _ => {
+                    return Err(
+                        self.unexpected("`,` or `}`", &[TokenType::Comma, TokenType::RightBrace])
+                    )
+                }
+            }
+        }
+        self.current += 1;
+        if self.peek_type() == TokenType::Semicolon {
+            self.current += 1;
+        }
+
+        let mut e = EnumDecl {
+            name,
+            tag,
+            increment,
+            members,
+            size: None,
+            span: start.to(self.previous_span()),
+        };
+        e.size = e.compute_offsets()?;
+
+        Ok(e)
+    }
+
+    fn parse_enum_member(&mut self) -> Result<EnumMember, ParseError> {
+        // (tag:)name([size]) (= value)
+        let start = self.span();
+        let tag = self.parse_tag()?;
+        let name = self.expect_symbol()?;
+
+        let mut size = None;
+        if self.peek_type() == TokenType::LeftSquare {
+            self.current += 1;
+            size = Some(self.parse_bracketed(TokenType::RightSquare)?);
+        }
+
+        let mut value = None;
+        if self.peek_type() == TokenType::Assign {
+            self.current += 1;
+            value = Some(self.parse_expression_bp(BP_ASSIGN)?);
+        }
+
+        Ok(EnumMember {
+            name,
+            tag,
+            size,
+            value,
+            offset: None,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// is_function_ahead looks past any qualifiers and return tag for
+    /// `name(`, which is what tells a function apart from a variable.
+    fn is_function_ahead(&self) -> bool {
+        let mut i = 0;
+        while matches!(
+            self.peek_type_at(i),
+            TokenType::Public
+                | TokenType::Static
+                | TokenType::Stock
+                | TokenType::Forward
+                | TokenType::Native
+        ) {
+            i += 1;
+        }
+        if self.peek_type_at(i) == TokenType::Symbol && self.peek_type_at(i + 1) == TokenType::Colon
+        {
+            i += 2;
+        }
+        self.peek_type_at(i) == TokenType::Operator
+            || self.peek_type_at(i) == TokenType::Symbol
+                && self.peek_type_at(i + 1) == TokenType::LeftBracket
+    }
+
+    fn parse_qualifiers(&mut self) -> Result<Vec<Qualifier>, ParseError> {
+        let start = self.span();
+        let mut qualifiers = vec![];
+        let mut spans = vec![];
+        loop {
+            let qualifier = match self.peek_type() {
+                TokenType::Public => Qualifier::Public,
+                TokenType::Static => Qualifier::Static,
+                TokenType::Stock => Qualifier::Stock,
+                TokenType::Forward => Qualifier::Forward,
+                TokenType::Native => Qualifier::Native,
+                _ => break,
+            };
+            if let Some(i) = qualifiers.iter().position(|q| *q == qualifier) {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidFunction,
+                    self.span(),
+                    format!("duplicate `{}`", self.peek_type()),
+                )
+                .with_related(spans[i], "first given here"));
+            }
+            spans.push(self.span());
+            self.current += 1;
+            qualifiers.push(qualifier);
+        }
+
+        // only static and stock combine, with each other
+        let alone = qualifiers.contains(&Qualifier::Forward)
+            || qualifiers.contains(&Qualifier::Native)
+            || qualifiers.contains(&Qualifier::Public);
+        if alone && qualifiers.len() > 1 {
+            return Err(ParseError::new(
+                ErrorCode::InvalidFunction,
+                start.to(self.previous_span()),
+                format!("invalid combination of qualifiers {:?}", qualifiers),
+            )
+            .with_note("only `static` and `stock` can be combined"));
+        }
+
+        Ok(qualifiers)
+    }
+
+    fn parse_function(&mut self) -> Result<Item, ParseError> {
+        // qualifiers (tag:)name(parameters) (= binding); | { body }
+        let start = self.span();
+        let qualifiers = self.parse_qualifiers()?;
+
+        let tag = self.parse_tag()?;
+        if self.peek_type() == TokenType::Operator {
+            let operator = self.parse_operator(start, qualifiers, tag)?;
+            return Ok(Item::Operator(Box::new(operator)));
+        }
+        let name = self.expect_symbol()?;
+
+        self.current += 1;
+        let parameters = self.parse_parameters()?;
+
+        let mut states = None;
+        if self.peek_type() == TokenType::LowerThan {
+            if qualifiers.contains(&Qualifier::Native) {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidFunction,
+                    self.span(),
+                    format!("native function `{}` cannot have states", name),
+                )
+                .with_note("states select between implementations, which a native doesn't have"));
+            }
+            states = Some(self.parse_state_spec()?);
+        }
+
+        let native = self.parse_native_binding(&qualifiers)?;
+        let body = self.parse_function_body(&qualifiers)?;
+
+        Ok(Item::Function(Box::new(FunctionDecl {
+            name,
+            tag,
+            qualifiers,
+            parameters,
+            native,
+            states,
+            body,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_operator(
+        &mut self,
+        start: Span,
+        qualifiers: Vec<Qualifier>,
+        tag: Option<Tag>,
+    ) -> Result<OperatorDecl, ParseError> {
+        // qualifiers (tag:)operator<op>(operands) (= binding); | { body }
+        let operator_start = self.span();
+        self.current += 1;
+        let operator = self.peek_type();
+        // `-` is both unary and binary, `=` is a tag conversion on assignment
+        // and `~` is a destructor, which takes an array and its size
+        let arity: &[usize] = match operator {
+            TokenType::Plus
+            | TokenType::Asterisk
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::LowerThan
+            | TokenType::GreaterThan
+            | TokenType::LowerThanEqual
+            | TokenType::GreaterThanEqual
+            | TokenType::Tilde => &[2],
+            TokenType::Minus => &[1, 2],
+            TokenType::PlusPlus | TokenType::MinusMinus | TokenType::Bang | TokenType::Assign => {
+                &[1]
+            }
+            token => {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidOperator,
+                    self.span(),
+                    format!("operator `{}` cannot be overloaded", token),
+                )
+                .with_note(
+                    "the operators that can be overloaded are \
+                     + - * / % ++ -- == != < > <= >= ! = and ~",
+                ))
+            }
+        };
+        self.current += 1;
+
+        self.expect(TokenType::LeftBracket)?;
+        let parameters = self.parse_parameters()?;
+        let span = operator_start.to(self.previous_span());
+        if !arity.contains(&parameters.len()) {
+            let expected = match arity {
+                [1] => String::from("1 operand"),
+                [2] => String::from("2 operands"),
+                _ => String::from("1 or 2 operands"),
+            };
+            return Err(ParseError::new(
+                ErrorCode::InvalidOperator,
+                span,
+                format!(
+                    "operator `{}` takes {}, found {}",
+                    operator,
+                    expected,
+                    parameters.len()
+                ),
+            ));
+        }
+
+        if operator == TokenType::Tilde {
+            // the destructor is called with the variable and its cell count
+            let valid = matches!(&parameters[..], [array, count]
+                if array.dimensions.len() == 1
+                    && matches!(&array.tags[..], [tag] if tag.name != "_")
+                    && !array.variadic
+                    && array.default.is_none()
+                    && count.dimensions.is_empty()
+                    && count.tags.iter().all(|t| t.name == "_")
+                    && !count.reference
+                    && !count.variadic
+                    && count.default.is_none());
+            if !valid {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidOperator,
+                    span,
+                    String::from(
+                        "operator `~` takes a tagged array and an untagged count, \
+                         as in `operator~(Tag:values[], count)`",
+                    ),
+                ));
+            }
+        } else if let Some(p) = parameters.iter().find(|p| {
+            p.reference
+                || p.variadic
+                || !p.dimensions.is_empty()
+                || p.default.is_some()
+                || p.tags.len() > 1
+        }) {
+            return Err(ParseError::new(
+                ErrorCode::InvalidOperator,
+                span,
+                format!(
+                    "operand `{}` of operator `{}` must be a single-tagged value without a default",
+                    p.name, operator
+                ),
+            ));
+        }
+        let operands = OperatorDecl::tags_of(&parameters);
+        if tag.is_none() && operands.iter().all(|t| *t == "_") {
+            return Err(ParseError::new(
+                ErrorCode::InvalidOperator,
+                span,
+                format!(
+                    "operator `{}` must have a tagged operand or result",
+                    operator
+                ),
+            ));
+        }
+
+        let native = self.parse_native_binding(&qualifiers)?;
+        let body = self.parse_function_body(&qualifiers)?;
+
+        Ok(OperatorDecl {
+            operator,
+            tag,
+            qualifiers,
+            parameters,
+            native,
+            body,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// parse_native_binding parses the `= index` or `= name` after a native's
+    /// parameter list, if there is one.
+    fn parse_native_binding(
+        &mut self,
+        qualifiers: &[Qualifier],
+    ) -> Result<Option<NativeBinding>, ParseError> {
+        if !qualifiers.contains(&Qualifier::Native) || self.peek_type() != TokenType::Assign {
+            return Ok(None);
+        }
+        self.current += 1;
+        let negative = self.peek_type() == TokenType::Minus;
+        if negative {
+            self.current += 1;
+        }
+
+        let index = self.peek_integer_at(0, negative);
+        let binding = match (index, self.peek_type(), &self.peek().value) {
+            (Some(v), _, _) => NativeBinding::Index(signed(v, negative)),
+            _ if negative => return Err(self.unexpected("number after `-`", &[TokenType::Integer])),
+            (_, TokenType::Symbol, TokenValue::Name(name)) => NativeBinding::Name(name.clone()),
+            _ => {
+                return Err(self.unexpected(
+                    "native index or name",
+                    &[TokenType::Integer, TokenType::Symbol],
+                ))
+            }
+        };
+        self.current += 1;
+
+        Ok(Some(binding))
+    }
+
+    /// parse_function_body parses a definition's body, or the end of a
+    /// `forward` or `native` prototype, which has none. A body can be any
+    /// single statement, as in `main() return 1;`, which is kept as a block
+    /// holding that statement.
+    fn parse_function_body(
+        &mut self,
+        qualifiers: &[Qualifier],
+    ) -> Result<Option<Block>, ParseError> {
+        if qualifiers.contains(&Qualifier::Forward) || qualifiers.contains(&Qualifier::Native) {
+            self.expect_end_of_statement()?;
+            return Ok(None);
+        }
+        match self.peek_type() {
+            TokenType::LeftBrace => Ok(Some(self.parse_block()?)),
+            t if t == TokenType::Semicolon || t == TokenType::End || is_item_keyword(t) => {
+                Err(self.unexpected("function body", &[TokenType::LeftBrace]))
+            }
+            _ => {
+                let statement = self.parse_statement()?;
+                Ok(Some(Block {
+                    span: statement.span(),
+                    statements: vec![statement],
+                }))
+            }
+        }
+    }
+
+    fn parse_state_spec(&mut self) -> Result<StateSpec, ParseError> {
+        // <(automaton:)(state, ...)>
+        let start = self.span();
+        self.current += 1;
+        let mut automaton = None;
+        if self.peek_type() == TokenType::Symbol && self.peek_type_at(1) == TokenType::Colon {
+            automaton = Some(self.expect_symbol()?);
+            self.current += 1;
+        }
+
+        let mut states = vec![];
+        if self.peek_type() != TokenType::GreaterThan {
+            loop {
+                states.push(self.expect_symbol()?);
+                match self.peek_type() {
+                    TokenType::Comma => self.current += 1,
+                    TokenType::GreaterThan => break,
+                    _ => {
+                        return Err(self
+                            .unexpected("`,` or `>`", &[TokenType::Comma, TokenType::GreaterThan]))
+                    }
+                }
+            }
+        }
+        self.current += 1;
+
+        Ok(StateSpec {
+            automaton,
+            states,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn parse_parameters(&mut self) -> Result<Vec<Parameter>, ParseError> {
+        let mut parameters = vec![];
+        if self.peek_type() == TokenType::RightBracket {
+            self.current += 1;
+            return Ok(parameters);
+        }
+
+        loop {
+            let start = self.span();
+            let parameter = self.parse_parameter()?;
+            let variadic = parameter.variadic;
+            parameters.push(parameter);
+
+            match self.peek_type() {
+                TokenType::Comma if variadic => {
+                    return Err(ParseError::new(
+                        ErrorCode::InvalidFunction,
+                        start,
+                        String::from("`...` must be the last parameter"),
+                    ))
+                }
+                TokenType::Comma => self.current += 1,
+                TokenType::RightBracket => break,
+                _ => {
+                    return Err(
+                        self.unexpected("`,` or `)`", &[TokenType::Comma, TokenType::RightBracket])
+                    )
+                }
+            }
+        }
+        self.current += 1;
+
+        Ok(parameters)
+    }
+
+    fn parse_parameter(&mut self) -> Result<Parameter, ParseError> {
+        // (const) (&) (tags:) name([dim]) (= default) | (tags:) ...
+        let start = self.span();
+        let constant = self.peek_type() == TokenType::Const;
+        if constant {
+            self.current += 1;
+        }
+        let reference = self.peek_type() == TokenType::BitAnd;
+        if reference {
+            self.current += 1;
+        }
+        let tags = self.parse_tags()?;
+
+        if self.peek_type() == TokenType::Elipsis {
+            self.current += 1;
+            return Ok(Parameter {
+                name: Name::default(),
+                tags,
+                reference,
+                constant,
+                dimensions: vec![],
+                default: None,
+                variadic: true,
+                span: start.to(self.previous_span()),
+            });
+        }
+
+        let name_span = self.span();
+        let name = self.expect_symbol()?;
+        let dimensions = self.parse_dimensions()?;
+        if reference && !dimensions.is_empty() {
+            return Err(ParseError::new(
+                ErrorCode::InvalidFunction,
+                name_span,
+                format!("array parameter `{}` is always passed by reference", name),
+            ));
+        }
+
+        let mut default = None;
+        if self.peek_type() == TokenType::Assign {
+            self.current += 1;
+            default = Some(self.parse_initializer()?);
+        }
+
+        Ok(Parameter {
+            name,
+            tags,
+            reference,
+            constant,
+            dimensions,
+            default,
+            variadic: false,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// parse_tags parses an optional `Tag:` or `{Tag, Tag}:` prefix.
+    fn parse_tags(&mut self) -> Result<Vec<Tag>, ParseError> {
+        match (self.peek_type(), self.peek_type_at(1)) {
+            (TokenType::Symbol, TokenType::Colon) => Ok(self.parse_tag()?.into_iter().collect()),
+            (TokenType::LeftBrace, _) => {
+                self.current += 1;
+                let mut tags = vec![];
+                loop {
+                    let span = self.span();
+                    let name = self.expect_symbol()?;
+                    tags.push(Tag { name, span });
+                    if self.peek_type() != TokenType::Comma {
+                        break;
+                    }
+                    self.current += 1;
+                }
+                if self.peek_type() != TokenType::RightBrace {
+                    return Err(self.unexpected("`}:` after tag list", &[TokenType::RightBrace]));
+                }
+                self.current += 1;
+                if self.peek_type() != TokenType::Colon {
+                    return Err(self.unexpected("`}:` after tag list", &[TokenType::Colon]));
+                }
+                self.current += 1;
+                Ok(tags)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// parse_block parses a block of statements. An error in a statement is
+    /// recorded and the block carries on from the next statement, so a
+    /// function keeps whatever of its body could be parsed.
+    fn parse_block(&mut self) -> Result<Block, ParseError> {
+        // { statement* }
+        let open = self.span();
+        self.expect(TokenType::LeftBrace)?;
+
+        let mut statements = vec![];
+        loop {
+            match self.peek_type() {
+                TokenType::RightBrace => break,
+                t if t == TokenType::End || is_item_keyword(t) => {
+                    let mut e = self.unexpected("`}`", &[TokenType::RightBrace]);
+                    e.code = ErrorCode::UnterminatedBlock;
+                    e.message = String::from("unterminated block");
+                    self.errors.push(e.with_related(open, "block opened here"));
+                    return Ok(Block {
+                        statements,
+                        span: open.to(self.previous_span()),
+                    });
+                }
+                _ => {
+                    let start = self.current;
+                    match self.parse_statement() {
+                        Ok(statement) => statements.push(statement),
+                        Err(e) => {
+                            self.errors.push(e);
+                            self.synchronize_statement(start);
+                        }
+                    }
+                }
+            }
+        }
+        self.current += 1;
+
+        Ok(Block {
+            statements,
+            span: open.to(self.previous_span()),
+        })
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.span();
+        let statement = match self.peek_type() {
+            TokenType::LeftBrace => Stmt::Block(self.parse_block()?),
+            TokenType::Semicolon => {
+                self.current += 1;
+                Stmt::Empty(start)
+            }
+            TokenType::New | TokenType::Static | TokenType::Const => {
+                Stmt::Declaration(self.parse_declaration(true)?)
+            }
+            TokenType::If => self.parse_if()?,
+            TokenType::While => self.parse_while()?,
+            TokenType::Do => self.parse_do_while()?,
+            TokenType::For => self.parse_for()?,
+            TokenType::Switch => self.parse_switch()?,
+            TokenType::Break => {
+                self.current += 1;
+                self.expect_end_of_statement()?;
+                Stmt::Break(start.to(self.previous_span()))
+            }
+            TokenType::Continue => {
+                self.current += 1;
+                self.expect_end_of_statement()?;
+                Stmt::Continue(start.to(self.previous_span()))
+            }
+            TokenType::Return => {
+                self.current += 1;
+                let value = self.parse_optional_value()?;
+                let span = start.to(self.previous_span());
+                Stmt::Return(Return { value, span })
+            }
+            TokenType::Exit => {
+                self.current += 1;
+                let value = self.parse_optional_value()?;
+                let span = start.to(self.previous_span());
+                Stmt::Exit(Exit { value, span })
+            }
+            TokenType::Sleep => {
+                self.current += 1;
+                let value = self.parse_optional_value()?;
+                let span = start.to(self.previous_span());
+                Stmt::Sleep(Sleep { value, span })
+            }
+            TokenType::Assert => {
+                self.current += 1;
+                let condition = self.parse_expression()?;
+                self.expect_end_of_statement()?;
+                let span = start.to(self.previous_span());
+                Stmt::Assert(Assert { condition, span })
+            }
+            TokenType::State => self.parse_state()?,
+            TokenType::Goto => {
+                self.current += 1;
+                let label = self.expect_symbol()?;
+                self.expect_end_of_statement()?;
+                let span = start.to(self.previous_span());
+                Stmt::Goto(Goto { label, span })
+            }
+            // as in pawncc, `name:` at the start of a statement is always a
+            // label, never a tag override
+            TokenType::Symbol if self.peek_type_at(1) == TokenType::Colon => {
+                let name = self.expect_symbol()?;
+                self.current += 1;
+                let span = start.to(self.previous_span());
+                Stmt::Label(Label { name, span })
+            }
+            TokenType::Directive => match self.parse_directive()? {
+                Some(emit) => Stmt::Emit(emit),
+                None => Stmt::Empty(start.to(self.previous_span())),
+            },
+            TokenType::Emit => Stmt::Emit(self.parse_inline_emit()?),
+            _ => {
+                let expr = self.parse_expression()?;
+                self.expect_end_of_statement()?;
+                Stmt::Expr(expr)
+            }
+        };
+        Ok(statement)
+    }
+
+    /// parse_optional_value parses the operand of `return`, `exit` and
+    /// `sleep`, up to and including the semicolon.
+    fn parse_optional_value(&mut self) -> Result<Option<Expr>, ParseError> {
+        let value = match self.at_end_of_statement() {
+            true => None,
+            false => Some(self.parse_expression()?),
+        };
+        self.expect_end_of_statement()?;
+        Ok(value)
+    }
+
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        self.expect(TokenType::LeftBracket)?;
+        self.parse_bracketed(TokenType::RightBracket)
+    }
+
+    fn parse_state(&mut self) -> Result<Stmt, ParseError> {
+        // state ((cond)) (automaton:)name;
+        let start = self.span();
+        self.current += 1;
+        let mut condition = None;
+        if self.peek_type() == TokenType::LeftBracket {
+            condition = Some(self.parse_condition()?);
+        }
+
+        let token = self.expect_symbol()?;
+        let (automaton, name) = if self.peek_type() == TokenType::Colon {
+            self.current += 1;
+            (Some(token), self.expect_symbol()?)
+        } else {
+            (None, token)
+        };
+        self.expect_end_of_statement()?;
+
+        Ok(Stmt::State(State {
+            automaton,
+            name,
+            condition,
+            span: start.to(self.previous_span()),
+        }))
+    }
+
+    fn parse_if(&mut self) -> Result<Stmt, ParseError> {
+        // if (cond) statement (else statement)
+        let start = self.span();
+        self.current += 1;
+        let condition = self.parse_condition()?;
+        let then = self.parse_statement()?;
+
+        let mut otherwise = None;
+        if self.peek_type() == TokenType::Else {
+            self.current += 1;
+            otherwise = Some(self.parse_statement()?);
+        }
+
+        Ok(Stmt::If(Box::new(If {
+            condition,
+            then,
+            otherwise,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_while(&mut self) -> Result<Stmt, ParseError> {
+        // while (cond) statement
+        let start = self.span();
+        self.current += 1;
+        let condition = self.parse_condition()?;
+        let body = self.parse_statement()?;
+
+        Ok(Stmt::While(Box::new(While {
+            condition,
+            body,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_do_while(&mut self) -> Result<Stmt, ParseError> {
+        // do statement while (cond);
+        let start = self.span();
+        self.current += 1;
+        let body = self.parse_statement()?;
+        self.expect(TokenType::While)?;
+        let condition = self.parse_condition()?;
+        self.expect_end_of_statement()?;
+
+        Ok(Stmt::DoWhile(Box::new(DoWhile {
+            body,
+            condition,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_for(&mut self) -> Result<Stmt, ParseError> {
+        // for (init; cond; increment) statement
+        let start = self.span();
+        self.current += 1;
+        self.expect(TokenType::LeftBracket)?;
+
+        let init = match self.peek_type() {
+            TokenType::Semicolon => {
+                self.current += 1;
+                None
+            }
+            TokenType::New | TokenType::Static => {
+                let mut declaration = self.parse_declarators(true)?;
+                self.expect_semicolon()?;
+                declaration.span = declaration.span.to(self.previous_span());
+                Some(Stmt::Declaration(declaration))
+            }
+            _ => {
+                let expr = self.parse_expression()?;
+                self.expect_semicolon()?;
+                Some(Stmt::Expr(expr))
+            }
+        };
+
+        let condition = match self.peek_type() {
+            TokenType::Semicolon => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.expect_semicolon()?;
+
+        let increment = match self.peek_type() {
+            TokenType::RightBracket => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.expect(TokenType::RightBracket)?;
+
+        let body = self.parse_statement()?;
+
+        Ok(Stmt::For(Box::new(For {
+            init,
+            condition,
+            increment,
+            body,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_switch(&mut self) -> Result<Stmt, ParseError> {
+        // switch (value) { (case values: statement)* (default: statement) }
+        let start = self.span();
+        self.current += 1;
+        let value = self.parse_condition()?;
+        self.expect(TokenType::LeftBrace)?;
+
+        let mut cases = vec![];
+        let mut default = None;
+        let mut default_span = None;
+        loop {
+            match self.peek_type() {
+                TokenType::Case => {
+                    let case_start = self.span();
+                    self.current += 1;
+                    let values = self.parse_case_values()?;
+                    let body = self.parse_statement()?;
+                    let span = case_start.to(self.previous_span());
+                    cases.push(Case { values, body, span });
+                }
+                TokenType::Default => {
+                    // the switch is still well-formed, so carry on parsing it
+                    if let Some(first) = default_span {
+                        let e = ParseError::new(
+                            ErrorCode::InvalidStatement,
+                            self.span(),
+                            String::from("multiple `default` cases in switch"),
+                        );
+                        self.errors
+                            .push(e.with_related(first, "first `default` here"));
+                    }
+                    default_span = Some(self.span());
+                    self.current += 1;
+                    self.expect(TokenType::Colon)?;
+                    default = Some(self.parse_statement()?);
+                }
+                TokenType::RightBrace => {
+                    self.current += 1;
+                    break;
+                }
+                TokenType::End => {
+                    return Err(self.unexpected(
+                        "`case`",
+                        &[TokenType::Case, TokenType::Default, TokenType::RightBrace],
+                    ))
+                }
+                // a stray statement doesn't end the switch, so skip it
+                _ => {
+                    let e = self.unexpected(
+                        "`case`",
+                        &[TokenType::Case, TokenType::Default, TokenType::RightBrace],
+                    );
+                    self.errors.push(e);
+                    let start = self.current;
+                    self.synchronize_statement(start);
+                }
+            }
+        }
+
+        Ok(Stmt::Switch(Box::new(Switch {
+            value,
+            cases,
+            default,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn parse_case_values(&mut self) -> Result<Vec<CaseValue>, ParseError> {
+        // value(..value) (, value(..value))* :
+        // tags are disabled so the closing `:` isn't read as a tag override
+        let allow_tags = self.allow_tags;
+        self.allow_tags = false;
+        let values = self.parse_case_value_list();
+        self.allow_tags = allow_tags;
+        let values = values?;
+
+        self.expect(TokenType::Colon)?;
+        Ok(values)
+    }
+
+    fn parse_case_value_list(&mut self) -> Result<Vec<CaseValue>, ParseError> {
+        let mut values = vec![];
+        loop {
+            let low = self.parse_expression_bp(BP_ASSIGN)?;
+            if self.peek_type() == TokenType::Range {
+                self.current += 1;
+                let high = self.parse_expression_bp(BP_ASSIGN)?;
+                values.push(CaseValue::Range(low, high));
+            } else {
+                values.push(CaseValue::Value(low));
+            }
+
+            if self.peek_type() != TokenType::Comma {
+                break;
+            }
+            self.current += 1;
+        }
+        Ok(values)
+    }
+
+    /// parse_directive parses an `#emit` instruction and skips any other
+    /// directive, which is left to the preprocessor.
+    fn parse_directive(&mut self) -> Result<Option<Emit>, ParseError> {
+        let start = self.span();
+        self.next();
+
+        let token = self.peek();
+        if token.token_type == TokenType::Symbol
+            && matches!(&token.value, TokenValue::Name(name) if *name == "emit")
+        {
+            self.current += 1;
+            let line = start.start.line;
+            let instruction = self.parse_emit_instruction(Some(line))?;
+            return Ok(Some(Emit {
+                instructions: vec![instruction],
+                span: start.to(self.previous_span()),
+            }));
+        }
+
+        // `#pragma semicolon` is the one pragma that changes the grammar
+        let line = self.tokens[self.current - 1].span.start.line;
+        if self.is_symbol_at(0, "pragma") && self.is_symbol_at(1, "semicolon") {
+            if let Some(token) = self.tokens.get(self.current + 2) {
+                if let (TokenType::Integer, TokenValue::Integer(v)) =
+                    (token.token_type, &token.value)
+                {
+                    if token.span.start.line == line {
+                        self.semicolons_required = *v != 0;
+                    }
+                }
+            }
+        }
+
+        // other directives are left to the preprocessor, so skip the rest of
+        // the line
+        while self.current < self.tokens.len() && self.peek().span.start.line == line {
+            self.current += 1;
+        }
+
+        Ok(None)
+    }
+
+    fn parse_inline_emit(&mut self) -> Result<Emit, ParseError> {
+        // __emit instr | __emit(instr, instr, ...)
+        let start = self.span();
+        self.next();
+
+        let mut instructions = vec![];
+        if self.peek_type() == TokenType::LeftBracket {
+            self.current += 1;
+            loop {
+                instructions.push(self.parse_emit_instruction(None)?);
+                match self.peek_type() {
+                    TokenType::Comma => self.current += 1,
+                    TokenType::RightBracket => break,
+                    _ => {
+                        return Err(self.unexpected(
+                            "`,` or `)`",
+                            &[TokenType::Comma, TokenType::RightBracket],
+                        ))
+                    }
+                }
+            }
+            self.current += 1;
+        } else {
+            instructions.push(self.parse_emit_instruction(None)?);
+        }
+
+        if self.peek_type() == TokenType::Semicolon {
+            self.current += 1;
+        }
+
+        Ok(Emit {
+            instructions,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// parse_emit_instruction parses one instruction. An `#emit` directive
+    /// passes its `line`, since the instruction ends with the line.
+    fn parse_emit_instruction(&mut self, line: Option<i32>) -> Result<EmitInstruction, ParseError> {
+        // mnemonic(.part)* operand*
+        let start = self.span();
+        let mut mnemonic = self.expect_word()?;
+        // a `.` continues the mnemonic only when a part follows it directly,
+        // as a `.` operand can follow a mnemonic that is also a prefix
+        while self.current + 1 < self.tokens.len()
+            && self.peek_type() == TokenType::Dot
+            && self.tokens[self.current + 1].span.start == self.peek().span.end
+            && (self.peek_type_at(1) == TokenType::Symbol || self.peek_type_at(1).is_keyword())
+            && self.on_line(line)
+            && opcode::is_mnemonic_prefix(&mnemonic)
+        {
+            self.current += 1;
+            mnemonic = format!("{}.{}", mnemonic, self.expect_word()?);
+        }
+
+        let opcode = match opcode::lookup_opcode(&mnemonic) {
+            Some(opcode) => opcode,
+            None => {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidEmit,
+                    start.to(self.previous_span()),
+                    format!("unknown opcode `{}`", mnemonic),
+                ))
+            }
+        };
+
+        let mut operands = vec![];
+        for kind in opcode.operands() {
+            if !self.on_line(line) {
+                return Err(ParseError::new(
+                    ErrorCode::InvalidEmit,
+                    start.to(self.previous_span()),
+                    format!(
+                        "opcode `{}` expects {:?} operand, found end of line",
+                        opcode.mnemonic(),
+                        kind
+                    ),
+                ));
+            }
+            operands.push(self.parse_emit_operand(opcode, *kind)?);
+        }
+
+        Ok(EmitInstruction {
+            opcode,
+            operands,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn parse_emit_operand(
+        &mut self,
+        opcode: Opcode,
+        kind: OperandKind,
+    ) -> Result<EmitOperand, ParseError> {
+        let start = self.span();
+        let relative = self.peek_type() == TokenType::Dot;
+        if relative {
+            self.current += 1;
+            if !matches!(self.peek_type(), TokenType::Plus | TokenType::Minus)
+                || self.peek().span.start.line != start.start.line
+            {
+                return self.emit_operand(opcode, kind, start, EmitOperand::Relative(0));
+            }
+        }
+        let negative = self.peek_type() == TokenType::Minus;
+        if negative || relative {
+            self.current += 1;
+        }
+
+        let number = self.peek_integer_at(0, negative);
+        let operand = match (number, self.peek_type(), &self.peek().value) {
+            (Some(v), _, _) => {
+                let v = signed(v, negative);
+                if relative {
+                    EmitOperand::Relative(v)
+                } else {
+                    EmitOperand::Number(v)
+                }
+            }
+            _ if negative || relative => {
+                return Err(self.unexpected("number", &[TokenType::Integer]))
+            }
+            (_, TokenType::Symbol, TokenValue::Name(name)) => {
+                if kind == OperandKind::Label {
+                    EmitOperand::Label(name.clone())
+                } else {
+                    EmitOperand::Symbol(Symbol {
+                        name: name.clone(),
+                        span: self.span(),
+                    })
+                }
+            }
+            _ => {
+                return Err(self.unexpected(
+                    &format!("{:?} operand", kind),
+                    &[TokenType::Integer, TokenType::Symbol],
+                ))
+            }
+        };
+        self.current += 1;
+        self.emit_operand(opcode, kind, start, operand)
+    }
+
+    /// emit_operand checks that `operand` is of a kind `opcode` accepts.
+    fn emit_operand(
+        &self,
+        opcode: Opcode,
+        kind: OperandKind,
+        start: Span,
+        operand: EmitOperand,
+    ) -> Result<EmitOperand, ParseError> {
+        let valid = matches!(
+            (&operand, kind),
+            (
+                EmitOperand::Number(_),
+                OperandKind::Number | OperandKind::Address | OperandKind::Offset
+            ) | (
+                EmitOperand::Symbol(_),
+                OperandKind::Address | OperandKind::Offset | OperandKind::Function
+            ) | (EmitOperand::Label(_), OperandKind::Label)
+                | (
+                    EmitOperand::Relative(_),
+                    OperandKind::Number | OperandKind::Label
+                )
+        );
+        if !valid {
+            let found = match &operand {
+                EmitOperand::Symbol(symbol) => format!("Symbol({:?})", symbol.name),
+                operand => format!("{:?}", operand),
+            };
+            return Err(ParseError::new(
+                ErrorCode::InvalidEmit,
+                start.to(self.previous_span()),
+                format!(
+                    "opcode `{}` expects {:?} operand, found {}",
+                    opcode.mnemonic(),
+                    kind,
+                    found
+                ),
+            ));
+        }
+
+        Ok(operand)
+    }
+
+    /// on_line reports whether the next token is on `line`. Without a line,
+    /// as in `__emit`, any token is.
+    fn on_line(&self, line: Option<i32>) -> bool {
+        match line {
+            Some(line) => self.current < self.tokens.len() && self.peek().span.start.line == line,
+            None => true,
+        }
+    }
+
+    fn expect_symbol(&mut self) -> Result<Name, ParseError> {
+        match (self.peek_type(), &self.peek().value) {
+            (TokenType::Symbol, TokenValue::Name(name)) => {
+                let name = name.clone();
+                self.current += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected("symbol", &[TokenType::Symbol])),
+        }
+    }
+
+    /// expect_word accepts a symbol or a keyword, since mnemonics such as
+    /// `const.pri` and `break` collide with keywords.
+    fn expect_word(&mut self) -> Result<String, ParseError> {
+        let word = match (self.peek_type(), &self.peek().value) {
+            (TokenType::Symbol, TokenValue::Name(word)) => word.to_string(),
+            (keyword, _) if keyword.is_keyword() => keyword.to_string(),
+            _ => return Err(self.unexpected("opcode", &[TokenType::Symbol])),
+        };
+        self.current += 1;
+        Ok(word)
+    }
+
+    fn expect(&mut self, expected: TokenType) -> Result<(), ParseError> {
+        if self.peek_type() != expected {
+            return Err(self.unexpected(&format!("`{}`", expected), &[expected]));
+        }
+        self.current += 1;
+        Ok(())
+    }
+
+    /// at_end_of_statement reports whether the next token ends a statement:
+    /// a semicolon or, when semicolons are optional, the end of the line or
+    /// of the input.
+    fn at_end_of_statement(&self) -> bool {
+        match self.peek_type() {
+            TokenType::Semicolon => true,
+            _ if self.semicolons_required => false,
+            TokenType::End => true,
+            _ => self.newline_before(),
+        }
+    }
+
+    /// expect_end_of_statement consumes the semicolon that ends a statement,
+    /// which may be left out at the end of a line unless semicolons are
+    /// required.
+    fn expect_end_of_statement(&mut self) -> Result<(), ParseError> {
+        if !self.at_end_of_statement() {
+            return Err(self.unexpected("semicolon", &[TokenType::Semicolon]));
+        }
+        if self.peek_type() == TokenType::Semicolon {
+            self.current += 1;
+        }
+        Ok(())
+    }
+
+    /// newline_before reports whether the next token starts on a later line
+    /// than the one before it ends.
+    fn newline_before(&self) -> bool {
+        match self.current {
+            0 => false,
+            i => self.peek().span.start.line > self.tokens[i - 1].span.end.line,
+        }
+    }
+
+    fn expect_semicolon(&mut self) -> Result<TokenType, ParseError> {
+        if self.peek_type() != TokenType::Semicolon {
+            return Err(self.unexpected("semicolon", &[TokenType::Semicolon]));
+        }
+        self.current += 1;
+        Ok(TokenType::Semicolon)
+    }
+
+    /// parse_expression parses a full expression, including the comma
+    /// operator.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_expression_bp(0)
+    }
+
+    fn parse_expression_bp(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+        let mut left = self.parse_prefix()?;
+
+        loop {
+            let (expr, applied) = self.parse_postfix(left, min_bp)?;
+            left = expr;
+            if applied {
+                continue;
+            }
+
+            let t = self.peek_type();
+            let (l_bp, r_bp) = match infix_binding_power(t) {
+                Some(bp) => bp,
+                None => break,
+            };
+            if l_bp < min_bp {
+                break;
+            }
+            self.current += 1;
+
+            left = if t == TokenType::Question {
+                // tags are disabled in the middle operand so that the `:`
+                // isn't mistaken for a tag override, as pawncc does
+                let allow_tags = self.allow_tags;
+                self.allow_tags = false;
+                let then = self.parse_expression_bp(BP_ASSIGN);
+                self.allow_tags = allow_tags;
+                let then = then?;
+
+                self.expect(TokenType::Colon)?;
+                let otherwise = self.parse_expression_bp(r_bp)?;
+
+                Expr::TernaryOperation(Box::new(TernaryOperation {
+                    span: left.span().to(otherwise.span()),
+                    condition: left,
+                    then,
+                    otherwise,
+                }))
+            } else {
+                let right = self.parse_expression_bp(r_bp)?;
+                Expr::BinaryOperation(Box::new(BinaryOperation {
+                    operator: t,
+                    span: left.span().to(right.span()),
+                    left,
+                    right,
+                }))
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expr, ParseError> {
+        if self.is_literal_scalar() {
+            return self.parse_literal_scalar();
+        }
+
+        let start = self.span();
+        match self.peek_type() {
+            TokenType::Minus
+            | TokenType::Bang
+            | TokenType::Tilde
+            | TokenType::PlusPlus
+            | TokenType::MinusMinus => {
+                let operator = self.next().token_type;
+                let operand = self.parse_expression_bp(BP_PREFIX)?;
+                Ok(Expr::UnaryOperation(Box::new(UnaryOperation {
+                    operator,
+                    span: start.to(operand.span()),
+                    operand,
+                })))
+            }
+            TokenType::Sizeof => {
+                self.current += 1;
+                let (symbol, dimensions) = self.parse_name_operand(|p| {
+                    let symbol = p.parse_symbol()?;
+                    Ok((symbol, p.parse_dimensions()?))
+                })?;
+                Ok(Expr::Sizeof(Box::new(Sizeof {
+                    symbol,
+                    dimensions,
+                    span: start.to(self.previous_span()),
+                })))
+            }
+            TokenType::Tagof => {
+                self.current += 1;
+                let (operand, dimensions) = self.parse_name_operand(|p| {
+                    let tag = if p.allow_tags { p.parse_tag()? } else { None };
+                    match tag {
+                        Some(tag) => Ok((TagofOperand::Tag(tag), vec![])),
+                        None => {
+                            let symbol = p.parse_symbol()?;
+                            Ok((TagofOperand::Symbol(symbol), p.parse_dimensions()?))
+                        }
+                    }
+                })?;
+                Ok(Expr::Tagof(Box::new(Tagof {
+                    operand,
+                    dimensions,
+                    span: start.to(self.previous_span()),
+                })))
+            }
+            TokenType::Defined | TokenType::Nameof | TokenType::Addressof => {
+                let operator = self.next().token_type;
+                let symbol = self.parse_name_operand(|p| p.parse_symbol())?;
+                let span = start.to(self.previous_span());
+                Ok(match operator {
+                    TokenType::Defined => Expr::Defined(Box::new(Defined { symbol, span })),
+                    TokenType::Nameof => Expr::Nameof(Box::new(Nameof { symbol, span })),
+                    _ => Expr::Addressof(Box::new(Addressof { symbol, span })),
+                })
+            }
+            TokenType::Symbol if self.allow_tags && self.peek_type_at(1) == TokenType::Colon => {
+                let tag = self.parse_tag()?.unwrap();
+                let operand = self.parse_expression_bp(BP_PREFIX)?;
+                Ok(Expr::TagOverride(Box::new(TagOverride {
+                    tag,
+                    span: start.to(operand.span()),
+                    operand,
+                })))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    /// parse_postfix applies a single postfix operator to `left` if one
+    /// follows, returning whether it did.
+    fn parse_postfix(&mut self, operand: Expr, min_bp: u8) -> Result<(Expr, bool), ParseError> {
+        if BP_POSTFIX < min_bp {
+            return Ok((operand, false));
+        }
+        let start = operand.span();
+
+        let expr = match self.peek_type() {
+            // without semicolons, `a\n++b` and `a\n(b)` are two statements
+            TokenType::PlusPlus | TokenType::MinusMinus | TokenType::LeftBracket
+                if !self.semicolons_required && self.newline_before() =>
+            {
+                return Ok((operand, false))
+            }
+            TokenType::PlusPlus | TokenType::MinusMinus => {
+                let operator = self.next().token_type;
+                Expr::PostfixOperation(Box::new(PostfixOperation {
+                    operator,
+                    operand,
+                    span: start.to(self.previous_span()),
+                }))
+            }
+            TokenType::Char => {
+                self.current += 1;
+                Expr::Char(Box::new(Char {
+                    operand,
+                    span: start.to(self.previous_span()),
+                }))
+            }
+            TokenType::LeftBracket if matches!(operand, Expr::Symbol(_)) => {
+                self.current += 1;
+                let function = match operand {
+                    Expr::Symbol(s) => *s,
+                    _ => unreachable!(),
+                };
+                let arguments = self.parse_arguments()?;
+                Expr::Call(Box::new(Call {
+                    function,
+                    arguments,
+                    span: start.to(self.previous_span()),
+                }))
+            }
+            TokenType::LeftSquare => {
+                self.current += 1;
+                let index = self.parse_bracketed(TokenType::RightSquare)?;
+                Expr::ArrayIndex(Box::new(ArrayIndex {
+                    array: operand,
+                    index,
+                    span: start.to(self.previous_span()),
+                }))
+            }
+            _ => return Ok((operand, false)),
+        };
+
+        Ok((expr, true))
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
+        let mut arguments: Vec<Argument> = vec![];
+        if self.peek_type() == TokenType::RightBracket {
+            self.current += 1;
+            return Ok(arguments);
+        }
+
+        let allow_tags = self.allow_tags;
+        self.allow_tags = true;
+        let mut named = None;
+        let result = loop {
+            let start = self.span();
+            let argument = match self.parse_argument() {
+                Ok(argument) => argument,
+                Err(e) => break Err(e),
+            };
+            let span = start.to(self.previous_span());
+            match (&argument.name, named) {
+                (Some(_), None) => named = Some(span),
+                (None, Some(first)) => {
+                    break Err(ParseError::new(
+                        ErrorCode::InvalidArgument,
+                        span,
+                        String::from("positional arguments must come before named arguments"),
+                    )
+                    .with_related(first, "first named argument here"))
+                }
+                _ => (),
+            }
+            arguments.push(argument);
+
+            match self.peek_type() {
+                TokenType::Comma => self.current += 1,
+                TokenType::RightBracket => {
+                    self.current += 1;
+                    break Ok(arguments);
+                }
+                _ => {
+                    break Err(
+                        self.unexpected("`,` or `)`", &[TokenType::Comma, TokenType::RightBracket])
+                    )
+                }
+            }
+        };
+        self.allow_tags = allow_tags;
+
+        result
+    }
+
+    fn parse_argument(&mut self) -> Result<Argument, ParseError> {
+        // (.name =) value | (.name =) _
+        let start = self.span();
+        let mut name = None;
+        if self.peek_type() == TokenType::Dot {
+            self.current += 1;
+            name = Some(self.expect_symbol()?);
+            self.expect(TokenType::Assign)?;
+        }
+
+        let placeholder = self.peek_type() == TokenType::Symbol
+            && matches!(&self.peek().value, TokenValue::Name(s) if *s == "_")
+            && matches!(
+                self.peek_type_at(1),
+                TokenType::Comma | TokenType::RightBracket
+            );
+        let value = if placeholder {
+            self.current += 1;
+            None
+        } else {
+            Some(self.parse_expression_bp(BP_ASSIGN)?)
+        };
+
+        Ok(Argument {
+            name,
+            value,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        match (self.peek_type(), &self.peek().value) {
+            (TokenType::Symbol, TokenValue::Name(name)) => {
+                let name = name.clone();
+                let span = self.span();
+                self.current += 1;
+                Ok(Expr::Symbol(Box::new(Symbol { name, span })))
+            }
+            (TokenType::LeftBracket, _) => {
+                self.current += 1;
+                self.parse_bracketed(TokenType::RightBracket)
+            }
+            _ => Err(self.unexpected("expression", &[TokenType::Symbol, TokenType::LeftBracket])),
+        }
+    }
+
+    fn parse_symbol(&mut self) -> Result<Symbol, ParseError> {
+        let span = self.span();
+        let name = self.expect_symbol()?;
+        Ok(Symbol { name, span })
+    }
+
+    /// parse_name_operand parses the operand of `sizeof`, `tagof`,
+    /// `defined`, `__nameof` or `__addressof`, which names something rather
+    /// than being an expression, inside any number of brackets.
+    fn parse_name_operand<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let mut brackets = 0;
+        while self.peek_type() == TokenType::LeftBracket {
+            self.current += 1;
+            brackets += 1;
+        }
+        let allow_tags = self.allow_tags;
+        self.allow_tags |= brackets > 0;
+        let operand = parse(self);
+        self.allow_tags = allow_tags;
+        let operand = operand?;
+
+        for _ in 0..brackets {
+            self.expect(TokenType::RightBracket)?;
+        }
+        Ok(operand)
+    }
+
+    /// parse_bracketed parses a full expression up to a closing bracket, with
+    /// tag overrides re-enabled since the bracket removes any ambiguity.
+    fn parse_bracketed(&mut self, close: TokenType) -> Result<Expr, ParseError> {
+        let allow_tags = self.allow_tags;
+        self.allow_tags = true;
+        let expr = self.parse_expression();
+        self.allow_tags = allow_tags;
+        let expr = expr?;
+
+        self.expect(close)?;
+        Ok(expr)
+    }
+
+    fn parse_dimensions(&mut self) -> Result<Vec<Option<Expr>>, ParseError> {
+        let mut dimensions = vec![];
+        while self.peek_type() == TokenType::LeftSquare {
+            self.current += 1;
+            if self.peek_type() == TokenType::RightSquare {
+                self.current += 1;
+                dimensions.push(None);
+            } else {
+                dimensions.push(Some(self.parse_bracketed(TokenType::RightSquare)?));
+            }
+        }
+        Ok(dimensions)
+    }
+
+    /// parse_initializer parses the value after `=` in a declaration: either
+    /// an expression or a brace-enclosed, possibly nested, array initializer.
+    fn parse_initializer(&mut self) -> Result<Expr, ParseError> {
+        if self.peek_type() != TokenType::LeftBrace {
+            return self.parse_expression_bp(BP_ASSIGN);
+        }
+        let start = self.span();
+        self.current += 1;
+
+        let mut elements = vec![];
+        let mut fill = false;
+        while self.peek_type() != TokenType::RightBrace {
+            if self.peek_type() == TokenType::Elipsis {
+                if elements.is_empty() {
+                    return Err(ParseError::new(
+                        ErrorCode::InvalidDeclaration,
+                        self.span(),
+                        String::from("`...` must follow at least one element"),
+                    ));
+                }
+                self.current += 1;
+                fill = true;
+                break;
+            }
+
+            elements.push(self.parse_initializer()?);
+
+            match self.peek_type() {
+                TokenType::Comma => self.current += 1,
+                TokenType::RightBrace => (),
+                _ => {
+                    return Err(
+                        self.unexpected("`,` or `}`", &[TokenType::Comma, TokenType::RightBrace])
+                    )
+                }
+            }
+        }
+
+        self.expect(TokenType::RightBrace)?;
+        Ok(Expr::ArrayInitializer(Box::new(ArrayInitializer {
+            elements,
+            fill,
+            span: start.to(self.previous_span()),
+        })))
+    }
+
+    fn is_symbol_at(&self, offset: usize, name: &str) -> bool {
+        match self.tokens.get(self.current + offset) {
+            Some(token) => {
+                token.token_type == TokenType::Symbol
+                    && matches!(&token.value, TokenValue::Name(n) if *n == name)
+            }
+            None => false,
+        }
+    }
+
+    fn is_bool_at(&self, offset: usize) -> bool {
+        match self.tokens.get(self.current + offset) {
+            Some(token) => {
+                token.token_type == TokenType::Symbol
+                    && matches!(&token.value, TokenValue::Name(n) if *n == "true" || *n == "false")
+            }
+            None => false,
+        }
+    }
+
+    /// is_literal_scalar looks ahead for `(tag:)(+|-)literal`. Signs are only
+    /// folded into numeric and character literals.
+    fn is_literal_scalar(&self) -> bool {
+        let mut i = 0;
+        if self.allow_tags
+            && self.peek_type() == TokenType::Symbol
+            && self.peek_type_at(1) == TokenType::Colon
+        {
+            i = 2;
+        }
+
+        let signed = matches!(self.peek_type_at(i), TokenType::Plus | TokenType::Minus);
+        if signed {
+            i += 1;
+        }
+
+        let negative = signed && self.peek_type_at(i - 1) == TokenType::Minus;
+        match self.peek_type_at(i) {
+            _ if self.peek_integer_at(i, negative).is_some() => true,
+            TokenType::Float | TokenType::Character => true,
+            TokenType::Literal | TokenType::PackedLiteral => !signed,
+            TokenType::Symbol => !signed && self.is_bool_at(i),
+            _ => false,
+        }
+    }
+
+    fn parse_literal_scalar(&mut self) -> Result<Expr, ParseError> {
+        // (tag:)(+|-)literal
+        let start = self.span();
+        let mut tag = None;
+        if self.allow_tags {
+            tag = self.parse_tag()?;
+        }
+
+        let sign = match self.peek_type() {
+            TokenType::Plus => Some(Sign::Positive),
+            TokenType::Minus => Some(Sign::Negative),
+            _ => None,
+        };
+        if sign.is_some() {
+            self.current += 1;
+        }
+
+        let value_span = self.span();
+        let token_type = self.peek_type();
+        let integer = self.peek_integer_at(0, sign == Some(Sign::Negative));
+        let mut value = match (integer, token_type, &self.peek().value) {
+            (Some(v), _, _) => LiteralValue::Integer(v),
+            (_, TokenType::Float, TokenValue::Float(v)) => LiteralValue::Float(*v),
+            (_, TokenType::Character, TokenValue::Integer(v)) => {
+                match std::char::from_u32(*v as u32) {
+                    Some(c) => LiteralValue::Character(c),
+                    None => {
+                        return Err(ParseError::new(
+                            ErrorCode::InvalidLiteral,
+                            self.span(),
+                            format!("invalid character literal {}", v),
+                        ))
+                    }
+                }
+            }
+            (_, TokenType::Literal, TokenValue::String(s)) => LiteralValue::String(s.clone()),
+            (_, TokenType::PackedLiteral, TokenValue::String(s)) => {
+                LiteralValue::PackedString(s.clone())
+            }
+            (_, TokenType::Symbol, TokenValue::Name(s)) if *s == "true" => LiteralValue::Bool(true),
+            (_, TokenType::Symbol, TokenValue::Name(s)) if *s == "false" => {
+                LiteralValue::Bool(false)
+            }
+            _ => {
+                return Err(self.unexpected(
+                    "literal",
+                    &[
+                        TokenType::Integer,
+                        TokenType::Float,
+                        TokenType::Character,
+                        TokenType::Literal,
+                        TokenType::PackedLiteral,
+                    ],
+                ))
+            }
+        };
+        self.current += 1;
+
+        // adjacent string literals of the same kind are one literal, so
+        // `"a" "b"` is `"ab"`
+        if let LiteralValue::String(text) | LiteralValue::PackedString(text) = &mut value {
+            while let (next, TokenValue::String(more)) = (self.peek_type(), &self.peek().value) {
+                if next != token_type {
+                    break;
+                }
+                text.push_str(more);
+                self.current += 1;
+            }
+        }
+
+        let implicit = match value {
+            LiteralValue::Float(_) => Some("Float"),
+            LiteralValue::Bool(_) => Some("bool"),
+            _ => None,
+        };
+        let implicit_tag = tag.is_none() && implicit.is_some();
+        if implicit_tag {
+            tag = implicit.map(|name| Tag {
+                name: self.names.intern(name),
+                span: value_span,
+            });
+        }
+
+        Ok(Expr::Literal(Box::new(Literal {
+            value,
+            tag,
+            implicit_tag,
+            sign,
+            span: start.to(self.previous_span()),
+        })))
     }
 }
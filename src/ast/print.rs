@@ -0,0 +1,800 @@
+use std::mem;
+
+use crate::ast::infix_binding_power;
+use crate::ast::Block;
+use crate::ast::CaseValue;
+use crate::ast::CompilationUnit;
+use crate::ast::Declaration;
+use crate::ast::Declarator;
+use crate::ast::Emit;
+use crate::ast::EmitInstruction;
+use crate::ast::EmitOperand;
+use crate::ast::EnumDecl;
+use crate::ast::Expr;
+use crate::ast::FunctionDecl;
+use crate::ast::Item;
+use crate::ast::Literal;
+use crate::ast::LiteralValue;
+use crate::ast::NativeBinding;
+use crate::ast::OperatorDecl;
+use crate::ast::Parameter;
+use crate::ast::Qualifier;
+use crate::ast::Sign;
+use crate::ast::Stmt;
+use crate::ast::StorageClass;
+use crate::ast::Tag;
+use crate::ast::TagofOperand;
+use crate::ast::BP_ASSIGN;
+use crate::ast::BP_POSTFIX;
+use crate::ast::BP_PREFIX;
+use crate::token::TokenType;
+
+/// to_source renders an AST node back into Pawn source. Parsing the output
+/// gives back the same AST, apart from spans: brackets are only added where
+/// precedence, or one of Pawn's ambiguities such as `Tag:` being read as a
+/// label, calls for them. Every statement ends in a semicolon, so the output
+/// also parses under `#pragma semicolon 1`.
+pub fn to_source<T: Print + ?Sized>(node: &T) -> String {
+    let mut p = Printer::default();
+    node.print(&mut p);
+    p.output
+}
+
+pub trait Print {
+    fn print(&self, p: &mut Printer);
+}
+
+/// Printer accumulates source text. `tags_disabled` mirrors the parser's
+/// `allow_tags`, since a tag written where the parser doesn't expect one
+/// must be bracketed.
+#[derive(Default)]
+pub struct Printer {
+    output: String,
+    indent: usize,
+    tags_disabled: bool,
+}
+
+impl Print for CompilationUnit {
+    fn print(&self, p: &mut Printer) {
+        for (i, item) in self.items.iter().enumerate() {
+            // a blank line around everything but runs of declarations
+            let simple = |item: &Item| matches!(item, Item::Declaration(_) | Item::Emit(_));
+            if i > 0 && !(simple(item) && simple(&self.items[i - 1])) {
+                p.write("\n");
+            }
+            item.print(p);
+            p.write("\n");
+        }
+    }
+}
+
+impl Print for Item {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Item::Declaration(d) => {
+                d.print(p);
+                p.write(";");
+            }
+            Item::Function(f) => f.print(p),
+            Item::Operator(o) => o.print(p),
+            Item::Enum(e) => e.print(p),
+            Item::Emit(e) => e.print(p),
+        }
+    }
+}
+
+/// A Declaration prints without its semicolon, as it appears in a `for`.
+impl Print for Declaration {
+    fn print(&self, p: &mut Printer) {
+        p.write(match self.storage {
+            StorageClass::New => "new",
+            StorageClass::Static => "static",
+            StorageClass::Stock => "stock",
+            StorageClass::StaticStock => "static stock",
+            StorageClass::Const => "const",
+        });
+        if self.constant && self.storage != StorageClass::Const {
+            p.write(" const");
+        }
+        for (i, declarator) in self.declarators.iter().enumerate() {
+            p.write(if i == 0 { " " } else { ", " });
+            p.declarator(declarator);
+        }
+    }
+}
+
+impl Print for FunctionDecl {
+    fn print(&self, p: &mut Printer) {
+        p.qualifiers(&self.qualifiers);
+        p.tag(&self.tag);
+        p.write(&self.name);
+        p.parameters(&self.parameters);
+        if let Some(spec) = &self.states {
+            p.write(" <");
+            if let Some(automaton) = &spec.automaton {
+                p.write(automaton);
+                p.write(":");
+            }
+            let states: Vec<&str> = spec.states.iter().map(|s| s.as_str()).collect();
+            p.write(&states.join(", "));
+            p.write(">");
+        }
+        p.native_binding(&self.native);
+        p.function_body(&self.body);
+    }
+}
+
+impl Print for OperatorDecl {
+    fn print(&self, p: &mut Printer) {
+        p.qualifiers(&self.qualifiers);
+        p.tag(&self.tag);
+        p.write(&format!("operator{}", self.operator));
+        p.parameters(&self.parameters);
+        p.native_binding(&self.native);
+        p.function_body(&self.body);
+    }
+}
+
+impl Print for EnumDecl {
+    fn print(&self, p: &mut Printer) {
+        p.write("enum ");
+        p.tag(&self.tag);
+        if let Some(name) = &self.name {
+            p.write(name);
+            p.write(" ");
+        }
+        let default = self.increment.operator == TokenType::PlusAssign
+            && matches!(&self.increment.value, Expr::Literal(l)
+                if l.value == LiteralValue::Integer(1) && l.sign.is_none() && l.tag.is_none());
+        if !default {
+            p.write(&format!("({} ", self.increment.operator));
+            p.expr(&self.increment.value, 0);
+            p.write(") ");
+        }
+
+        p.write("{");
+        p.indent += 1;
+        for (i, member) in self.members.iter().enumerate() {
+            if i > 0 {
+                p.write(",");
+            }
+            p.newline();
+            p.tag(&member.tag);
+            p.write(&member.name);
+            if let Some(size) = &member.size {
+                p.write("[");
+                p.expr(size, 0);
+                p.write("]");
+            }
+            if let Some(value) = &member.value {
+                p.write(" = ");
+                p.expr(value, BP_ASSIGN);
+            }
+        }
+        p.indent -= 1;
+        p.newline();
+        p.write("}");
+    }
+}
+
+/// A single instruction prints as `#emit`, which must end its line, and
+/// several as `__emit(...)`.
+impl Print for Emit {
+    fn print(&self, p: &mut Printer) {
+        if let [instruction] = &self.instructions[..] {
+            p.write("#emit ");
+            p.instruction(instruction);
+            return;
+        }
+        p.write("__emit(");
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if i > 0 {
+                p.write(", ");
+            }
+            p.instruction(instruction);
+        }
+        p.write(");");
+    }
+}
+
+impl Print for Block {
+    fn print(&self, p: &mut Printer) {
+        if self.statements.is_empty() {
+            p.write("{}");
+            return;
+        }
+        p.write("{");
+        p.indent += 1;
+        for statement in &self.statements {
+            p.newline();
+            statement.print(p);
+        }
+        p.indent -= 1;
+        p.newline();
+        p.write("}");
+    }
+}
+
+impl Print for Stmt {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Stmt::Block(b) => b.print(p),
+            Stmt::Empty(_) => p.write(";"),
+            Stmt::Declaration(d) => {
+                d.print(p);
+                p.write(";");
+            }
+            Stmt::Expr(e) => {
+                p.expression_statement(e);
+                p.write(";");
+            }
+            Stmt::If(i) => {
+                p.write("if (");
+                p.expr(&i.condition, 0);
+                p.write(")");
+                p.body(&i.then);
+                if let Some(otherwise) = &i.otherwise {
+                    p.continuation(&i.then);
+                    p.write("else");
+                    match otherwise {
+                        Stmt::If(_) => {
+                            p.write(" ");
+                            otherwise.print(p);
+                        }
+                        _ => p.body(otherwise),
+                    }
+                }
+            }
+            Stmt::While(w) => {
+                p.write("while (");
+                p.expr(&w.condition, 0);
+                p.write(")");
+                p.body(&w.body);
+            }
+            Stmt::DoWhile(d) => {
+                p.write("do");
+                p.body(&d.body);
+                p.continuation(&d.body);
+                p.write("while (");
+                p.expr(&d.condition, 0);
+                p.write(");");
+            }
+            Stmt::For(f) => {
+                p.write("for (");
+                match &f.init {
+                    Some(Stmt::Declaration(d)) => d.print(p),
+                    Some(Stmt::Expr(e)) => p.expr(e, 0),
+                    _ => (),
+                }
+                p.write(";");
+                if let Some(condition) = &f.condition {
+                    p.write(" ");
+                    p.expr(condition, 0);
+                }
+                p.write(";");
+                if let Some(increment) = &f.increment {
+                    p.write(" ");
+                    p.expr(increment, 0);
+                }
+                p.write(")");
+                p.body(&f.body);
+            }
+            Stmt::Switch(s) => {
+                p.write("switch (");
+                p.expr(&s.value, 0);
+                p.write(") {");
+                p.indent += 1;
+                for case in &s.cases {
+                    p.newline();
+                    p.write("case ");
+                    // case values are parsed with tags disabled, like the
+                    // middle of a `?:`
+                    let tags_disabled = mem::replace(&mut p.tags_disabled, true);
+                    for (i, value) in case.values.iter().enumerate() {
+                        if i > 0 {
+                            p.write(", ");
+                        }
+                        match value {
+                            CaseValue::Value(e) => p.expr(e, BP_ASSIGN),
+                            CaseValue::Range(low, high) => {
+                                p.expr(low, BP_ASSIGN);
+                                p.write("..");
+                                p.expr(high, BP_ASSIGN);
+                            }
+                        }
+                    }
+                    p.tags_disabled = tags_disabled;
+                    p.write(":");
+                    p.body(&case.body);
+                }
+                if let Some(default) = &s.default {
+                    p.newline();
+                    p.write("default:");
+                    p.body(default);
+                }
+                p.indent -= 1;
+                p.newline();
+                p.write("}");
+            }
+            Stmt::Break(_) => p.write("break;"),
+            Stmt::Continue(_) => p.write("continue;"),
+            Stmt::Return(r) => p.keyword_value("return", r.value.as_ref()),
+            Stmt::Exit(e) => p.keyword_value("exit", e.value.as_ref()),
+            Stmt::Sleep(s) => p.keyword_value("sleep", s.value.as_ref()),
+            Stmt::Assert(a) => p.keyword_value("assert", Some(&a.condition)),
+            Stmt::Label(l) => {
+                p.write(&l.name);
+                p.write(":");
+            }
+            Stmt::Goto(g) => {
+                p.write("goto ");
+                p.write(&g.label);
+                p.write(";");
+            }
+            Stmt::State(s) => {
+                p.write("state ");
+                if let Some(condition) = &s.condition {
+                    p.write("(");
+                    p.expr(condition, 0);
+                    p.write(") ");
+                }
+                if let Some(automaton) = &s.automaton {
+                    p.write(automaton);
+                    p.write(":");
+                }
+                p.write(&s.name);
+                p.write(";");
+            }
+            Stmt::Emit(e) => e.print(p),
+        }
+    }
+}
+
+impl Print for Expr {
+    fn print(&self, p: &mut Printer) {
+        p.expr(self, 0);
+    }
+}
+
+/// binding_power returns the binding power an expression has on its left
+/// and right, as the parser sees it. Operands that aren't operators bind as
+/// tightly as possible.
+fn binding_power(e: &Expr) -> (u8, u8) {
+    match e {
+        Expr::BinaryOperation(b) => infix_binding_power(b.operator).unwrap_or((0, 0)),
+        Expr::TernaryOperation(_) => infix_binding_power(TokenType::Question).unwrap_or((0, 0)),
+        // brackets after `sizeof a` and `tagof a` would be read as theirs
+        Expr::UnaryOperation(_) | Expr::TagOverride(_) | Expr::Sizeof(_) | Expr::Tagof(_) => {
+            (u8::MAX, BP_PREFIX)
+        }
+        Expr::PostfixOperation(_) | Expr::Char(_) | Expr::Call(_) | Expr::ArrayIndex(_) => {
+            (BP_POSTFIX, u8::MAX)
+        }
+        Expr::Literal(_)
+        | Expr::Symbol(_)
+        | Expr::ArrayInitializer(_)
+        | Expr::Defined(_)
+        | Expr::Nameof(_)
+        | Expr::Addressof(_) => (u8::MAX, u8::MAX),
+    }
+}
+
+/// explicit_tag returns a literal's tag if it was written out.
+fn explicit_tag(l: &Literal) -> Option<&Tag> {
+    match &l.tag {
+        Some(tag) if !l.implicit_tag => Some(tag),
+        _ => None,
+    }
+}
+
+impl Printer {
+    fn write(&mut self, s: &str) {
+        self.output.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        for _ in 0..self.indent {
+            self.output.push_str("    ");
+        }
+    }
+
+    /// body prints the statement a control statement owns: a block on the
+    /// same line, anything else indented on the next.
+    fn body(&mut self, statement: &Stmt) {
+        if let Stmt::Block(b) = statement {
+            self.write(" ");
+            b.print(self);
+            return;
+        }
+        self.indent += 1;
+        self.newline();
+        statement.print(self);
+        self.indent -= 1;
+    }
+
+    /// continuation starts the `else` or `while` that follows a body.
+    fn continuation(&mut self, body: &Stmt) {
+        match body {
+            Stmt::Block(_) => self.write(" "),
+            _ => self.newline(),
+        }
+    }
+
+    fn keyword_value(&mut self, keyword: &str, value: Option<&Expr>) {
+        self.write(keyword);
+        if let Some(value) = value {
+            self.write(" ");
+            self.expr(value, 0);
+        }
+        self.write(";");
+    }
+
+    /// expression_statement brackets an expression statement that would
+    /// otherwise start with `name:`, which the parser reads as a label.
+    fn expression_statement(&mut self, e: &Expr) {
+        let start = self.output.len();
+        self.expr(e, 0);
+        let text = &self.output[start..];
+        let name = text
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '@'))
+            .unwrap_or(text.len());
+        if name > 0 && text[name..].starts_with(':') {
+            self.output.insert(start, '(');
+            self.output.push(')');
+        }
+    }
+
+    fn qualifiers(&mut self, qualifiers: &[Qualifier]) {
+        for qualifier in qualifiers {
+            self.write(match qualifier {
+                Qualifier::Public => "public ",
+                Qualifier::Static => "static ",
+                Qualifier::Stock => "stock ",
+                Qualifier::Forward => "forward ",
+                Qualifier::Native => "native ",
+            });
+        }
+    }
+
+    fn tag(&mut self, tag: &Option<Tag>) {
+        if let Some(tag) = tag {
+            self.write(&tag.name);
+            self.write(":");
+        }
+    }
+
+    fn dimensions(&mut self, dimensions: &[Option<Expr>]) {
+        for dimension in dimensions {
+            self.bracketed_by("[", "]", |p| {
+                if let Some(size) = dimension {
+                    p.expr(size, 0);
+                }
+            });
+        }
+    }
+
+    fn declarator(&mut self, declarator: &Declarator) {
+        self.tag(&declarator.tag);
+        self.write(&declarator.name);
+        self.dimensions(&declarator.dimensions);
+        if let Some(initializer) = &declarator.initializer {
+            self.write(" = ");
+            self.expr(initializer, BP_ASSIGN);
+        }
+    }
+
+    fn parameters(&mut self, parameters: &[Parameter]) {
+        self.write("(");
+        for (i, parameter) in parameters.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            if parameter.constant {
+                self.write("const ");
+            }
+            if parameter.reference {
+                self.write("&");
+            }
+            match &parameter.tags[..] {
+                [] => (),
+                [tag] => {
+                    self.write(&tag.name);
+                    self.write(":");
+                }
+                tags => {
+                    let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+                    self.write(&format!("{{{}}}:", names.join(", ")));
+                }
+            }
+            if parameter.variadic {
+                self.write("...");
+                continue;
+            }
+            self.write(&parameter.name);
+            self.dimensions(&parameter.dimensions);
+            if let Some(default) = &parameter.default {
+                self.write(" = ");
+                self.expr(default, BP_ASSIGN);
+            }
+        }
+        self.write(")");
+    }
+
+    fn native_binding(&mut self, native: &Option<NativeBinding>) {
+        match native {
+            Some(NativeBinding::Index(i)) => self.write(&format!(" = {}", i)),
+            Some(NativeBinding::Name(name)) => self.write(&format!(" = {}", name)),
+            None => (),
+        }
+    }
+
+    fn function_body(&mut self, body: &Option<Block>) {
+        match body {
+            Some(body) => {
+                self.write(" ");
+                body.print(self);
+            }
+            None => self.write(";"),
+        }
+    }
+
+    fn instruction(&mut self, instruction: &EmitInstruction) {
+        self.write(instruction.opcode.mnemonic());
+        for operand in &instruction.operands {
+            match operand {
+                EmitOperand::Number(n) => self.write(&format!(" {}", n)),
+                EmitOperand::Relative(0) => self.write(" ."),
+                EmitOperand::Relative(n) => self.write(&format!(" .{:+}", n)),
+                EmitOperand::Symbol(symbol) => {
+                    self.write(" ");
+                    self.write(&symbol.name);
+                }
+                EmitOperand::Label(name) => {
+                    self.write(" ");
+                    self.write(name);
+                }
+            }
+        }
+    }
+
+    /// expr prints an expression where the parser would read it with
+    /// `min_bp`, bracketing it if it binds too loosely to be read there.
+    fn expr(&mut self, e: &Expr, min_bp: u8) {
+        self.operand(e, min_bp, 0);
+    }
+
+    /// operand prints an expression that is either read with `min_bp`, or is
+    /// the left operand of an operator binding with `left_bp` on its left,
+    /// which would otherwise take the expression's own right operand.
+    fn operand(&mut self, e: &Expr, min_bp: u8, left_bp: u8) {
+        let (l, r) = binding_power(e);
+        let tagged = match e {
+            Expr::TagOverride(_) => true,
+            Expr::Literal(l) => explicit_tag(l).is_some(),
+            _ => false,
+        };
+        if l < min_bp || r <= left_bp || (tagged && self.tags_disabled) {
+            self.bracketed(|p| p.unbracketed(e));
+        } else {
+            self.unbracketed(e);
+        }
+    }
+
+    /// bracketed prints inside `(...)` or similar, where tags are allowed
+    /// again.
+    fn bracketed(&mut self, f: impl FnOnce(&mut Printer)) {
+        let tags_disabled = mem::replace(&mut self.tags_disabled, false);
+        self.write("(");
+        f(self);
+        self.write(")");
+        self.tags_disabled = tags_disabled;
+    }
+
+    fn unbracketed(&mut self, e: &Expr) {
+        match e {
+            Expr::Literal(l) => self.literal(l),
+            Expr::Symbol(s) => self.write(&s.name),
+            Expr::ArrayInitializer(a) => {
+                self.write("{");
+                for (i, element) in a.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.expr(element, BP_ASSIGN);
+                }
+                if a.fill {
+                    self.write(", ...");
+                }
+                self.write("}");
+            }
+            Expr::BinaryOperation(b) => {
+                let (l_bp, r_bp) = binding_power(e);
+                self.operand(&b.left, 0, l_bp);
+                match b.operator {
+                    TokenType::Comma => self.write(", "),
+                    operator => self.write(&format!(" {} ", operator)),
+                }
+                self.expr(&b.right, r_bp);
+            }
+            Expr::UnaryOperation(u) => {
+                self.write(&u.operator.to_string());
+                if u.operator.is_keyword() {
+                    self.write(" ");
+                }
+                // `-5` would be read back as a literal, and `- -a` and
+                // `!"a"` as `--a` and a packed string
+                let start = self.output.len();
+                self.expr(&u.operand, BP_PREFIX);
+                let number = matches!(&u.operand, Expr::Literal(l)
+                    if l.sign.is_none() && explicit_tag(l).is_none() && matches!(l.value,
+                        LiteralValue::Integer(_) | LiteralValue::Float(_) | LiteralValue::Character(_)));
+                let merges = match u.operator {
+                    TokenType::Minus | TokenType::MinusMinus => {
+                        self.output[start..].starts_with('-')
+                    }
+                    TokenType::Plus | TokenType::PlusPlus => self.output[start..].starts_with('+'),
+                    TokenType::Bang => self.output[start..].starts_with('"'),
+                    _ => false,
+                };
+                if (number && u.operator == TokenType::Minus) || merges {
+                    self.output.insert(start, '(');
+                    self.output.push(')');
+                }
+            }
+            Expr::PostfixOperation(p) => {
+                self.operand(&p.operand, 0, BP_POSTFIX);
+                self.write(&p.operator.to_string());
+            }
+            Expr::TernaryOperation(t) => {
+                let (l_bp, r_bp) = binding_power(e);
+                self.operand(&t.condition, 0, l_bp);
+                self.write(" ? ");
+                let tags_disabled = mem::replace(&mut self.tags_disabled, true);
+                self.expr(&t.then, BP_ASSIGN);
+                self.tags_disabled = tags_disabled;
+                self.write(" : ");
+                self.expr(&t.otherwise, r_bp);
+            }
+            Expr::TagOverride(t) => {
+                self.write(&t.tag.name);
+                self.write(":");
+                // `Tag:5` would be read back as a tagged literal
+                match &t.operand {
+                    Expr::Literal(l) if explicit_tag(l).is_none() => {
+                        self.bracketed(|p| p.literal(l))
+                    }
+                    operand => self.expr(operand, BP_PREFIX),
+                }
+            }
+            Expr::ArrayIndex(a) => {
+                self.operand(&a.array, 0, BP_POSTFIX);
+                self.bracketed_by("[", "]", |p| p.expr(&a.index, 0));
+            }
+            Expr::Call(c) => {
+                self.write(&c.function.name);
+                self.bracketed_by("(", ")", |p| {
+                    for (i, argument) in c.arguments.iter().enumerate() {
+                        if i > 0 {
+                            p.write(", ");
+                        }
+                        if let Some(name) = &argument.name {
+                            p.write(&format!(".{} = ", name));
+                        }
+                        match &argument.value {
+                            Some(value) => p.expr(value, BP_ASSIGN),
+                            None => p.write("_"),
+                        }
+                    }
+                });
+            }
+            Expr::Sizeof(s) => {
+                self.write("sizeof ");
+                self.write(&s.symbol.name);
+                self.dimensions(&s.dimensions);
+            }
+            Expr::Tagof(t) => match &t.operand {
+                TagofOperand::Symbol(s) => {
+                    self.write("tagof ");
+                    self.write(&s.name);
+                    self.dimensions(&t.dimensions);
+                }
+                TagofOperand::Tag(tag) => {
+                    self.write("tagof(");
+                    self.write(&tag.name);
+                    self.write(":)");
+                }
+            },
+            Expr::Char(c) => {
+                self.operand(&c.operand, 0, BP_POSTFIX);
+                self.write(" char");
+            }
+            Expr::Defined(d) => {
+                self.write("defined ");
+                self.write(&d.symbol.name);
+            }
+            Expr::Nameof(n) => {
+                self.write("__nameof(");
+                self.write(&n.symbol.name);
+                self.write(")");
+            }
+            Expr::Addressof(a) => {
+                self.write("__addressof(");
+                self.write(&a.symbol.name);
+                self.write(")");
+            }
+        }
+    }
+
+    fn bracketed_by(&mut self, open: &str, close: &str, f: impl FnOnce(&mut Printer)) {
+        let tags_disabled = mem::replace(&mut self.tags_disabled, false);
+        self.write(open);
+        f(self);
+        self.write(close);
+        self.tags_disabled = tags_disabled;
+    }
+
+    fn literal(&mut self, l: &Literal) {
+        if let Some(tag) = explicit_tag(l) {
+            self.write(&tag.name);
+            self.write(":");
+        }
+        match l.sign {
+            Some(Sign::Positive) => self.write("+"),
+            Some(Sign::Negative) => self.write("-"),
+            None => (),
+        }
+        let value = match &l.value {
+            // the lexer reads `-2147483648` as the magnitude i32::MIN
+            LiteralValue::Integer(i32::MIN) if l.sign == Some(Sign::Negative) => {
+                i32::MIN.unsigned_abs().to_string()
+            }
+            // cells are unsigned to the lexer, so negative values come from
+            // hex literals such as 0xFFFFFFFF
+            LiteralValue::Integer(v) if *v < 0 => format!("0x{:X}", *v as u32),
+            LiteralValue::Integer(v) => v.to_string(),
+            LiteralValue::Float(v) => float(*v),
+            LiteralValue::Character(c) => format!("'{}'", escape(&c.to_string(), '\'')),
+            LiteralValue::String(s) => format!("\"{}\"", escape(s, '"')),
+            LiteralValue::PackedString(s) => format!("!\"{}\"", escape(s, '"')),
+            LiteralValue::Bool(b) => b.to_string(),
+        };
+        self.write(&value);
+    }
+}
+
+/// float formats a float the way the lexer reads it back: always with a
+/// fraction, and with an `e` exponent only for very large or small values.
+fn float(v: f32) -> String {
+    let s = format!("{:?}", v);
+    if s.contains('.') && !s.contains('e') {
+        return s;
+    }
+    let s = format!("{:e}", v);
+    match s.split_once('e') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => {
+            format!("{}.0e{}", mantissa, exponent)
+        }
+        _ => s,
+    }
+}
+
+/// escape escapes a string or character literal's body for `quote`.
+fn escape(s: &str, quote: char) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:X};", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,574 @@
+use crate::ast::Addressof;
+use crate::ast::Argument;
+use crate::ast::ArrayIndex;
+use crate::ast::ArrayInitializer;
+use crate::ast::Assert;
+use crate::ast::BinaryOperation;
+use crate::ast::Block;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::CaseValue;
+use crate::ast::Char;
+use crate::ast::CompilationUnit;
+use crate::ast::Declaration;
+use crate::ast::Declarator;
+use crate::ast::Defined;
+use crate::ast::DoWhile;
+use crate::ast::Emit;
+use crate::ast::EmitInstruction;
+use crate::ast::EmitOperand;
+use crate::ast::EnumDecl;
+use crate::ast::EnumMember;
+use crate::ast::Exit;
+use crate::ast::Expr;
+use crate::ast::For;
+use crate::ast::FunctionDecl;
+use crate::ast::Goto;
+use crate::ast::If;
+use crate::ast::Item;
+use crate::ast::Label;
+use crate::ast::Literal;
+use crate::ast::Nameof;
+use crate::ast::NativeBinding;
+use crate::ast::OperatorDecl;
+use crate::ast::Parameter;
+use crate::ast::PostfixOperation;
+use crate::ast::Return;
+use crate::ast::Sizeof;
+use crate::ast::Sleep;
+use crate::ast::State;
+use crate::ast::StateSpec;
+use crate::ast::Stmt;
+use crate::ast::Switch;
+use crate::ast::Symbol;
+use crate::ast::Tag;
+use crate::ast::TagOverride;
+use crate::ast::Tagof;
+use crate::ast::TagofOperand;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::While;
+use crate::token::Span;
+
+/// Visitor walks the AST without changing it. Each `visit_*` method defaults
+/// to the matching `walk_*` function, which visits the node's children in
+/// source order, so an override that still wants the children visited calls
+/// the walk function itself.
+pub trait Visitor {
+    fn visit_unit(&mut self, unit: &CompilationUnit) {
+        walk_unit(self, unit)
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item)
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        walk_declaration(self, declaration)
+    }
+
+    fn visit_declarator(&mut self, declarator: &Declarator) {
+        walk_declarator(self, declarator)
+    }
+
+    fn visit_function(&mut self, function: &FunctionDecl) {
+        walk_function(self, function)
+    }
+
+    fn visit_operator(&mut self, operator: &OperatorDecl) {
+        walk_operator(self, operator)
+    }
+
+    fn visit_parameter(&mut self, parameter: &Parameter) {
+        walk_parameter(self, parameter)
+    }
+
+    fn visit_enum(&mut self, e: &EnumDecl) {
+        walk_enum(self, e)
+    }
+
+    fn visit_enum_member(&mut self, member: &EnumMember) {
+        walk_enum_member(self, member)
+    }
+
+    fn visit_native_binding(&mut self, _native: &NativeBinding) {}
+
+    fn visit_state_spec(&mut self, _states: &StateSpec) {}
+
+    fn visit_emit(&mut self, emit: &Emit) {
+        walk_emit(self, emit)
+    }
+
+    fn visit_emit_instruction(&mut self, instruction: &EmitInstruction) {
+        walk_emit_instruction(self, instruction)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        walk_stmt(self, statement)
+    }
+
+    fn visit_empty(&mut self, _span: &Span) {}
+
+    fn visit_break(&mut self, _span: &Span) {}
+
+    fn visit_continue(&mut self, _span: &Span) {}
+
+    fn visit_return(&mut self, r: &Return) {
+        walk_return(self, r)
+    }
+
+    fn visit_exit(&mut self, e: &Exit) {
+        walk_exit(self, e)
+    }
+
+    fn visit_sleep(&mut self, s: &Sleep) {
+        walk_sleep(self, s)
+    }
+
+    fn visit_assert(&mut self, a: &Assert) {
+        walk_assert(self, a)
+    }
+
+    fn visit_label(&mut self, _label: &Label) {}
+
+    fn visit_goto(&mut self, _goto: &Goto) {}
+
+    fn visit_if(&mut self, i: &If) {
+        walk_if(self, i)
+    }
+
+    fn visit_while(&mut self, w: &While) {
+        walk_while(self, w)
+    }
+
+    fn visit_do_while(&mut self, d: &DoWhile) {
+        walk_do_while(self, d)
+    }
+
+    fn visit_for(&mut self, f: &For) {
+        walk_for(self, f)
+    }
+
+    fn visit_switch(&mut self, s: &Switch) {
+        walk_switch(self, s)
+    }
+
+    fn visit_case(&mut self, case: &Case) {
+        walk_case(self, case)
+    }
+
+    fn visit_state(&mut self, state: &State) {
+        walk_state(self, state)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_binary(&mut self, b: &BinaryOperation) {
+        walk_binary(self, b)
+    }
+
+    fn visit_unary(&mut self, u: &UnaryOperation) {
+        walk_unary(self, u)
+    }
+
+    fn visit_postfix(&mut self, p: &PostfixOperation) {
+        walk_postfix(self, p)
+    }
+
+    fn visit_ternary(&mut self, t: &TernaryOperation) {
+        walk_ternary(self, t)
+    }
+
+    fn visit_tag_override(&mut self, t: &TagOverride) {
+        walk_tag_override(self, t)
+    }
+
+    fn visit_array_index(&mut self, a: &ArrayIndex) {
+        walk_array_index(self, a)
+    }
+
+    fn visit_array_initializer(&mut self, a: &ArrayInitializer) {
+        walk_array_initializer(self, a)
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        walk_call(self, call)
+    }
+
+    fn visit_argument(&mut self, argument: &Argument) {
+        walk_argument(self, argument)
+    }
+
+    fn visit_sizeof(&mut self, s: &Sizeof) {
+        walk_sizeof(self, s)
+    }
+
+    fn visit_tagof(&mut self, t: &Tagof) {
+        walk_tagof(self, t)
+    }
+
+    fn visit_char(&mut self, c: &Char) {
+        walk_char(self, c)
+    }
+
+    fn visit_defined(&mut self, d: &Defined) {
+        walk_defined(self, d)
+    }
+
+    fn visit_nameof(&mut self, n: &Nameof) {
+        walk_nameof(self, n)
+    }
+
+    fn visit_addressof(&mut self, a: &Addressof) {
+        walk_addressof(self, a)
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        walk_literal(self, literal)
+    }
+
+    fn visit_symbol(&mut self, _symbol: &Symbol) {}
+
+    fn visit_tag(&mut self, _tag: &Tag) {}
+}
+
+pub fn walk_unit<V: Visitor + ?Sized>(v: &mut V, unit: &CompilationUnit) {
+    for item in &unit.items {
+        v.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(v: &mut V, item: &Item) {
+    match item {
+        Item::Declaration(d) => v.visit_declaration(d),
+        Item::Function(f) => v.visit_function(f),
+        Item::Operator(o) => v.visit_operator(o),
+        Item::Enum(e) => v.visit_enum(e),
+        Item::Emit(e) => v.visit_emit(e),
+    }
+}
+
+pub fn walk_declaration<V: Visitor + ?Sized>(v: &mut V, declaration: &Declaration) {
+    for declarator in &declaration.declarators {
+        v.visit_declarator(declarator);
+    }
+}
+
+pub fn walk_declarator<V: Visitor + ?Sized>(v: &mut V, declarator: &Declarator) {
+    if let Some(tag) = &declarator.tag {
+        v.visit_tag(tag);
+    }
+    for dimension in declarator.dimensions.iter().flatten() {
+        v.visit_expr(dimension);
+    }
+    if let Some(initializer) = &declarator.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(v: &mut V, function: &FunctionDecl) {
+    if let Some(tag) = &function.tag {
+        v.visit_tag(tag);
+    }
+    for parameter in &function.parameters {
+        v.visit_parameter(parameter);
+    }
+    if let Some(native) = &function.native {
+        v.visit_native_binding(native);
+    }
+    if let Some(states) = &function.states {
+        v.visit_state_spec(states);
+    }
+    if let Some(body) = &function.body {
+        v.visit_block(body);
+    }
+}
+
+pub fn walk_operator<V: Visitor + ?Sized>(v: &mut V, operator: &OperatorDecl) {
+    if let Some(tag) = &operator.tag {
+        v.visit_tag(tag);
+    }
+    for parameter in &operator.parameters {
+        v.visit_parameter(parameter);
+    }
+    if let Some(native) = &operator.native {
+        v.visit_native_binding(native);
+    }
+    if let Some(body) = &operator.body {
+        v.visit_block(body);
+    }
+}
+
+pub fn walk_parameter<V: Visitor + ?Sized>(v: &mut V, parameter: &Parameter) {
+    for tag in &parameter.tags {
+        v.visit_tag(tag);
+    }
+    for dimension in parameter.dimensions.iter().flatten() {
+        v.visit_expr(dimension);
+    }
+    if let Some(default) = &parameter.default {
+        v.visit_expr(default);
+    }
+}
+
+pub fn walk_enum<V: Visitor + ?Sized>(v: &mut V, e: &EnumDecl) {
+    if let Some(tag) = &e.tag {
+        v.visit_tag(tag);
+    }
+    v.visit_expr(&e.increment.value);
+    for member in &e.members {
+        v.visit_enum_member(member);
+    }
+}
+
+pub fn walk_enum_member<V: Visitor + ?Sized>(v: &mut V, member: &EnumMember) {
+    if let Some(tag) = &member.tag {
+        v.visit_tag(tag);
+    }
+    if let Some(size) = &member.size {
+        v.visit_expr(size);
+    }
+    if let Some(value) = &member.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_emit<V: Visitor + ?Sized>(v: &mut V, emit: &Emit) {
+    for instruction in &emit.instructions {
+        v.visit_emit_instruction(instruction);
+    }
+}
+
+pub fn walk_emit_instruction<V: Visitor + ?Sized>(v: &mut V, instruction: &EmitInstruction) {
+    for operand in &instruction.operands {
+        if let EmitOperand::Symbol(symbol) = operand {
+            v.visit_symbol(symbol);
+        }
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, block: &Block) {
+    for statement in &block.statements {
+        v.visit_stmt(statement);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, statement: &Stmt) {
+    match statement {
+        Stmt::Block(b) => v.visit_block(b),
+        Stmt::Empty(span) => v.visit_empty(span),
+        Stmt::Declaration(d) => v.visit_declaration(d),
+        Stmt::Expr(e) => v.visit_expr(e),
+        Stmt::If(i) => v.visit_if(i),
+        Stmt::While(w) => v.visit_while(w),
+        Stmt::DoWhile(d) => v.visit_do_while(d),
+        Stmt::For(f) => v.visit_for(f),
+        Stmt::Switch(s) => v.visit_switch(s),
+        Stmt::Break(span) => v.visit_break(span),
+        Stmt::Continue(span) => v.visit_continue(span),
+        Stmt::Return(r) => v.visit_return(r),
+        Stmt::Exit(e) => v.visit_exit(e),
+        Stmt::Sleep(s) => v.visit_sleep(s),
+        Stmt::Assert(a) => v.visit_assert(a),
+        Stmt::Label(l) => v.visit_label(l),
+        Stmt::Goto(g) => v.visit_goto(g),
+        Stmt::State(s) => v.visit_state(s),
+        Stmt::Emit(e) => v.visit_emit(e),
+    }
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(v: &mut V, r: &Return) {
+    if let Some(value) = &r.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_exit<V: Visitor + ?Sized>(v: &mut V, e: &Exit) {
+    if let Some(value) = &e.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_sleep<V: Visitor + ?Sized>(v: &mut V, s: &Sleep) {
+    if let Some(value) = &s.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_assert<V: Visitor + ?Sized>(v: &mut V, a: &Assert) {
+    v.visit_expr(&a.condition);
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, i: &If) {
+    v.visit_expr(&i.condition);
+    v.visit_stmt(&i.then);
+    if let Some(otherwise) = &i.otherwise {
+        v.visit_stmt(otherwise);
+    }
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(v: &mut V, w: &While) {
+    v.visit_expr(&w.condition);
+    v.visit_stmt(&w.body);
+}
+
+pub fn walk_do_while<V: Visitor + ?Sized>(v: &mut V, d: &DoWhile) {
+    v.visit_stmt(&d.body);
+    v.visit_expr(&d.condition);
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(v: &mut V, f: &For) {
+    if let Some(init) = &f.init {
+        v.visit_stmt(init);
+    }
+    if let Some(condition) = &f.condition {
+        v.visit_expr(condition);
+    }
+    if let Some(increment) = &f.increment {
+        v.visit_expr(increment);
+    }
+    v.visit_stmt(&f.body);
+}
+
+pub fn walk_switch<V: Visitor + ?Sized>(v: &mut V, s: &Switch) {
+    v.visit_expr(&s.value);
+    for case in &s.cases {
+        v.visit_case(case);
+    }
+    if let Some(default) = &s.default {
+        v.visit_stmt(default);
+    }
+}
+
+pub fn walk_case<V: Visitor + ?Sized>(v: &mut V, case: &Case) {
+    for value in &case.values {
+        match value {
+            CaseValue::Value(e) => v.visit_expr(e),
+            CaseValue::Range(low, high) => {
+                v.visit_expr(low);
+                v.visit_expr(high);
+            }
+        }
+    }
+    v.visit_stmt(&case.body);
+}
+
+pub fn walk_state<V: Visitor + ?Sized>(v: &mut V, state: &State) {
+    if let Some(condition) = &state.condition {
+        v.visit_expr(condition);
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Literal(l) => v.visit_literal(l),
+        Expr::Symbol(s) => v.visit_symbol(s),
+        Expr::ArrayInitializer(a) => v.visit_array_initializer(a),
+        Expr::BinaryOperation(b) => v.visit_binary(b),
+        Expr::UnaryOperation(u) => v.visit_unary(u),
+        Expr::PostfixOperation(p) => v.visit_postfix(p),
+        Expr::TernaryOperation(t) => v.visit_ternary(t),
+        Expr::TagOverride(t) => v.visit_tag_override(t),
+        Expr::ArrayIndex(a) => v.visit_array_index(a),
+        Expr::Call(c) => v.visit_call(c),
+        Expr::Sizeof(s) => v.visit_sizeof(s),
+        Expr::Tagof(t) => v.visit_tagof(t),
+        Expr::Char(c) => v.visit_char(c),
+        Expr::Defined(d) => v.visit_defined(d),
+        Expr::Nameof(n) => v.visit_nameof(n),
+        Expr::Addressof(a) => v.visit_addressof(a),
+    }
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(v: &mut V, b: &BinaryOperation) {
+    v.visit_expr(&b.left);
+    v.visit_expr(&b.right);
+}
+
+pub fn walk_unary<V: Visitor + ?Sized>(v: &mut V, u: &UnaryOperation) {
+    v.visit_expr(&u.operand);
+}
+
+pub fn walk_postfix<V: Visitor + ?Sized>(v: &mut V, p: &PostfixOperation) {
+    v.visit_expr(&p.operand);
+}
+
+pub fn walk_ternary<V: Visitor + ?Sized>(v: &mut V, t: &TernaryOperation) {
+    v.visit_expr(&t.condition);
+    v.visit_expr(&t.then);
+    v.visit_expr(&t.otherwise);
+}
+
+pub fn walk_tag_override<V: Visitor + ?Sized>(v: &mut V, t: &TagOverride) {
+    v.visit_tag(&t.tag);
+    v.visit_expr(&t.operand);
+}
+
+pub fn walk_array_index<V: Visitor + ?Sized>(v: &mut V, a: &ArrayIndex) {
+    v.visit_expr(&a.array);
+    v.visit_expr(&a.index);
+}
+
+pub fn walk_array_initializer<V: Visitor + ?Sized>(v: &mut V, a: &ArrayInitializer) {
+    for element in &a.elements {
+        v.visit_expr(element);
+    }
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(v: &mut V, call: &Call) {
+    v.visit_symbol(&call.function);
+    for argument in &call.arguments {
+        v.visit_argument(argument);
+    }
+}
+
+pub fn walk_argument<V: Visitor + ?Sized>(v: &mut V, argument: &Argument) {
+    if let Some(value) = &argument.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_sizeof<V: Visitor + ?Sized>(v: &mut V, s: &Sizeof) {
+    v.visit_symbol(&s.symbol);
+    for dimension in s.dimensions.iter().flatten() {
+        v.visit_expr(dimension);
+    }
+}
+
+pub fn walk_tagof<V: Visitor + ?Sized>(v: &mut V, t: &Tagof) {
+    match &t.operand {
+        TagofOperand::Symbol(s) => v.visit_symbol(s),
+        TagofOperand::Tag(tag) => v.visit_tag(tag),
+    }
+    for dimension in t.dimensions.iter().flatten() {
+        v.visit_expr(dimension);
+    }
+}
+
+pub fn walk_char<V: Visitor + ?Sized>(v: &mut V, c: &Char) {
+    v.visit_expr(&c.operand);
+}
+
+pub fn walk_defined<V: Visitor + ?Sized>(v: &mut V, d: &Defined) {
+    v.visit_symbol(&d.symbol);
+}
+
+pub fn walk_nameof<V: Visitor + ?Sized>(v: &mut V, n: &Nameof) {
+    v.visit_symbol(&n.symbol);
+}
+
+pub fn walk_addressof<V: Visitor + ?Sized>(v: &mut V, a: &Addressof) {
+    v.visit_symbol(&a.symbol);
+}
+
+pub fn walk_literal<V: Visitor + ?Sized>(v: &mut V, literal: &Literal) {
+    if let Some(tag) = &literal.tag {
+        v.visit_tag(tag);
+    }
+}
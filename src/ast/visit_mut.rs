@@ -0,0 +1,576 @@
+use crate::ast::Addressof;
+use crate::ast::Argument;
+use crate::ast::ArrayIndex;
+use crate::ast::ArrayInitializer;
+use crate::ast::Assert;
+use crate::ast::BinaryOperation;
+use crate::ast::Block;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::CaseValue;
+use crate::ast::Char;
+use crate::ast::CompilationUnit;
+use crate::ast::Declaration;
+use crate::ast::Declarator;
+use crate::ast::Defined;
+use crate::ast::DoWhile;
+use crate::ast::Emit;
+use crate::ast::EmitInstruction;
+use crate::ast::EmitOperand;
+use crate::ast::EnumDecl;
+use crate::ast::EnumMember;
+use crate::ast::Exit;
+use crate::ast::Expr;
+use crate::ast::For;
+use crate::ast::FunctionDecl;
+use crate::ast::Goto;
+use crate::ast::If;
+use crate::ast::Item;
+use crate::ast::Label;
+use crate::ast::Literal;
+use crate::ast::Nameof;
+use crate::ast::NativeBinding;
+use crate::ast::OperatorDecl;
+use crate::ast::Parameter;
+use crate::ast::PostfixOperation;
+use crate::ast::Return;
+use crate::ast::Sizeof;
+use crate::ast::Sleep;
+use crate::ast::State;
+use crate::ast::StateSpec;
+use crate::ast::Stmt;
+use crate::ast::Switch;
+use crate::ast::Symbol;
+use crate::ast::Tag;
+use crate::ast::TagOverride;
+use crate::ast::Tagof;
+use crate::ast::TagofOperand;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::While;
+use crate::token::Span;
+
+/// VisitMut is the mutable counterpart of Visitor, for passes that rewrite
+/// the AST in place. Each `visit_*_mut` method defaults to the matching
+/// `walk_*_mut` function.
+pub trait VisitMut {
+    fn visit_unit_mut(&mut self, unit: &mut CompilationUnit) {
+        walk_unit_mut(self, unit)
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item)
+    }
+
+    fn visit_declaration_mut(&mut self, declaration: &mut Declaration) {
+        walk_declaration_mut(self, declaration)
+    }
+
+    fn visit_declarator_mut(&mut self, declarator: &mut Declarator) {
+        walk_declarator_mut(self, declarator)
+    }
+
+    fn visit_function_mut(&mut self, function: &mut FunctionDecl) {
+        walk_function_mut(self, function)
+    }
+
+    fn visit_operator_mut(&mut self, operator: &mut OperatorDecl) {
+        walk_operator_mut(self, operator)
+    }
+
+    fn visit_parameter_mut(&mut self, parameter: &mut Parameter) {
+        walk_parameter_mut(self, parameter)
+    }
+
+    fn visit_enum_mut(&mut self, e: &mut EnumDecl) {
+        walk_enum_mut(self, e)
+    }
+
+    fn visit_enum_member_mut(&mut self, member: &mut EnumMember) {
+        walk_enum_member_mut(self, member)
+    }
+
+    fn visit_native_binding_mut(&mut self, _native: &mut NativeBinding) {}
+
+    fn visit_state_spec_mut(&mut self, _states: &mut StateSpec) {}
+
+    fn visit_emit_mut(&mut self, emit: &mut Emit) {
+        walk_emit_mut(self, emit)
+    }
+
+    fn visit_emit_instruction_mut(&mut self, instruction: &mut EmitInstruction) {
+        walk_emit_instruction_mut(self, instruction)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, statement: &mut Stmt) {
+        walk_stmt_mut(self, statement)
+    }
+
+    fn visit_empty_mut(&mut self, _span: &mut Span) {}
+
+    fn visit_break_mut(&mut self, _span: &mut Span) {}
+
+    fn visit_continue_mut(&mut self, _span: &mut Span) {}
+
+    fn visit_return_mut(&mut self, r: &mut Return) {
+        walk_return_mut(self, r)
+    }
+
+    fn visit_exit_mut(&mut self, e: &mut Exit) {
+        walk_exit_mut(self, e)
+    }
+
+    fn visit_sleep_mut(&mut self, s: &mut Sleep) {
+        walk_sleep_mut(self, s)
+    }
+
+    fn visit_assert_mut(&mut self, a: &mut Assert) {
+        walk_assert_mut(self, a)
+    }
+
+    fn visit_label_mut(&mut self, _label: &mut Label) {}
+
+    fn visit_goto_mut(&mut self, _goto: &mut Goto) {}
+
+    fn visit_if_mut(&mut self, i: &mut If) {
+        walk_if_mut(self, i)
+    }
+
+    fn visit_while_mut(&mut self, w: &mut While) {
+        walk_while_mut(self, w)
+    }
+
+    fn visit_do_while_mut(&mut self, d: &mut DoWhile) {
+        walk_do_while_mut(self, d)
+    }
+
+    fn visit_for_mut(&mut self, f: &mut For) {
+        walk_for_mut(self, f)
+    }
+
+    fn visit_switch_mut(&mut self, s: &mut Switch) {
+        walk_switch_mut(self, s)
+    }
+
+    fn visit_case_mut(&mut self, case: &mut Case) {
+        walk_case_mut(self, case)
+    }
+
+    fn visit_state_mut(&mut self, state: &mut State) {
+        walk_state_mut(self, state)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_binary_mut(&mut self, b: &mut BinaryOperation) {
+        walk_binary_mut(self, b)
+    }
+
+    fn visit_unary_mut(&mut self, u: &mut UnaryOperation) {
+        walk_unary_mut(self, u)
+    }
+
+    fn visit_postfix_mut(&mut self, p: &mut PostfixOperation) {
+        walk_postfix_mut(self, p)
+    }
+
+    fn visit_ternary_mut(&mut self, t: &mut TernaryOperation) {
+        walk_ternary_mut(self, t)
+    }
+
+    fn visit_tag_override_mut(&mut self, t: &mut TagOverride) {
+        walk_tag_override_mut(self, t)
+    }
+
+    fn visit_array_index_mut(&mut self, a: &mut ArrayIndex) {
+        walk_array_index_mut(self, a)
+    }
+
+    fn visit_array_initializer_mut(&mut self, a: &mut ArrayInitializer) {
+        walk_array_initializer_mut(self, a)
+    }
+
+    fn visit_call_mut(&mut self, call: &mut Call) {
+        walk_call_mut(self, call)
+    }
+
+    fn visit_argument_mut(&mut self, argument: &mut Argument) {
+        walk_argument_mut(self, argument)
+    }
+
+    fn visit_sizeof_mut(&mut self, s: &mut Sizeof) {
+        walk_sizeof_mut(self, s)
+    }
+
+    fn visit_tagof_mut(&mut self, t: &mut Tagof) {
+        walk_tagof_mut(self, t)
+    }
+
+    fn visit_char_mut(&mut self, c: &mut Char) {
+        walk_char_mut(self, c)
+    }
+
+    fn visit_defined_mut(&mut self, d: &mut Defined) {
+        walk_defined_mut(self, d)
+    }
+
+    fn visit_nameof_mut(&mut self, n: &mut Nameof) {
+        walk_nameof_mut(self, n)
+    }
+
+    fn visit_addressof_mut(&mut self, a: &mut Addressof) {
+        walk_addressof_mut(self, a)
+    }
+
+    fn visit_literal_mut(&mut self, literal: &mut Literal) {
+        walk_literal_mut(self, literal)
+    }
+
+    fn visit_symbol_mut(&mut self, _symbol: &mut Symbol) {}
+
+    fn visit_tag_mut(&mut self, _tag: &mut Tag) {}
+}
+
+pub fn walk_unit_mut<V: VisitMut + ?Sized>(v: &mut V, unit: &mut CompilationUnit) {
+    for item in &mut unit.items {
+        v.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitMut + ?Sized>(v: &mut V, item: &mut Item) {
+    match item {
+        Item::Declaration(d) => v.visit_declaration_mut(d),
+        Item::Function(f) => v.visit_function_mut(f),
+        Item::Operator(o) => v.visit_operator_mut(o),
+        Item::Enum(e) => v.visit_enum_mut(e),
+        Item::Emit(e) => v.visit_emit_mut(e),
+    }
+}
+
+pub fn walk_declaration_mut<V: VisitMut + ?Sized>(v: &mut V, declaration: &mut Declaration) {
+    for declarator in &mut declaration.declarators {
+        v.visit_declarator_mut(declarator);
+    }
+}
+
+pub fn walk_declarator_mut<V: VisitMut + ?Sized>(v: &mut V, declarator: &mut Declarator) {
+    if let Some(tag) = &mut declarator.tag {
+        v.visit_tag_mut(tag);
+    }
+    for dimension in declarator.dimensions.iter_mut().flatten() {
+        v.visit_expr_mut(dimension);
+    }
+    if let Some(initializer) = &mut declarator.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_function_mut<V: VisitMut + ?Sized>(v: &mut V, function: &mut FunctionDecl) {
+    if let Some(tag) = &mut function.tag {
+        v.visit_tag_mut(tag);
+    }
+    for parameter in &mut function.parameters {
+        v.visit_parameter_mut(parameter);
+    }
+    if let Some(native) = &mut function.native {
+        v.visit_native_binding_mut(native);
+    }
+    if let Some(states) = &mut function.states {
+        v.visit_state_spec_mut(states);
+    }
+    if let Some(body) = &mut function.body {
+        v.visit_block_mut(body);
+    }
+}
+
+pub fn walk_operator_mut<V: VisitMut + ?Sized>(v: &mut V, operator: &mut OperatorDecl) {
+    if let Some(tag) = &mut operator.tag {
+        v.visit_tag_mut(tag);
+    }
+    for parameter in &mut operator.parameters {
+        v.visit_parameter_mut(parameter);
+    }
+    if let Some(native) = &mut operator.native {
+        v.visit_native_binding_mut(native);
+    }
+    if let Some(body) = &mut operator.body {
+        v.visit_block_mut(body);
+    }
+}
+
+pub fn walk_parameter_mut<V: VisitMut + ?Sized>(v: &mut V, parameter: &mut Parameter) {
+    for tag in &mut parameter.tags {
+        v.visit_tag_mut(tag);
+    }
+    for dimension in parameter.dimensions.iter_mut().flatten() {
+        v.visit_expr_mut(dimension);
+    }
+    if let Some(default) = &mut parameter.default {
+        v.visit_expr_mut(default);
+    }
+}
+
+pub fn walk_enum_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut EnumDecl) {
+    if let Some(tag) = &mut e.tag {
+        v.visit_tag_mut(tag);
+    }
+    v.visit_expr_mut(&mut e.increment.value);
+    for member in &mut e.members {
+        v.visit_enum_member_mut(member);
+    }
+}
+
+pub fn walk_enum_member_mut<V: VisitMut + ?Sized>(v: &mut V, member: &mut EnumMember) {
+    if let Some(tag) = &mut member.tag {
+        v.visit_tag_mut(tag);
+    }
+    if let Some(size) = &mut member.size {
+        v.visit_expr_mut(size);
+    }
+    if let Some(value) = &mut member.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_emit_mut<V: VisitMut + ?Sized>(v: &mut V, emit: &mut Emit) {
+    for instruction in &mut emit.instructions {
+        v.visit_emit_instruction_mut(instruction);
+    }
+}
+
+pub fn walk_emit_instruction_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    instruction: &mut EmitInstruction,
+) {
+    for operand in &mut instruction.operands {
+        if let EmitOperand::Symbol(symbol) = operand {
+            v.visit_symbol_mut(symbol);
+        }
+    }
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(v: &mut V, block: &mut Block) {
+    for statement in &mut block.statements {
+        v.visit_stmt_mut(statement);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, statement: &mut Stmt) {
+    match statement {
+        Stmt::Block(b) => v.visit_block_mut(b),
+        Stmt::Empty(span) => v.visit_empty_mut(span),
+        Stmt::Declaration(d) => v.visit_declaration_mut(d),
+        Stmt::Expr(e) => v.visit_expr_mut(e),
+        Stmt::If(i) => v.visit_if_mut(i),
+        Stmt::While(w) => v.visit_while_mut(w),
+        Stmt::DoWhile(d) => v.visit_do_while_mut(d),
+        Stmt::For(f) => v.visit_for_mut(f),
+        Stmt::Switch(s) => v.visit_switch_mut(s),
+        Stmt::Break(span) => v.visit_break_mut(span),
+        Stmt::Continue(span) => v.visit_continue_mut(span),
+        Stmt::Return(r) => v.visit_return_mut(r),
+        Stmt::Exit(e) => v.visit_exit_mut(e),
+        Stmt::Sleep(s) => v.visit_sleep_mut(s),
+        Stmt::Assert(a) => v.visit_assert_mut(a),
+        Stmt::Label(l) => v.visit_label_mut(l),
+        Stmt::Goto(g) => v.visit_goto_mut(g),
+        Stmt::State(s) => v.visit_state_mut(s),
+        Stmt::Emit(e) => v.visit_emit_mut(e),
+    }
+}
+
+pub fn walk_return_mut<V: VisitMut + ?Sized>(v: &mut V, r: &mut Return) {
+    if let Some(value) = &mut r.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_exit_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut Exit) {
+    if let Some(value) = &mut e.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_sleep_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Sleep) {
+    if let Some(value) = &mut s.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_assert_mut<V: VisitMut + ?Sized>(v: &mut V, a: &mut Assert) {
+    v.visit_expr_mut(&mut a.condition);
+}
+
+pub fn walk_if_mut<V: VisitMut + ?Sized>(v: &mut V, i: &mut If) {
+    v.visit_expr_mut(&mut i.condition);
+    v.visit_stmt_mut(&mut i.then);
+    if let Some(otherwise) = &mut i.otherwise {
+        v.visit_stmt_mut(otherwise);
+    }
+}
+
+pub fn walk_while_mut<V: VisitMut + ?Sized>(v: &mut V, w: &mut While) {
+    v.visit_expr_mut(&mut w.condition);
+    v.visit_stmt_mut(&mut w.body);
+}
+
+pub fn walk_do_while_mut<V: VisitMut + ?Sized>(v: &mut V, d: &mut DoWhile) {
+    v.visit_stmt_mut(&mut d.body);
+    v.visit_expr_mut(&mut d.condition);
+}
+
+pub fn walk_for_mut<V: VisitMut + ?Sized>(v: &mut V, f: &mut For) {
+    if let Some(init) = &mut f.init {
+        v.visit_stmt_mut(init);
+    }
+    if let Some(condition) = &mut f.condition {
+        v.visit_expr_mut(condition);
+    }
+    if let Some(increment) = &mut f.increment {
+        v.visit_expr_mut(increment);
+    }
+    v.visit_stmt_mut(&mut f.body);
+}
+
+pub fn walk_switch_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Switch) {
+    v.visit_expr_mut(&mut s.value);
+    for case in &mut s.cases {
+        v.visit_case_mut(case);
+    }
+    if let Some(default) = &mut s.default {
+        v.visit_stmt_mut(default);
+    }
+}
+
+pub fn walk_case_mut<V: VisitMut + ?Sized>(v: &mut V, case: &mut Case) {
+    for value in &mut case.values {
+        match value {
+            CaseValue::Value(e) => v.visit_expr_mut(e),
+            CaseValue::Range(low, high) => {
+                v.visit_expr_mut(low);
+                v.visit_expr_mut(high);
+            }
+        }
+    }
+    v.visit_stmt_mut(&mut case.body);
+}
+
+pub fn walk_state_mut<V: VisitMut + ?Sized>(v: &mut V, state: &mut State) {
+    if let Some(condition) = &mut state.condition {
+        v.visit_expr_mut(condition);
+    }
+}
+
+pub fn walk_expr_mut<V: VisitMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Literal(l) => v.visit_literal_mut(l),
+        Expr::Symbol(s) => v.visit_symbol_mut(s),
+        Expr::ArrayInitializer(a) => v.visit_array_initializer_mut(a),
+        Expr::BinaryOperation(b) => v.visit_binary_mut(b),
+        Expr::UnaryOperation(u) => v.visit_unary_mut(u),
+        Expr::PostfixOperation(p) => v.visit_postfix_mut(p),
+        Expr::TernaryOperation(t) => v.visit_ternary_mut(t),
+        Expr::TagOverride(t) => v.visit_tag_override_mut(t),
+        Expr::ArrayIndex(a) => v.visit_array_index_mut(a),
+        Expr::Call(c) => v.visit_call_mut(c),
+        Expr::Sizeof(s) => v.visit_sizeof_mut(s),
+        Expr::Tagof(t) => v.visit_tagof_mut(t),
+        Expr::Char(c) => v.visit_char_mut(c),
+        Expr::Defined(d) => v.visit_defined_mut(d),
+        Expr::Nameof(n) => v.visit_nameof_mut(n),
+        Expr::Addressof(a) => v.visit_addressof_mut(a),
+    }
+}
+
+pub fn walk_binary_mut<V: VisitMut + ?Sized>(v: &mut V, b: &mut BinaryOperation) {
+    v.visit_expr_mut(&mut b.left);
+    v.visit_expr_mut(&mut b.right);
+}
+
+pub fn walk_unary_mut<V: VisitMut + ?Sized>(v: &mut V, u: &mut UnaryOperation) {
+    v.visit_expr_mut(&mut u.operand);
+}
+
+pub fn walk_postfix_mut<V: VisitMut + ?Sized>(v: &mut V, p: &mut PostfixOperation) {
+    v.visit_expr_mut(&mut p.operand);
+}
+
+pub fn walk_ternary_mut<V: VisitMut + ?Sized>(v: &mut V, t: &mut TernaryOperation) {
+    v.visit_expr_mut(&mut t.condition);
+    v.visit_expr_mut(&mut t.then);
+    v.visit_expr_mut(&mut t.otherwise);
+}
+
+pub fn walk_tag_override_mut<V: VisitMut + ?Sized>(v: &mut V, t: &mut TagOverride) {
+    v.visit_tag_mut(&mut t.tag);
+    v.visit_expr_mut(&mut t.operand);
+}
+
+pub fn walk_array_index_mut<V: VisitMut + ?Sized>(v: &mut V, a: &mut ArrayIndex) {
+    v.visit_expr_mut(&mut a.array);
+    v.visit_expr_mut(&mut a.index);
+}
+
+pub fn walk_array_initializer_mut<V: VisitMut + ?Sized>(v: &mut V, a: &mut ArrayInitializer) {
+    for element in &mut a.elements {
+        v.visit_expr_mut(element);
+    }
+}
+
+pub fn walk_call_mut<V: VisitMut + ?Sized>(v: &mut V, call: &mut Call) {
+    v.visit_symbol_mut(&mut call.function);
+    for argument in &mut call.arguments {
+        v.visit_argument_mut(argument);
+    }
+}
+
+pub fn walk_argument_mut<V: VisitMut + ?Sized>(v: &mut V, argument: &mut Argument) {
+    if let Some(value) = &mut argument.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_sizeof_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Sizeof) {
+    v.visit_symbol_mut(&mut s.symbol);
+    for dimension in s.dimensions.iter_mut().flatten() {
+        v.visit_expr_mut(dimension);
+    }
+}
+
+pub fn walk_tagof_mut<V: VisitMut + ?Sized>(v: &mut V, t: &mut Tagof) {
+    match &mut t.operand {
+        TagofOperand::Symbol(s) => v.visit_symbol_mut(s),
+        TagofOperand::Tag(tag) => v.visit_tag_mut(tag),
+    }
+    for dimension in t.dimensions.iter_mut().flatten() {
+        v.visit_expr_mut(dimension);
+    }
+}
+
+pub fn walk_char_mut<V: VisitMut + ?Sized>(v: &mut V, c: &mut Char) {
+    v.visit_expr_mut(&mut c.operand);
+}
+
+pub fn walk_defined_mut<V: VisitMut + ?Sized>(v: &mut V, d: &mut Defined) {
+    v.visit_symbol_mut(&mut d.symbol);
+}
+
+pub fn walk_nameof_mut<V: VisitMut + ?Sized>(v: &mut V, n: &mut Nameof) {
+    v.visit_symbol_mut(&mut n.symbol);
+}
+
+pub fn walk_addressof_mut<V: VisitMut + ?Sized>(v: &mut V, a: &mut Addressof) {
+    v.visit_symbol_mut(&mut a.symbol);
+}
+
+pub fn walk_literal_mut<V: VisitMut + ?Sized>(v: &mut V, literal: &mut Literal) {
+    if let Some(tag) = &mut literal.tag {
+        v.visit_tag_mut(tag);
+    }
+}
@@ -1,12 +1,1699 @@
 #[cfg(test)]
+use crate::ast::Block;
+#[cfg(test)]
+use crate::ast::CaseValue;
+#[cfg(test)]
+use crate::ast::Constants;
+#[cfg(test)]
+use crate::ast::Declaration;
+#[cfg(test)]
+use crate::ast::Emit;
+#[cfg(test)]
+use crate::ast::EmitOperand;
+#[cfg(test)]
+use crate::ast::Expr;
+#[cfg(test)]
+use crate::ast::Item;
+#[cfg(test)]
+use crate::ast::LiteralValue;
+#[cfg(test)]
+use crate::ast::NativeBinding;
+#[cfg(test)]
 use crate::ast::Parser;
 #[cfg(test)]
+use crate::ast::Scope;
+#[cfg(test)]
+use crate::ast::Sign;
+#[cfg(test)]
+use crate::ast::Stmt;
+#[cfg(test)]
+use crate::ast::StorageClass;
+#[cfg(test)]
+use crate::ast::Symbol;
+#[cfg(test)]
+use crate::ast::TagofOperand;
+#[cfg(test)]
+use crate::error::ErrorCode;
+#[cfg(test)]
+use crate::error::ParseError;
+#[cfg(test)]
+use crate::intern::Name;
+#[cfg(test)]
 use crate::lexer::Lexer;
+#[cfg(test)]
+use crate::opcode::Opcode;
+#[cfg(test)]
+use crate::token::Position;
+#[cfg(test)]
+use crate::token::Span;
+#[cfg(test)]
+use crate::token::TokenType;
 
 #[test]
 fn test_basic() {
     let mut p = Parser::new(Lexer::new("new a = 4;").lex());
     p.parse().expect("failed to parse");
 
-    println!("{:?}", p.root);
+    println!("{:?}", p.unit);
+}
+
+/// messages joins the messages of every error from a parse.
+#[cfg(test)]
+fn messages(errors: &[ParseError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.message.clone())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+fn parse_emit(input: &str) -> Result<Vec<(Opcode, Vec<EmitOperand>)>, String> {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    p.parse().map_err(|e| messages(&e))?;
+
+    Ok(p.unit
+        .items
+        .into_iter()
+        .flat_map(|item| match item {
+            Item::Emit(e) => e.instructions,
+            item => panic!("expected emit, found {:?}", item),
+        })
+        .map(|i| (i.opcode, i.operands))
+        .collect())
+}
+
+/// symbol is the Symbol for `name`, written at `line` and `column`.
+#[cfg(test)]
+fn symbol(name: &str, line: i32, column: i32) -> Symbol {
+    Symbol {
+        name: Name::from(name),
+        span: Span {
+            start: Position { line, column },
+            end: Position {
+                line,
+                column: column + name.len() as i32,
+            },
+        },
+    }
+}
+
+#[test]
+fn test_emit_directive() {
+    assert_eq!(
+        parse_emit(
+            "
+#emit LOAD.S.pri 12
+#emit push.pri
+#emit const.alt buffer
+#emit push.c -4
+#emit jzer skip
+#emit sysreq.c printf
+#emit break
+#emit const.pri -2147483648
+#emit jump .
+#emit jrel .-8
+"
+        ),
+        Ok(vec![
+            (Opcode::LoadSPri, vec![EmitOperand::Number(12)]),
+            (Opcode::PushPri, vec![]),
+            (
+                Opcode::ConstAlt,
+                vec![EmitOperand::Symbol(symbol("buffer", 4, 17))]
+            ),
+            (Opcode::PushC, vec![EmitOperand::Number(-4)]),
+            (Opcode::Jzer, vec![EmitOperand::Label(Name::from("skip"))]),
+            (
+                Opcode::SysreqC,
+                vec![EmitOperand::Symbol(symbol("printf", 7, 16))]
+            ),
+            (Opcode::Break, vec![]),
+            (Opcode::ConstPri, vec![EmitOperand::Number(i32::MIN)]),
+            (Opcode::Jump, vec![EmitOperand::Relative(0)]),
+            (Opcode::Jrel, vec![EmitOperand::Relative(-8)]),
+        ])
+    );
+}
+
+#[test]
+fn test_emit_inline() {
+    assert_eq!(
+        parse_emit("__emit(load.pri x, add.c 4, stor.pri x);"),
+        Ok(vec![
+            (
+                Opcode::LoadPri,
+                vec![EmitOperand::Symbol(symbol("x", 1, 17))]
+            ),
+            (Opcode::AddC, vec![EmitOperand::Number(4)]),
+            (
+                Opcode::StorPri,
+                vec![EmitOperand::Symbol(symbol("x", 1, 38))]
+            ),
+        ])
+    );
+    assert_eq!(
+        parse_emit("__emit zero.pri;"),
+        Ok(vec![(Opcode::ZeroPri, vec![])])
+    );
+    assert_eq!(
+        parse_emit("__emit(jzer .+16, jump\n.);"),
+        Ok(vec![
+            (Opcode::Jzer, vec![EmitOperand::Relative(16)]),
+            (Opcode::Jump, vec![EmitOperand::Relative(0)]),
+        ])
+    );
+}
+
+#[test]
+fn test_emit_errors() {
+    assert_eq!(
+        parse_emit("#emit load.q 4"),
+        Err(String::from("unknown opcode `load.q`"))
+    );
+    assert_eq!(
+        parse_emit("#emit jump 4"),
+        Err(String::from(
+            "opcode `jump` expects Label operand, found Number(4)"
+        ))
+    );
+    assert_eq!(
+        parse_emit("#emit add.c x"),
+        Err(String::from(
+            "opcode `add.c` expects Number operand, found Symbol(\"x\")"
+        ))
+    );
+    assert_eq!(
+        parse_emit("#emit load.s.pri .+4"),
+        Err(String::from(
+            "opcode `load.s.pri` expects Offset operand, found Relative(4)"
+        ))
+    );
+    assert_eq!(
+        parse_emit("#emit const.pri -2147483649"),
+        Err(String::from("number `2147483649` is out of range"))
+    );
+    // an `#emit` instruction ends with its line
+    assert_eq!(
+        parse_emit("#emit load.pri\n#emit push.pri"),
+        Err(String::from(
+            "opcode `load.pri` expects Address operand, found end of line"
+        ))
+    );
+}
+
+/// sexp renders an expression as a fully parenthesised s-expression so tests
+/// can assert on precedence and associativity.
+#[cfg(test)]
+fn sexp(e: &Expr) -> String {
+    match e {
+        Expr::BinaryOperation(b) => {
+            format!("({} {} {})", b.operator, sexp(&b.left), sexp(&b.right))
+        }
+        Expr::UnaryOperation(u) => {
+            format!("({} {})", u.operator, sexp(&u.operand))
+        }
+        Expr::PostfixOperation(p) => {
+            format!("(post{} {})", p.operator, sexp(&p.operand))
+        }
+        Expr::TernaryOperation(t) => format!(
+            "(? {} {} {})",
+            sexp(&t.condition),
+            sexp(&t.then),
+            sexp(&t.otherwise)
+        ),
+        Expr::TagOverride(t) => format!("({}: {})", t.tag.name, sexp(&t.operand)),
+        Expr::ArrayIndex(a) => format!("([] {} {})", sexp(&a.array), sexp(&a.index)),
+        Expr::ArrayInitializer(a) => {
+            let mut elements: Vec<String> = a.elements.iter().map(sexp).collect();
+            if a.fill {
+                elements.push(String::from("..."));
+            }
+            format!("{{{}}}", elements.join(" "))
+        }
+        Expr::Call(c) => {
+            let mut s = format!("(call {}", c.function.name);
+            for a in &c.arguments {
+                s += " ";
+                if let Some(name) = &a.name {
+                    s += &format!(".{}=", name);
+                }
+                match &a.value {
+                    Some(value) => s += &sexp(value),
+                    None => s += "_",
+                }
+            }
+            s + ")"
+        }
+        Expr::Sizeof(s) => format!("(sizeof {}{})", s.symbol.name, dimensions(&s.dimensions)),
+        Expr::Tagof(t) => match &t.operand {
+            TagofOperand::Symbol(s) => format!("(tagof {}{})", s.name, dimensions(&t.dimensions)),
+            TagofOperand::Tag(tag) => format!("(tagof {}:)", tag.name),
+        },
+        Expr::Char(c) => format!("(char {})", sexp(&c.operand)),
+        Expr::Defined(d) => format!("(defined {})", d.symbol.name),
+        Expr::Nameof(n) => format!("(__nameof {})", n.symbol.name),
+        Expr::Addressof(a) => format!("(__addressof {})", a.symbol.name),
+        Expr::Symbol(s) => s.name.to_string(),
+        Expr::Literal(l) => {
+            let tag = match &l.tag {
+                Some(tag) if !l.implicit_tag => format!("{}:", tag.name),
+                _ => String::new(),
+            };
+            let sign = match l.sign {
+                Some(Sign::Positive) => "+",
+                Some(Sign::Negative) => "-",
+                None => "",
+            };
+            let value = match &l.value {
+                LiteralValue::Integer(i32::MIN) if !sign.is_empty() => {
+                    i32::MIN.unsigned_abs().to_string()
+                }
+                LiteralValue::Integer(v) => v.to_string(),
+                LiteralValue::Float(v) => format!("{:?}", v),
+                LiteralValue::Character(c) => format!("{:?}", c),
+                LiteralValue::String(s) => format!("{:?}", s),
+                LiteralValue::PackedString(s) => format!("!{:?}", s),
+                LiteralValue::Bool(b) => b.to_string(),
+            };
+            format!("{}{}{}", tag, sign, value)
+        }
+    }
+}
+
+#[cfg(test)]
+fn dimensions(dimensions: &[Option<Expr>]) -> String {
+    dimensions
+        .iter()
+        .map(|d| match d {
+            Some(e) => format!("[{}]", sexp(e)),
+            None => String::from("[]"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn parse_expr(input: &str) -> String {
+    match Parser::new(Lexer::new(input).lex()).parse_expression() {
+        Ok(e) => sexp(&e),
+        Err(e) => format!("error: {}", e.message),
+    }
+}
+
+#[test]
+fn test_expression_precedence() {
+    assert_eq!(parse_expr("1 + 2 * 3"), "(+ 1 (* 2 3))");
+    assert_eq!(parse_expr("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+    assert_eq!(parse_expr("a << 1 + b"), "(<< a (+ 1 b))");
+    assert_eq!(parse_expr("a & b == c"), "(== (& a b) c)");
+    assert_eq!(parse_expr("a | b < c ^ d"), "(< (| a b) (^ c d))");
+    assert_eq!(parse_expr("a < b == c > d"), "(== (< a b) (> c d))");
+    assert_eq!(parse_expr("a || b && c"), "(|| a (&& b c))");
+    assert_eq!(parse_expr("a >>> 2 >> 1"), "(>> (>>> a 2) 1)");
+}
+
+#[test]
+fn test_expression_associativity() {
+    assert_eq!(parse_expr("a - b - c"), "(- (- a b) c)");
+    assert_eq!(parse_expr("a = b += c"), "(= a (+= b c))");
+    assert_eq!(parse_expr("a ? b : c ? d : e"), "(? a b (? c d e))");
+    assert_eq!(parse_expr("a = b, c = d"), "(, (= a b) (= c d))");
+    assert_eq!(parse_expr("a = b ? c : d"), "(= a (? b c d))");
+}
+
+#[test]
+fn test_expression_unary() {
+    assert_eq!(parse_expr("-a * b"), "(* (- a) b)");
+    assert_eq!(parse_expr("!a++"), "(! (post++ a))");
+    assert_eq!(parse_expr("~-a[1]"), "(~ (- ([] a 1)))");
+    assert_eq!(parse_expr("--a[i][j]"), "(-- ([] ([] a i) j))");
+    assert_eq!(parse_expr("sizeof a + 1"), "(+ (sizeof a) 1)");
+    assert_eq!(
+        parse_expr("defined a && tagof b"),
+        "(&& (defined a) (tagof b))"
+    );
+    assert_eq!(parse_expr("10 char"), "(char 10)");
+}
+
+#[test]
+fn test_expression_compile_time() {
+    assert_eq!(parse_expr("sizeof a[]"), "(sizeof a[])");
+    assert_eq!(parse_expr("sizeof a[0][]"), "(sizeof a[0][])");
+    assert_eq!(parse_expr("sizeof(E_DATA) * 2"), "(* (sizeof E_DATA) 2)");
+    assert_eq!(
+        parse_expr("sizeof a[E_NAME] - 1"),
+        "(- (sizeof a[E_NAME]) 1)"
+    );
+    assert_eq!(parse_expr("tagof(Float:)"), "(tagof Float:)");
+    assert_eq!(parse_expr("tagof a"), "(tagof a)");
+    assert_eq!(parse_expr("tagof((a[]))"), "(tagof a[])");
+    assert_eq!(parse_expr("c ? tagof a : b"), "(? c (tagof a) b)");
+    assert_eq!(parse_expr("10 char + 1"), "(+ (char 10) 1)");
+    assert_eq!(parse_expr("!defined Foo"), "(! (defined Foo))");
+    assert_eq!(parse_expr("defined(Foo)"), "(defined Foo)");
+    assert_eq!(parse_expr("__nameof(Func)"), "(__nameof Func)");
+    assert_eq!(parse_expr("__addressof(Func)"), "(__addressof Func)");
+
+    assert_eq!(
+        parse_expr("sizeof 5"),
+        "error: expected symbol, found Integer"
+    );
+    assert_eq!(parse_expr("sizeof(a"), "error: expected `)`, found End");
+    assert_eq!(parse_expr("tagof(Float:"), "error: expected `)`, found End");
+}
+
+/// Symbols is what a semantic pass might know about `new a[10][3]`,
+/// `new Float:f` and `enum { E_NAME[24] }`.
+#[cfg(test)]
+struct Symbols;
+
+#[cfg(test)]
+impl Constants for Symbols {
+    fn value(&self, name: &Name) -> Option<i32> {
+        match name.as_str() {
+            "MAX" => Some(24),
+            _ => None,
+        }
+    }
+
+    fn size(&self, name: &Name, dimension: usize) -> Option<i32> {
+        match (name.as_str(), dimension) {
+            ("a", 0) => Some(10),
+            ("a", 1) => Some(3),
+            ("E_NAME", 0) => Some(24),
+            _ => None,
+        }
+    }
+
+    fn tag(&self, tag: &Name) -> Option<i32> {
+        match tag.as_str() {
+            "_" => Some(0),
+            "Float" => Some(1),
+            _ => None,
+        }
+    }
+
+    fn tag_of(&self, name: &Name) -> Option<Name> {
+        match name.as_str() {
+            "a" => Some(Name::from("_")),
+            "f" => Some(Name::from("Float")),
+            _ => None,
+        }
+    }
+
+    fn defined(&self, name: &Name) -> Option<bool> {
+        Some(*name == "a" || *name == "f" || *name == "MAX")
+    }
+}
+
+#[test]
+fn test_expression_constant_value() {
+    let value = |input: &str| {
+        let e = Parser::new(Lexer::new(input).lex())
+            .parse_expression()
+            .expect("failed to parse");
+        e.constant_value(&Symbols)
+    };
+
+    assert_eq!(value("sizeof a"), Some(10));
+    assert_eq!(value("sizeof a[]"), Some(3));
+    assert_eq!(value("sizeof a[E_NAME] - 1"), Some(23));
+    assert_eq!(value("sizeof b"), None);
+    assert_eq!(value("tagof f"), Some(1));
+    assert_eq!(value("tagof(Float:) + tagof a"), Some(1));
+    assert_eq!(value("tagof(bool:)"), None);
+    assert_eq!(value("MAX char"), Some(6));
+    assert_eq!(value("defined a + defined b"), Some(1));
+    assert_eq!(value("__nameof(a)"), None);
+    assert_eq!(value("__addressof(a)"), None);
+    assert_eq!(value("-2147483648"), Some(i32::MIN));
+    assert_eq!(value("-0x80000000"), Some(i32::MIN));
+    assert_eq!(value("1 - -2147483648"), None);
+}
+
+#[test]
+fn test_expression_min_cell() {
+    // only a unary `-` can spell i32::MIN in decimal
+    assert_eq!(parse_expr("x = -2147483648"), "(= x -2147483648)");
+    assert_eq!(parse_expr("b - -2147483648"), "(- b -2147483648)");
+    assert_eq!(
+        parse_expr("b -2147483648"),
+        "error: number `2147483648` is out of range"
+    );
+    assert_eq!(
+        parse_expr("b - 2147483648"),
+        "error: number `2147483648` is out of range"
+    );
+}
+
+#[test]
+fn test_expression_tags() {
+    assert_eq!(parse_expr("Float:a + b"), "(+ (Float: a) b)");
+    assert_eq!(parse_expr("_:-a"), "(_: (- a))");
+    assert_eq!(parse_expr("a ? b : c"), "(? a b c)");
+    assert_eq!(
+        parse_expr("a ? (Float:b) : Float:c"),
+        "(? a (Float: b) (Float: c))"
+    );
+}
+
+#[test]
+fn test_expression_errors() {
+    assert_eq!(
+        parse_expr("(1 + 2;"),
+        "error: expected `)`, found Semicolon"
+    );
+    assert_eq!(parse_expr("a ? b;"), "error: expected `:`, found Semicolon");
+    assert_eq!(
+        parse_expr("1 + ;"),
+        "error: expected expression, found Semicolon"
+    );
+}
+
+#[test]
+fn test_literal_scalar() {
+    assert_eq!(parse_expr("5"), "5");
+    assert_eq!(parse_expr("-5"), "-5");
+    assert_eq!(parse_expr("_:-1"), "_:-1");
+    assert_eq!(parse_expr("Float:5"), "Float:5");
+    assert_eq!(parse_expr("bool:+1"), "bool:+1");
+    assert_eq!(parse_expr("'a'"), "'a'");
+    assert_eq!(parse_expr("\"hi\\n\""), "\"hi\\n\"");
+    assert_eq!(parse_expr("!\"packed\""), "!\"packed\"");
+    assert_eq!(parse_expr("-1 - -2"), "(- -1 -2)");
+    assert_eq!(parse_expr("a ? true : false"), "(? a true false)");
+    assert_eq!(parse_expr("-\"a\""), "(- \"a\")");
+    assert_eq!(parse_expr("!\"a\" !\"b\""), "!\"ab\"");
+    assert_eq!(
+        parse_expr("4294967295"),
+        "error: number `4294967295` is out of range"
+    );
+    assert_eq!(
+        parse_expr("x = 1.0e99"),
+        "error: number `1.0e99` is out of range"
+    );
+    assert_eq!(parse_expr("x = 1.0e+5"), "error: malformed number `1.0e`");
+    assert_eq!(parse_expr("0x"), "error: malformed number `0x`");
+    assert_eq!(
+        parse_expr("f(\"a\" \"b\", !\"c\" \"d\")"),
+        "error: expected `,` or `)`, found Literal"
+    );
+}
+
+#[test]
+fn test_literal_implicit_tag() {
+    let tags = |input: &str| match Parser::new(Lexer::new(input).lex()).parse_expression() {
+        Ok(Expr::Literal(l)) => (l.tag.map(|t| t.name.to_string()), l.implicit_tag),
+        e => panic!("expected literal, found {:?}", e),
+    };
+
+    assert_eq!(tags("5.5"), (Some(String::from("Float")), true));
+    assert_eq!(tags("-5.5"), (Some(String::from("Float")), true));
+    assert_eq!(tags("_:5.5"), (Some(String::from("_")), false));
+    assert_eq!(tags("Float:5"), (Some(String::from("Float")), false));
+    assert_eq!(tags("true"), (Some(String::from("bool")), true));
+    assert_eq!(tags("5"), (None, false));
+}
+
+/// render renders a top-level item, with declarations as
+/// `storage tag:name[dims] = initializer, ...`.
+#[cfg(test)]
+fn render(item: &Item) -> String {
+    match item {
+        Item::Declaration(d) => render_declaration(d),
+        Item::Enum(e) => {
+            let known = |v: Option<i32>| match v {
+                Some(v) => v.to_string(),
+                None => String::from("?"),
+            };
+            let mut s = String::from("enum ");
+            if let Some(tag) = &e.tag {
+                s += &format!("{}:", tag.name);
+            }
+            if let Some(name) = &e.name {
+                s += &format!("{} ", name);
+            }
+            s += &format!("({} {}) ", e.increment.operator, sexp(&e.increment.value));
+            let members: Vec<String> = e
+                .members
+                .iter()
+                .map(|m| {
+                    let mut s = String::new();
+                    if let Some(tag) = &m.tag {
+                        s += &format!("{}:", tag.name);
+                    }
+                    s += &m.name;
+                    if let Some(size) = &m.size {
+                        s += &format!("[{}]", sexp(size));
+                    }
+                    if let Some(value) = &m.value {
+                        s += &format!(" = {}", sexp(value));
+                    }
+                    format!("{}@{}", s, known(m.offset))
+                })
+                .collect();
+            format!("{}{{{}}} size {}", s, members.join(", "), known(e.size))
+        }
+        Item::Function(f) => {
+            let mut s = String::new();
+            for q in &f.qualifiers {
+                s += &format!("{:?} ", q).to_lowercase();
+            }
+            if let Some(tag) = &f.tag {
+                s += &format!("{}:", tag.name);
+            }
+            let parameters: Vec<String> = f
+                .parameters
+                .iter()
+                .map(|p| {
+                    let mut s = String::new();
+                    if p.constant {
+                        s += "const ";
+                    }
+                    if p.reference {
+                        s += "&";
+                    }
+                    let tags: Vec<&str> = p.tags.iter().map(|t| t.name.as_str()).collect();
+                    match tags.len() {
+                        0 => (),
+                        1 => s += &format!("{}:", tags[0]),
+                        _ => s += &format!("{{{}}}:", tags.join(",")),
+                    }
+                    s += if p.variadic { "..." } else { &p.name };
+                    for d in &p.dimensions {
+                        match d {
+                            Some(e) => s += &format!("[{}]", sexp(e)),
+                            None => s += "[]",
+                        }
+                    }
+                    if let Some(e) = &p.default {
+                        s += &format!(" = {}", sexp(e));
+                    }
+                    s
+                })
+                .collect();
+            s += &format!("{}({})", f.name, parameters.join(", "));
+            if let Some(spec) = &f.states {
+                let automaton = match &spec.automaton {
+                    Some(a) => format!("{}:", a),
+                    None => String::new(),
+                };
+                let states: Vec<&str> = spec.states.iter().map(|s| s.as_str()).collect();
+                s += &format!(" <{}{}>", automaton, states.join(", "));
+            }
+            match &f.native {
+                Some(NativeBinding::Index(i)) => s += &format!(" = {}", i),
+                Some(NativeBinding::Name(n)) => s += &format!(" = {}", n),
+                None => (),
+            }
+            if let Some(body) = &f.body {
+                s += &format!(" {}", render_block(body));
+            }
+            s
+        }
+        Item::Operator(o) => {
+            let mut s = String::new();
+            for q in &o.qualifiers {
+                s += &format!("{:?} ", q).to_lowercase();
+            }
+            let tag = match &o.tag {
+                Some(tag) => tag.name.as_str(),
+                None => "_",
+            };
+            s += &format!(
+                "{}:operator{}({})",
+                tag,
+                o.operator,
+                o.operand_tags().join(", ")
+            );
+            match &o.native {
+                Some(NativeBinding::Index(i)) => s += &format!(" = {}", i),
+                Some(NativeBinding::Name(n)) => s += &format!(" = {}", n),
+                None => (),
+            }
+            if let Some(body) = &o.body {
+                s += &format!(" {}", render_block(body));
+            }
+            s
+        }
+        Item::Emit(e) => render_emit(e),
+    }
+}
+
+#[cfg(test)]
+fn render_declaration(d: &Declaration) -> String {
+    let storage = match d.storage {
+        StorageClass::New => "new",
+        StorageClass::Static => "static",
+        StorageClass::Stock => "stock",
+        StorageClass::StaticStock => "static stock",
+        StorageClass::Const => "const",
+    };
+    let constant = if d.constant && d.storage != StorageClass::Const {
+        " const"
+    } else {
+        ""
+    };
+    let declarators: Vec<String> = d
+        .declarators
+        .iter()
+        .map(|v| {
+            let tag = match &v.tag {
+                Some(tag) => tag.name.as_str(),
+                None => "_",
+            };
+            let mut s = format!("{}:{}", tag, v.name);
+            for d in &v.dimensions {
+                match d {
+                    Some(e) => s += &format!("[{}]", sexp(e)),
+                    None => s += "[]",
+                }
+            }
+            if let Some(initializer) = &v.initializer {
+                s += &format!(" = {}", sexp(initializer));
+            }
+            s
+        })
+        .collect();
+    format!("{}{} {}", storage, constant, declarators.join(", "))
+}
+
+#[cfg(test)]
+fn render_block(b: &Block) -> String {
+    let statements: Vec<String> = b.statements.iter().map(render_stmt).collect();
+    format!("{{{}}}", statements.join(" "))
+}
+
+#[cfg(test)]
+fn render_emit(e: &Emit) -> String {
+    e.instructions
+        .iter()
+        .map(|i| format!("emit {};", i.opcode.mnemonic()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// render_stmt renders a statement in a compact, brace-delimited form.
+#[cfg(test)]
+fn render_stmt(statement: &Stmt) -> String {
+    let optional = |e: &Option<Expr>| match e {
+        Some(e) => format!(" {}", sexp(e)),
+        None => String::new(),
+    };
+    match statement {
+        Stmt::Block(b) => render_block(b),
+        Stmt::Empty(_) => String::from(";"),
+        Stmt::Declaration(d) => format!("{};", render_declaration(d)),
+        Stmt::Expr(e) => format!("{};", sexp(e)),
+        Stmt::If(i) => {
+            let mut s = format!("if {} {}", sexp(&i.condition), render_stmt(&i.then));
+            if let Some(otherwise) = &i.otherwise {
+                s += &format!(" else {}", render_stmt(otherwise));
+            }
+            s
+        }
+        Stmt::While(w) => format!("while {} {}", sexp(&w.condition), render_stmt(&w.body)),
+        Stmt::DoWhile(d) => {
+            format!("do {} while {};", render_stmt(&d.body), sexp(&d.condition))
+        }
+        Stmt::For(f) => format!(
+            "for ({}{};{}) {}",
+            match &f.init {
+                Some(init) => render_stmt(init),
+                None => String::from(";"),
+            },
+            optional(&f.condition),
+            optional(&f.increment),
+            render_stmt(&f.body)
+        ),
+        Stmt::Switch(s) => {
+            let mut cases: Vec<String> = s
+                .cases
+                .iter()
+                .map(|c| {
+                    let values: Vec<String> = c
+                        .values
+                        .iter()
+                        .map(|v| match v {
+                            CaseValue::Value(e) => sexp(e),
+                            CaseValue::Range(low, high) => format!("{}..{}", sexp(low), sexp(high)),
+                        })
+                        .collect();
+                    format!("case {}: {}", values.join(", "), render_stmt(&c.body))
+                })
+                .collect();
+            if let Some(default) = &s.default {
+                cases.push(format!("default: {}", render_stmt(default)));
+            }
+            format!("switch {} {{{}}}", sexp(&s.value), cases.join(" "))
+        }
+        Stmt::Break(_) => String::from("break;"),
+        Stmt::Continue(_) => String::from("continue;"),
+        Stmt::Return(r) => format!("return{};", optional(&r.value)),
+        Stmt::Exit(e) => format!("exit{};", optional(&e.value)),
+        Stmt::Sleep(s) => format!("sleep{};", optional(&s.value)),
+        Stmt::Assert(a) => format!("assert {};", sexp(&a.condition)),
+        Stmt::Label(l) => format!("{}:", l.name),
+        Stmt::Goto(g) => format!("goto {};", g.label),
+        Stmt::State(st) => {
+            let mut s = String::from("state ");
+            if let Some(condition) = &st.condition {
+                s += &format!("({}) ", sexp(condition));
+            }
+            if let Some(automaton) = &st.automaton {
+                s += &format!("{}:", automaton);
+            }
+            format!("{}{};", s, st.name)
+        }
+        Stmt::Emit(e) => render_emit(e),
+    }
+}
+
+#[cfg(test)]
+fn parse_decl(input: &str) -> String {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    match p.parse() {
+        Ok(()) => p
+            .unit
+            .items
+            .iter()
+            .map(render)
+            .collect::<Vec<_>>()
+            .join("; "),
+        Err(e) => format!("error: {}", messages(&e)),
+    }
+}
+
+#[test]
+fn test_array_declarations() {
+    assert_eq!(parse_decl("new a[10];"), "new _:a[10]");
+    assert_eq!(parse_decl("new a[] = {1,2,3};"), "new _:a[] = {1 2 3}");
+    assert_eq!(
+        parse_decl("new a[3][] = {\"a\",\"bb\",\"ccc\"};"),
+        "new _:a[3][] = {\"a\" \"bb\" \"ccc\"}"
+    );
+    assert_eq!(
+        parse_decl("new a[MAX_PLAYERS][E_PLAYER_DATA];"),
+        "new _:a[MAX_PLAYERS][E_PLAYER_DATA]"
+    );
+    assert_eq!(
+        parse_decl("new a[10] = {1, 2, ...};"),
+        "new _:a[10] = {1 2 ...}"
+    );
+    assert_eq!(parse_decl("new a[10 char];"), "new _:a[(char 10)]");
+    assert_eq!(
+        parse_decl("new Float:m[2][2] = {{1.0, 0.0}, {0.0, 1.0}};"),
+        "new Float:m[2][2] = {{1.0 0.0} {0.0 1.0}}"
+    );
+    assert_eq!(parse_decl("new s[] = \"hi\";"), "new _:s[] = \"hi\"");
+    assert_eq!(
+        parse_decl("new s[] = \"a\" \"b\"\n    \"c\";"),
+        "new _:s[] = \"abc\""
+    );
+    assert_eq!(parse_decl("new a[4] = {};"), "new _:a[4] = {}");
+}
+
+#[test]
+fn test_array_declaration_errors() {
+    assert_eq!(
+        parse_decl("new a[];"),
+        "error: array `a` has an unsized dimension and no initializer"
+    );
+    assert_eq!(
+        parse_decl("new a[] = {...};"),
+        "error: `...` must follow at least one element"
+    );
+    assert_eq!(
+        parse_decl("new a[] = {1, ..., 2};"),
+        "error: expected `}`, found Comma"
+    );
+    assert_eq!(
+        parse_decl("new a[] = {1 2};"),
+        "error: expected `,` or `}`, found Integer"
+    );
+}
+
+#[test]
+fn test_declaration_storage_classes() {
+    assert_eq!(parse_decl("new a;"), "new _:a");
+    assert_eq!(parse_decl("new const a = 1;"), "new const _:a = 1");
+    assert_eq!(parse_decl("static a;"), "static _:a");
+    assert_eq!(parse_decl("static const a = 1;"), "static const _:a = 1");
+    assert_eq!(parse_decl("stock a;"), "stock _:a");
+    assert_eq!(parse_decl("static stock a;"), "static stock _:a");
+    assert_eq!(parse_decl("stock static a;"), "static stock _:a");
+    assert_eq!(parse_decl("const MAX = 10;"), "const _:MAX = 10");
+    assert_eq!(
+        parse_decl("new a, b = 2, Float:c;"),
+        "new _:a, _:b = 2, Float:c"
+    );
+    assert_eq!(
+        parse_decl("static stock const a[] = {1, 2}, b = 3;"),
+        "static stock const _:a[] = {1 2}, _:b = 3"
+    );
+}
+
+#[test]
+fn test_declaration_scope() {
+    let scopes = |input: &str| {
+        let mut p = Parser::new(Lexer::new(input).lex());
+        p.parse().expect("failed to parse");
+        p.unit
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Declaration(d) => d.scope,
+                item => panic!("expected declaration, found {:?}", item),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        scopes("new a; static b; stock c; static stock d; const e = 1;"),
+        vec![
+            Scope::Global,
+            Scope::File,
+            Scope::Global,
+            Scope::File,
+            Scope::Global
+        ]
+    );
+}
+
+#[test]
+fn test_declaration_errors() {
+    assert_eq!(
+        parse_decl("const a;"),
+        "error: constant `a` must be initialized"
+    );
+    assert_eq!(
+        parse_decl("new const a[3], b = 1;"),
+        "error: constant `a` must be initialized"
+    );
+    assert_eq!(
+        parse_decl("new a b;"),
+        "error: expected semicolon, found Symbol"
+    );
+}
+
+#[test]
+fn test_function_definitions() {
+    assert_eq!(parse_decl("main() {}"), "main() {}");
+    assert_eq!(
+        parse_decl("public OnPlayerConnect(playerid) { return playerid; }"),
+        "public OnPlayerConnect(playerid) {return playerid;}"
+    );
+    assert_eq!(
+        parse_decl("stock Float:Dist(Float:x, Float:y) { return x + y; }"),
+        "stock Float:Dist(Float:x, Float:y) {return (+ x y);}"
+    );
+    assert_eq!(
+        parse_decl("static stock bool:Check(&a, const b[], c[][4], d = 5) { { } }"),
+        "static stock bool:Check(&a, const b[], c[][4], d = 5) {{}}"
+    );
+    assert_eq!(
+        parse_decl("Print(const fmt[] = \"\", {Float,_}:...) {}"),
+        "Print(const fmt[] = \"\", {Float,_}:...) {}"
+    );
+    assert_eq!(
+        parse_decl("Set(&Float:v, {Float,_}:x = 0) {}"),
+        "Set(&Float:v, {Float,_}:x = 0) {}"
+    );
+    assert_eq!(
+        parse_decl("public OnGameModeInit() return 1;"),
+        "public OnGameModeInit() {return 1;}"
+    );
+    assert_eq!(
+        parse_decl("main() if (a) b(); else c();"),
+        "main() {if a (call b); else (call c);}"
+    );
+}
+
+#[test]
+fn test_function_prototypes() {
+    assert_eq!(
+        parse_decl("forward OnTimer(playerid);"),
+        "forward OnTimer(playerid)"
+    );
+    assert_eq!(
+        parse_decl("native printf(const format[], {Float,_}:...);"),
+        "native printf(const format[], {Float,_}:...)"
+    );
+    assert_eq!(
+        parse_decl("native Float:floatadd(Float:a, Float:b) = -1;"),
+        "native Float:floatadd(Float:a, Float:b) = -1"
+    );
+    assert_eq!(
+        parse_decl("native SendMsg(const msg[]) = SendClientMessage;"),
+        "native SendMsg(const msg[]) = SendClientMessage"
+    );
+    assert_eq!(
+        parse_decl("native Foo() = -2147483648;"),
+        "native Foo() = -2147483648"
+    );
+    assert_eq!(
+        parse_decl("native Foo() = -2147483649;"),
+        "error: number `2147483649` is out of range"
+    );
+    assert_eq!(
+        parse_decl("native Foo() = 2147483648;"),
+        "error: number `2147483648` is out of range"
+    );
+}
+
+#[test]
+fn test_function_errors() {
+    assert_eq!(
+        parse_decl("public stock Foo() {}"),
+        "error: invalid combination of qualifiers [Public, Stock]"
+    );
+    assert_eq!(
+        parse_decl("forward Foo() {}"),
+        "error: expected semicolon, found LeftBrace"
+    );
+    assert_eq!(
+        parse_decl("Foo();"),
+        "error: expected function body, found Semicolon"
+    );
+    assert_eq!(
+        parse_decl("Foo(..., a) {}"),
+        "error: `...` must be the last parameter"
+    );
+    assert_eq!(
+        parse_decl("Foo(&a[]) {}"),
+        "error: array parameter `a` is always passed by reference"
+    );
+    assert_eq!(parse_decl("Foo() {"), "error: unterminated block");
+    assert_eq!(
+        parse_decl("public a;"),
+        "error: expected function after `public`, found Symbol"
+    );
+}
+
+#[test]
+fn test_function_in_script() {
+    assert_eq!(
+        parse_decl(
+            "
+// Comment
+#include <a_samp>
+
+main() {
+    new a;
+    if(a == 3) {
+        a++;
+    } else if(a != 3) {
+        a--;
+    } else {
+        a = 0;
+    }
+}
+"
+        ),
+        "main() {new _:a; if (== a 3) {(post++ a);} else if (!= a 3) {(post-- a);} else {(= a 0);}}"
+    );
+}
+
+/// parse_body parses statements as the body of a function and renders them.
+#[cfg(test)]
+fn parse_body(input: &str) -> String {
+    let mut p = Parser::new(Lexer::new(&format!("f() {{ {} }}", input)).lex());
+    if let Err(e) = p.parse() {
+        return format!("error: {}", messages(&e));
+    }
+    let body = match &p.unit.items[0] {
+        Item::Function(f) => f.body.as_ref().unwrap(),
+        item => panic!("expected function, found {:?}", item),
+    };
+    body.statements
+        .iter()
+        .map(render_stmt)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn test_statement_loops() {
+    assert_eq!(
+        parse_body("while (i < 10) i++;"),
+        "while (< i 10) (post++ i);"
+    );
+    assert_eq!(
+        parse_body("do { i--; } while (i);"),
+        "do {(post-- i);} while i;"
+    );
+    assert_eq!(
+        parse_body("for (new i = 0; i < 10; i++) { continue; }"),
+        "for (new _:i = 0; (< i 10); (post++ i)) {continue;}"
+    );
+    assert_eq!(parse_body("for (;;) break;"), "for (;;) break;");
+    assert_eq!(
+        parse_body("for (i = 0, j = 1; ; ) {}"),
+        "for ((, (= i 0) (= j 1));;) {}"
+    );
+}
+
+#[test]
+fn test_statement_switch() {
+    assert_eq!(
+        parse_body(
+            "switch (a) { case 1: b = 1; case 2, 3: {} case 4..6, 8: ; default: return 0; }"
+        ),
+        "switch a {case 1: (= b 1); case 2, 3: {} case 4..6, 8: ; default: return 0;}"
+    );
+    assert_eq!(
+        parse_body("switch (a) { case X: {} }"),
+        "switch a {case X: {}}"
+    );
+    assert_eq!(
+        parse_body("switch (a) { default: {} default: {} }"),
+        "error: multiple `default` cases in switch"
+    );
+    assert_eq!(
+        parse_body("switch (a) { a++; }"),
+        "error: expected `case`, found Symbol"
+    );
+}
+
+#[test]
+fn test_statement_control() {
+    assert_eq!(parse_body("return;"), "return;");
+    assert_eq!(parse_body("return a + 1;"), "return (+ a 1);");
+    assert_eq!(parse_body("exit 1;"), "exit 1;");
+    assert_eq!(parse_body("sleep;"), "sleep;");
+    assert_eq!(parse_body("sleep 100;"), "sleep 100;");
+    assert_eq!(parse_body("assert a > 0;"), "assert (> a 0);");
+    assert_eq!(
+        parse_body("retry: a++; if (a < 5) goto retry;"),
+        "retry: (post++ a); if (< a 5) goto retry;"
+    );
+    assert_eq!(
+        parse_body("static s; new const c = 1; ;"),
+        "static _:s; new const _:c = 1; ;"
+    );
+    assert_eq!(parse_body("#emit nop\n a = 1;"), "emit nop; (= a 1);");
+}
+
+#[test]
+fn test_statement_errors() {
+    assert_eq!(parse_body("if a {}"), "error: expected `(`, found Symbol");
+    assert_eq!(
+        parse_body("do {} (a);"),
+        "error: expected `while`, found LeftBracket"
+    );
+    assert_eq!(
+        parse_body("a = 1"),
+        "error: expected semicolon, found RightBrace"
+    );
+    assert_eq!(
+        parse_body("stock a;"),
+        "error: unterminated block; expected declaration or function, found RightBrace"
+    );
+}
+
+#[test]
+fn test_enum_declarations() {
+    assert_eq!(
+        parse_decl("enum E_PLAYER { E_NAME[24], Float:E_HEALTH, E_SCORE }"),
+        "enum E_PLAYER (+= 1) {E_NAME[24]@0, Float:E_HEALTH@24, E_SCORE@25} size 26"
+    );
+    assert_eq!(
+        parse_decl("enum Colours: { RED = 2, GREEN, BLUE, };"),
+        "enum Colours:(+= 1) {RED = 2@2, GREEN@3, BLUE@4} size 5"
+    );
+    assert_eq!(
+        parse_decl("enum (+= 2) { A, B = 7, C }"),
+        "enum (+= 2) {A@0, B = 7@7, C@9} size 11"
+    );
+    assert_eq!(
+        parse_decl("enum FLAGS (<<= 1) { F_A = 1, F_B, F_C }"),
+        "enum FLAGS (<<= 1) {F_A = 1@1, F_B@2, F_C@4} size 8"
+    );
+    assert_eq!(
+        parse_decl("enum E { A[100000000], B }"),
+        "enum E (+= 1) {A[100000000]@0, B@100000000} size 100000001"
+    );
+    assert_eq!(
+        parse_decl("enum (*= -2) { A = 1, B[3], C }"),
+        "enum (*= -2) {A = 1@1, B[3]@-2, C@16} size -32"
+    );
+    assert_eq!(
+        parse_decl("enum (<<= 4) { A = 1, B[6], C }"),
+        "enum (<<= 4) {A = 1@1, B[6]@16, C@268435456} size ?"
+    );
+    assert_eq!(
+        parse_decl("enum (<<= 1) { A = 0x40000000, B, C = 0 }"),
+        "enum (<<= 1) {A = 1073741824@1073741824, B@-2147483648, C = 0@0} size 0"
+    );
+    assert_eq!(
+        parse_decl("enum E_DATA { E_A[4 char], E_B[E_A + 2], E_C }"),
+        "enum E_DATA (+= 1) {E_A[(char 4)]@0, E_B[(+ E_A 2)]@1, E_C@3} size 4"
+    );
+}
+
+#[test]
+fn test_enum_unknown_sizes() {
+    assert_eq!(
+        parse_decl("enum E_PLAYER { E_ID, E_NAME[MAX_PLAYER_NAME], E_SCORE }"),
+        "enum E_PLAYER (+= 1) {E_ID@0, E_NAME[MAX_PLAYER_NAME]@1, E_SCORE@?} size ?"
+    );
+    assert_eq!(
+        parse_decl("enum { A, B = MAX, C = 3, D }"),
+        "enum (+= 1) {A@0, B = MAX@?, C = 3@3, D@4} size 5"
+    );
+}
+
+#[test]
+fn test_enum_errors() {
+    assert_eq!(
+        parse_decl("enum E (-= 1) { A }"),
+        "error: expected `+=`, `*=` or `<<=` in enum increment, found MinusAssign"
+    );
+    assert_eq!(
+        parse_decl("enum E { A B }"),
+        "error: expected `,` or `}`, found Symbol"
+    );
+    assert_eq!(
+        parse_decl("enum E (*= 1) { A[2000000000] }"),
+        "enum E (*= 1) {A[2000000000]@0} size 0"
+    );
+    assert_eq!(
+        parse_decl("enum E (*= 2) { A = 1, B[30], C }"),
+        "error: enum counter overflows after `B`"
+    );
+    assert_eq!(
+        parse_decl("enum E { A = 2147483000, B[1000], C }"),
+        "error: enum counter overflows after `B`"
+    );
+    assert_eq!(
+        parse_decl("enum (<<= 4) { A = 1, B[7], C }"),
+        "error: enum counter overflows after `B`"
+    );
+}
+
+#[test]
+fn test_enum_overflow_unused() {
+    assert_eq!(
+        parse_decl("enum { A = 2147483647 }"),
+        "enum (+= 1) {A = 2147483647@2147483647} size ?"
+    );
+    assert_eq!(
+        parse_decl("enum E { A = 0x7FFFFFFF }"),
+        "enum E (+= 1) {A = 2147483647@2147483647} size ?"
+    );
+    assert_eq!(
+        parse_decl("enum E { A = 2147483647, B = 0, C }"),
+        "enum E (+= 1) {A = 2147483647@2147483647, B = 0@0, C@1} size 2"
+    );
+}
+
+#[test]
+fn test_state_functions() {
+    assert_eq!(
+        parse_decl("Foo() <idle> {} Foo() <auto:idle, running> {} Foo() <> {}"),
+        "Foo() <idle> {}; Foo() <auto:idle, running> {}; Foo() <> {}"
+    );
+    assert_eq!(
+        parse_decl("entry() <auto:running> { state auto:idle; }"),
+        "entry() <auto:running> {state auto:idle;}"
+    );
+    assert_eq!(
+        parse_decl("native Foo() <idle>;"),
+        "error: native function `Foo` cannot have states"
+    );
+    assert_eq!(
+        parse_decl("Foo() <idle running> {}"),
+        "error: expected `,` or `>`, found Symbol"
+    );
+}
+
+#[test]
+fn test_state_statements() {
+    assert_eq!(parse_body("state idle;"), "state idle;");
+    assert_eq!(parse_body("state auto:idle;"), "state auto:idle;");
+    assert_eq!(
+        parse_body("state (a > 0) auto:running;"),
+        "state ((> a 0)) auto:running;"
+    );
+    assert_eq!(
+        parse_body("state;"),
+        "error: expected symbol, found Semicolon"
+    );
+}
+
+#[test]
+fn test_operator_overloads() {
+    assert_eq!(
+        parse_decl("native Float:operator+(Float:oper1, Float:oper2) = floatadd;"),
+        "native Float:operator+(Float, Float) = floatadd"
+    );
+    assert_eq!(
+        parse_decl("stock Float:operator++(Float:oper) { return oper + 1.0; }"),
+        "stock Float:operator++(Float) {return (+ oper 1.0);}"
+    );
+    assert_eq!(
+        parse_decl("stock bool:operator==(Float:a, b) return true;"),
+        "stock bool:operator==(Float, _) {return true;}"
+    );
+    assert_eq!(
+        parse_decl("forward Float:operator-(Float:a); Float:operator-(Float:a, Float:b) {}"),
+        "forward Float:operator-(Float); Float:operator-(Float, Float) {}"
+    );
+    assert_eq!(
+        parse_decl("native Float:operator=(oper) = float;"),
+        "native Float:operator=(_) = float"
+    );
+    assert_eq!(
+        parse_decl("stock operator~(File:f[], count) {}"),
+        "stock _:operator~(File, _) {}"
+    );
+}
+
+#[test]
+fn test_operator_errors() {
+    assert_eq!(
+        parse_decl("native Float:operator&(Float:a, Float:b);"),
+        "error: operator `&` cannot be overloaded"
+    );
+    assert_eq!(
+        parse_decl("native Float:operator*(Float:a);"),
+        "error: operator `*` takes 2 operands, found 1"
+    );
+    assert_eq!(
+        parse_decl("native Float:operator-(Float:a, Float:b, c);"),
+        "error: operator `-` takes 1 or 2 operands, found 3"
+    );
+    assert_eq!(
+        parse_decl("native operator+(a, b);"),
+        "error: operator `+` must have a tagged operand or result"
+    );
+    assert_eq!(
+        parse_decl("native Float:operator+(&Float:a, Float:b);"),
+        "error: operand `a` of operator `+` must be a single-tagged value without a default"
+    );
+    for source in [
+        "stock operator~(File:f, count) {}",
+        "stock operator~(f[], count) {}",
+        "stock operator~(File:f[][], count) {}",
+        "stock operator~(File:f[], File:count) {}",
+        "stock operator~(File:f[], &count) {}",
+    ]
+    .iter()
+    {
+        assert_eq!(
+            parse_decl(source),
+            "error: operator `~` takes a tagged array and an untagged count, \
+             as in `operator~(Tag:values[], count)`",
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        parse_decl("stock operator~(File:f[]) {}"),
+        "error: operator `~` takes 2 operands, found 1"
+    );
+}
+
+#[test]
+fn test_call_arguments() {
+    assert_eq!(parse_expr("Foo();"), "(call Foo)");
+    assert_eq!(
+        parse_expr("Foo(a, b + 1, Float:c);"),
+        "(call Foo a (+ b 1) (Float: c))"
+    );
+    assert_eq!(
+        parse_expr("SetTimerEx(.funcname = \"Foo\", .interval = 1000);"),
+        "(call SetTimerEx .funcname=\"Foo\" .interval=1000)"
+    );
+    assert_eq!(parse_expr("Foo(a, _, .c = _);"), "(call Foo a _ .c=_)");
+    assert_eq!(
+        parse_expr("a = Foo(Bar(1), x = 2)[0] * 3;"),
+        "(= a (* ([] (call Foo (call Bar 1) (= x 2)) 0) 3))"
+    );
+}
+
+#[test]
+fn test_call_argument_errors() {
+    assert_eq!(
+        parse_expr("Foo(.a = 1, 2);"),
+        "error: positional arguments must come before named arguments"
+    );
+    assert_eq!(
+        parse_expr("Foo(.1 = 2);"),
+        "error: expected symbol, found Integer"
+    );
+    assert_eq!(
+        parse_expr("Foo(a b);"),
+        "error: expected `,` or `)`, found Symbol"
+    );
+}
+
+/// bind parses a function and a call to it, and renders how the call's
+/// arguments bind to the function's parameters.
+#[cfg(test)]
+fn bind(function: &str, call: &str) -> String {
+    let mut p = Parser::new(Lexer::new(&format!("{} {{}} f() {{ {}; }}", function, call)).lex());
+    if let Err(e) = p.parse() {
+        return format!("error: {}", messages(&e));
+    }
+    let (parameters, call) = match &p.unit.items[..] {
+        [Item::Function(callee), Item::Function(caller)] => {
+            let body = caller.body.as_ref().unwrap();
+            match &body.statements[..] {
+                [Stmt::Expr(Expr::Call(c))] => (&callee.parameters, c),
+                s => panic!("expected call, found {:?}", s),
+            }
+        }
+        items => panic!("expected two functions, found {:?}", items),
+    };
+    match call.bind_arguments(parameters) {
+        Ok(bound) => bound
+            .iter()
+            .map(|b| match b {
+                Some(e) => sexp(e),
+                None => String::from("default"),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(e) => format!("error: {}", e.message),
+    }
+}
+
+#[test]
+fn test_call_binding() {
+    assert_eq!(bind("Foo(a, b = 1, c = 2)", "Foo(5)"), "5 default default");
+    assert_eq!(bind("Foo(a, b = 1, c = 2)", "Foo(5, _, 7)"), "5 default 7");
+    assert_eq!(
+        bind("Foo(a, b = 1, c = 2)", "Foo(.c = 7, .a = 5)"),
+        "5 default 7"
+    );
+    assert_eq!(
+        bind("Foo(const fmt[], ...)", "Foo(\"%d %d\", 1, 2)"),
+        "\"%d %d\" 1 2"
+    );
+    assert_eq!(
+        bind("Foo(a, b = 1)", "Foo(.b = 2)"),
+        "error: parameter `a` of `Foo` has no default value"
+    );
+    assert_eq!(
+        bind("Foo(a, b = 1)", "Foo(1, .a = 2)"),
+        "error: parameter `a` of `Foo` is given more than once"
+    );
+    assert_eq!(
+        bind("Foo(a)", "Foo(.z = 1)"),
+        "error: `Foo` has no parameter named `z`"
+    );
+    assert_eq!(
+        bind("Foo(a)", "Foo(1, 2)"),
+        "error: too many arguments to `Foo`, expected at most 1"
+    );
+}
+
+/// bind_error parses `source`, which defines Foo and calls it from the second
+/// line, and returns the error from binding the call's arguments.
+#[cfg(test)]
+fn bind_error(source: &str) -> ParseError {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().unwrap();
+    match &p.unit.items[..] {
+        [Item::Function(callee), Item::Function(caller)] => {
+            match &caller.body.as_ref().unwrap().statements[..] {
+                [Stmt::Expr(Expr::Call(c))] => c.bind_arguments(&callee.parameters).unwrap_err(),
+                s => panic!("expected call, found {:?}", s),
+            }
+        }
+        items => panic!("expected two functions, found {:?}", items),
+    }
+}
+
+#[test]
+fn test_call_binding_spans() {
+    let at = |line, column| Position { line, column };
+
+    let e = bind_error("Foo(a, b = 1) {}\nf() { Foo(1, .a = 2); }");
+    assert_eq!(e.code, ErrorCode::InvalidArgument);
+    assert_eq!(e.span.start, at(2, 14));
+    assert_eq!(e.span.end, at(2, 20));
+    assert_eq!(e.related[0].0.start, at(1, 5));
+    assert_eq!(e.related[0].0.end, at(1, 13));
+    assert_eq!(e.related[0].1, "parameters declared here");
+    assert_eq!(e.related[1].0.start, at(2, 11));
+    assert_eq!(e.related[1].1, "first given here");
+
+    let e = bind_error("Foo(a, b = 1) {}\nf() { Foo(.z = 2); }");
+    assert_eq!(e.span.start, at(2, 11));
+    assert_eq!(e.related.len(), 1);
+
+    let e = bind_error("Foo(a, b = 1) {}\nf() { Foo(.b = 2); }");
+    assert_eq!(e.message, "parameter `a` of `Foo` has no default value");
+    assert_eq!(e.span.start, at(2, 7));
+
+    let e = bind_error("Foo() {}\nf() { Foo(1); }");
+    assert_eq!(e.span.start, at(2, 11));
+    assert!(e.related.is_empty());
+}
+
+/// parse_errors parses input, returning the rendered partial AST and the
+/// errors found.
+#[cfg(test)]
+fn parse_errors(input: &str) -> (String, Vec<ParseError>) {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    let errors = p.parse().err().unwrap_or_default();
+    let ast = p
+        .unit
+        .items
+        .iter()
+        .map(render)
+        .collect::<Vec<_>>()
+        .join("; ");
+    (ast, errors)
+}
+
+#[test]
+fn test_error_end_of_input() {
+    let (ast, errors) = parse_errors("#pragma semicolon 1\nnew a");
+    assert_eq!(ast, "");
+    assert_eq!(messages(&errors), "expected semicolon, found End");
+    assert_eq!(errors[0].code, ErrorCode::UnexpectedEnd);
+    assert_eq!(errors[0].expected, vec![TokenType::Semicolon]);
+    assert_eq!(errors[0].found, Some(TokenType::End));
+    assert_eq!(errors[0].span.start, Position { line: 2, column: 6 });
+
+    for input in &[
+        "new",
+        "Foo(",
+        "Foo(a, ",
+        "enum {",
+        "native Foo() =",
+        "#emit",
+        "a = b ?",
+    ] {
+        assert!(!parse_errors(input).1.is_empty(), "{}", input);
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    let (ast, errors) = parse_errors(
+        "#pragma semicolon 1
+new a = ;
+new b = 2;
+Foo() {
+    a = 1
+    b = 2;
+    if (a) {
+        c = ;
+    }
+    return a; // comments are skipped
+}
+stock Bar() {}
+",
+    );
+    assert_eq!(
+        messages(&errors),
+        "expected expression, found Semicolon; \
+         expected semicolon, found Symbol; \
+         expected expression, found Semicolon"
+    );
+    assert_eq!(
+        ast,
+        "new _:b = 2; Foo() {if a {} return a;}; stock Bar() {}"
+    );
+    let lines: Vec<i32> = errors.iter().map(|e| e.span.start.line).collect();
+    assert_eq!(lines, vec![2, 6, 8]);
+}
+
+#[test]
+fn test_error_recovery_emit() {
+    // an `#emit` line ends the directive, so recovery stops at the newline
+    let (ast, errors) = parse_errors(
+        "#emit bogus 5
+main() {
+    #emit bogus 5
+    #emit load.pri 4
+    #emit push.pri
+    a = 1;
+}
+",
+    );
+    assert_eq!(
+        messages(&errors),
+        "unknown opcode `bogus`; unknown opcode `bogus`"
+    );
+    assert_eq!(ast, "main() {emit load.pri; emit push.pri; (= a 1);}");
+    let lines: Vec<i32> = errors.iter().map(|e| e.span.start.line).collect();
+    assert_eq!(lines, vec![1, 3]);
+}
+
+#[test]
+fn test_error_unterminated_block() {
+    let (ast, errors) = parse_errors("Foo() {\n    a = 1;\n\npublic Bar() {}");
+    assert_eq!(ast, "Foo() {(= a 1);}; public Bar() {}");
+    assert_eq!(errors.len(), 1);
+    let e = &errors[0];
+    assert_eq!(e.code, ErrorCode::UnterminatedBlock);
+    assert_eq!(e.found, Some(TokenType::Public));
+    assert_eq!(e.span.start, Position { line: 4, column: 1 });
+    assert_eq!(e.related.len(), 1);
+    assert_eq!(e.related[0].0.start, Position { line: 1, column: 7 });
+    assert_eq!(
+        e.to_string(),
+        "4:1: error[E003]: unterminated block\n1:7: block opened here"
+    );
+}
+
+#[test]
+fn test_error_details() {
+    let (_, errors) = parse_errors("Foo() { switch (a) { default: ; default: ; } }");
+    assert_eq!(errors[0].code, ErrorCode::InvalidStatement);
+    assert_eq!(
+        errors[0].related[0].0.start,
+        Position {
+            line: 1,
+            column: 22
+        }
+    );
+
+    let (_, errors) = parse_errors("native Float:operator&(Float:a, Float:b);");
+    assert_eq!(errors[0].code, ErrorCode::InvalidOperator);
+    assert_eq!(errors[0].notes.len(), 1);
+
+    let (_, errors) = parse_errors("public public Foo() {}");
+    assert_eq!(errors[0].code, ErrorCode::InvalidFunction);
+    assert_eq!(errors[0].span.start, Position { line: 1, column: 8 });
+    assert_eq!(
+        errors[0].related[0].0.start,
+        Position { line: 1, column: 1 }
+    );
+
+    let (_, errors) = parse_errors("Foo() { Bar(.a = 1, 2); }");
+    assert_eq!(errors[0].code, ErrorCode::InvalidArgument);
+    assert_eq!(
+        errors[0].span.start,
+        Position {
+            line: 1,
+            column: 21
+        }
+    );
+
+    let (_, errors) = parse_errors("new const a;");
+    assert_eq!(errors[0].code, ErrorCode::InvalidDeclaration);
+    assert_eq!(
+        errors[0].span.start,
+        Position {
+            line: 1,
+            column: 11
+        }
+    );
+    assert_eq!(
+        errors[0].span.end,
+        Position {
+            line: 1,
+            column: 12
+        }
+    );
+}
+
+#[test]
+fn test_optional_semicolons() {
+    assert_eq!(
+        parse_body("a = 1\n b = 2\n return a\n"),
+        "(= a 1); (= b 2); return a;"
+    );
+    assert_eq!(
+        parse_body("a = b +\n c\n d++\n"),
+        "(= a (+ b c)); (post++ d);"
+    );
+    assert_eq!(parse_body("a = b\n ++c\n"), "(= a b); (++ c);");
+    assert_eq!(parse_body("a = b\n (c)\n"), "(= a b); c;");
+    assert_eq!(parse_body("return\n a\n"), "return; a;");
+    assert_eq!(
+        parse_body("new a = 1\n for (new i = 0; i < a; i++) {}"),
+        "new _:a = 1; for (new _:i = 0; (< i a); (post++ i)) {}"
+    );
+    assert_eq!(
+        parse_body("for (i = 0\n i < 1; i++) {}"),
+        "error: expected semicolon, found Symbol; expected semicolon, found RightBracket"
+    );
+    assert_eq!(
+        parse_decl("forward Foo()\nnew a\nnew b"),
+        "forward Foo(); new _:a; new _:b"
+    );
+    assert_eq!(
+        parse_body("a = 1 b = 2;"),
+        "error: expected semicolon, found Symbol"
+    );
+}
+
+#[test]
+fn test_required_semicolons() {
+    assert_eq!(
+        parse_decl("#pragma semicolon 1\nFoo() {\n a = 1\n b = 2;\n}"),
+        "error: expected semicolon, found Symbol"
+    );
+    assert_eq!(
+        parse_decl("#pragma semicolon 1\n#pragma semicolon 0\nnew a\n"),
+        "new _:a"
+    );
+
+    let mut p = Parser::new(Lexer::new("new a\nnew b;").lex());
+    p.require_semicolons(true);
+    assert_eq!(
+        p.parse().map_err(|e| messages(&e)),
+        Err(String::from("expected semicolon, found New"))
+    );
+}
+
+#[test]
+fn test_spans() {
+    let mut p = Parser::new(Lexer::new("new a = 1 + 2;\nmain() {\n    return a;\n}").lex());
+    p.parse().expect("failed to parse");
+    let at = |line, column| Position { line, column };
+
+    let declaration = match &p.unit.items[0] {
+        Item::Declaration(d) => d,
+        item => panic!("expected declaration, found {:?}", item),
+    };
+    assert_eq!(declaration.span.start, at(1, 1));
+    assert_eq!(declaration.span.end, at(1, 15));
+    let value = declaration.declarators[0].initializer.as_ref().unwrap();
+    assert_eq!(
+        (value.span().start, value.span().end),
+        (at(1, 9), at(1, 14))
+    );
+
+    let function = match &p.unit.items[1] {
+        Item::Function(f) => f,
+        item => panic!("expected function, found {:?}", item),
+    };
+    assert_eq!(
+        (function.span.start, function.span.end),
+        (at(2, 1), at(4, 2))
+    );
+    let statement = &function.body.as_ref().unwrap().statements[0];
+    assert_eq!(
+        (statement.span().start, statement.span().end),
+        (at(3, 5), at(3, 14))
+    );
+    assert_eq!(p.unit.span.end, at(4, 2));
 }
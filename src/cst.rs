@@ -0,0 +1,707 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::vec;
+
+use crate::ast::visit;
+use crate::ast::visit::Visitor;
+use crate::ast::Addressof;
+use crate::ast::Argument;
+use crate::ast::ArrayIndex;
+use crate::ast::ArrayInitializer;
+use crate::ast::Assert;
+use crate::ast::BinaryOperation;
+use crate::ast::Block;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::Char;
+use crate::ast::CompilationUnit;
+use crate::ast::Declaration;
+use crate::ast::Declarator;
+use crate::ast::Defined;
+use crate::ast::DoWhile;
+use crate::ast::Emit;
+use crate::ast::EmitInstruction;
+use crate::ast::EnumDecl;
+use crate::ast::EnumMember;
+use crate::ast::Exit;
+use crate::ast::Expr;
+use crate::ast::For;
+use crate::ast::FunctionDecl;
+use crate::ast::Goto;
+use crate::ast::If;
+use crate::ast::Item;
+use crate::ast::Label;
+use crate::ast::Literal;
+use crate::ast::Nameof;
+use crate::ast::OperatorDecl;
+use crate::ast::Parameter;
+use crate::ast::Parser;
+use crate::ast::PostfixOperation;
+use crate::ast::Return;
+use crate::ast::Sizeof;
+use crate::ast::Sleep;
+use crate::ast::State;
+use crate::ast::StateSpec;
+use crate::ast::Stmt;
+use crate::ast::Switch;
+use crate::ast::Symbol;
+use crate::ast::Tag;
+use crate::ast::TagOverride;
+use crate::ast::Tagof;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::While;
+use crate::error::ParseError;
+use crate::lexer::Lexer;
+use crate::token::Position;
+use crate::token::Span;
+use crate::token::Token;
+use crate::token::TokenType;
+
+pub mod edit;
+
+/// Cst is a lossless syntax tree: every character of the source, whitespace
+/// and comments included, belongs to exactly one token in `root`, so
+/// printing the tree gives back the source unchanged. The AST it was built
+/// from is kept in `unit`, and `SyntaxNode::get` finds the syntax node for
+/// any AST node in it.
+///
+/// Edits go through the syntax nodes and leave `unit` and the spans as they
+/// were parsed; parse the edited text again to bring them up to date.
+#[derive(Debug)]
+pub struct Cst {
+    pub root: SyntaxNode,
+    pub unit: CompilationUnit,
+    pub errors: Vec<ParseError>,
+    semicolon_pragmas: Vec<(Position, bool)>,
+}
+
+impl Cst {
+    /// parse builds the tree for `source`. Parse errors don't stop it: the
+    /// text the parser recovered over is kept as tokens of the enclosing
+    /// node.
+    pub fn parse(source: &str) -> Cst {
+        Cst::parse_with(source, false)
+    }
+
+    /// parse_with parses as if `#pragma semicolon` had been set to
+    /// `semicolons_required` before the source.
+    fn parse_with(source: &str, semicolons_required: bool) -> Cst {
+        let lexed = Lexer::new(source).with_trivia().lex();
+        let tokens = with_text(source, &lexed);
+        let semicolon_pragmas = edit::semicolon_pragmas(&lexed);
+        let mut p = Parser::new(lexed);
+        p.require_semicolons(semicolons_required);
+        let errors = p.parse().err().unwrap_or_default();
+        let unit = p.unit;
+
+        let mut builder = Builder {
+            tokens: tokens.into_iter().peekable(),
+            stack: vec![SyntaxNode {
+                kind: NodeKind::Unit,
+                span: unit.span,
+                children: vec![],
+            }],
+        };
+        builder.visit_unit(&unit);
+        builder.take_until(None);
+
+        Cst {
+            root: builder.stack.pop().unwrap(),
+            unit,
+            errors,
+            semicolon_pragmas,
+        }
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+/// NodeKind says which AST node a syntax node was built for. Statements and
+/// expressions get the kind of their variant; an expression statement is
+/// just its expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Unit,
+    Declaration,
+    Declarator,
+    Function,
+    Operator,
+    Parameter,
+    StateSpec,
+    Enum,
+    EnumMember,
+    Emit,
+    EmitInstruction,
+    Block,
+    Empty,
+    If,
+    While,
+    DoWhile,
+    For,
+    Switch,
+    Case,
+    Break,
+    Continue,
+    Return,
+    Exit,
+    Sleep,
+    Assert,
+    Label,
+    Goto,
+    State,
+    Literal,
+    Symbol,
+    ArrayInitializer,
+    BinaryOperation,
+    UnaryOperation,
+    PostfixOperation,
+    TernaryOperation,
+    TagOverride,
+    ArrayIndex,
+    Call,
+    Argument,
+    Sizeof,
+    Tagof,
+    Char,
+    Defined,
+    Nameof,
+    Addressof,
+    Tag,
+}
+
+/// SyntaxToken is a token with its exact source text. Whitespace and
+/// comments are tokens too, of type `Whitespace` and `Comment`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub token_type: TokenType,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// SyntaxNode holds the tokens of one AST node, with the nodes of its
+/// children nested in between. Trivia around a node belongs to its parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// tokens returns the node's tokens in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(n) => n.collect_tokens(tokens),
+                SyntaxElement::Token(t) => tokens.push(t),
+            }
+        }
+    }
+
+    /// nodes returns the node's direct child nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// get returns the syntax node built for an AST node of the tree.
+    pub fn get<T: AstNode + ?Sized>(&self, node: &T) -> Option<&SyntaxNode> {
+        self.find(node.kind(), node.span())
+    }
+
+    pub fn get_mut<T: AstNode + ?Sized>(&mut self, node: &T) -> Option<&mut SyntaxNode> {
+        self.find_mut(node.kind(), node.span())
+    }
+
+    /// find returns the node of `kind` built for the AST node at `span`.
+    pub fn find(&self, kind: NodeKind, span: Span) -> Option<&SyntaxNode> {
+        if self.kind == kind && self.span == span {
+            return Some(self);
+        }
+        self.nodes()
+            .filter(|n| n.contains(span))
+            .find_map(|n| n.find(kind, span))
+    }
+
+    pub fn find_mut(&mut self, kind: NodeKind, span: Span) -> Option<&mut SyntaxNode> {
+        if self.kind == kind && self.span == span {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| match child {
+            SyntaxElement::Node(n) if n.contains(span) => n.find_mut(kind, span),
+            _ => None,
+        })
+    }
+
+    fn contains(&self, span: Span) -> bool {
+        self.span.start <= span.start && span.end <= self.span.end
+    }
+
+    /// replace swaps the node's contents for `text`, keeping its kind. The
+    /// text is split into tokens but not parsed, so the node has no child
+    /// nodes until the tree is parsed again.
+    pub fn replace(&mut self, text: &str) {
+        self.children = tokenize(text)
+            .into_iter()
+            .map(SyntaxElement::Token)
+            .collect();
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(n) => n.fmt(f),
+            SyntaxElement::Token(t) => f.write_str(&t.text),
+        }
+    }
+}
+
+/// tokenize splits `source` into tokens with their exact text, trivia
+/// included.
+pub fn tokenize(source: &str) -> Vec<SyntaxToken> {
+    with_text(source, &Lexer::new(source).with_trivia().lex())
+}
+
+/// with_text pairs tokens lexed with trivia with their text. The lexer drops
+/// quotes and escapes from a token's value, so the text is read back from
+/// the source up to where each token ends.
+fn with_text(source: &str, lexed: &[Token]) -> Vec<SyntaxToken> {
+    let mut chars = source.chars();
+    let mut position = Position { line: 1, column: 1 };
+    let mut tokens = vec![];
+    for token in lexed {
+        let mut text = String::new();
+        while position < token.span.end {
+            let Some(c) = chars.next() else { break };
+            text.push(c);
+            if c == '\n' {
+                position.line += 1;
+                position.column = 1;
+            } else {
+                position.column += 1;
+            }
+        }
+        tokens.push(SyntaxToken {
+            token_type: token.token_type,
+            text,
+            span: token.span,
+        });
+    }
+    tokens
+}
+
+/// AstNode is an AST node a syntax node can be built for.
+pub trait AstNode {
+    fn kind(&self) -> NodeKind;
+    fn span(&self) -> Span;
+}
+
+impl AstNode for CompilationUnit {
+    fn kind(&self) -> NodeKind {
+        NodeKind::Unit
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl AstNode for Item {
+    fn kind(&self) -> NodeKind {
+        match self {
+            Item::Declaration(_) => NodeKind::Declaration,
+            Item::Function(_) => NodeKind::Function,
+            Item::Operator(_) => NodeKind::Operator,
+            Item::Enum(_) => NodeKind::Enum,
+            Item::Emit(_) => NodeKind::Emit,
+        }
+    }
+
+    fn span(&self) -> Span {
+        Item::span(self)
+    }
+}
+
+impl AstNode for Stmt {
+    fn kind(&self) -> NodeKind {
+        match self {
+            Stmt::Block(_) => NodeKind::Block,
+            Stmt::Empty(_) => NodeKind::Empty,
+            Stmt::Declaration(_) => NodeKind::Declaration,
+            Stmt::Expr(e) => e.kind(),
+            Stmt::If(_) => NodeKind::If,
+            Stmt::While(_) => NodeKind::While,
+            Stmt::DoWhile(_) => NodeKind::DoWhile,
+            Stmt::For(_) => NodeKind::For,
+            Stmt::Switch(_) => NodeKind::Switch,
+            Stmt::Break(_) => NodeKind::Break,
+            Stmt::Continue(_) => NodeKind::Continue,
+            Stmt::Return(_) => NodeKind::Return,
+            Stmt::Exit(_) => NodeKind::Exit,
+            Stmt::Sleep(_) => NodeKind::Sleep,
+            Stmt::Assert(_) => NodeKind::Assert,
+            Stmt::Label(_) => NodeKind::Label,
+            Stmt::Goto(_) => NodeKind::Goto,
+            Stmt::State(_) => NodeKind::State,
+            Stmt::Emit(_) => NodeKind::Emit,
+        }
+    }
+
+    fn span(&self) -> Span {
+        Stmt::span(self)
+    }
+}
+
+impl AstNode for Expr {
+    fn kind(&self) -> NodeKind {
+        match self {
+            Expr::Literal(_) => NodeKind::Literal,
+            Expr::Symbol(_) => NodeKind::Symbol,
+            Expr::ArrayInitializer(_) => NodeKind::ArrayInitializer,
+            Expr::BinaryOperation(_) => NodeKind::BinaryOperation,
+            Expr::UnaryOperation(_) => NodeKind::UnaryOperation,
+            Expr::PostfixOperation(_) => NodeKind::PostfixOperation,
+            Expr::TernaryOperation(_) => NodeKind::TernaryOperation,
+            Expr::TagOverride(_) => NodeKind::TagOverride,
+            Expr::ArrayIndex(_) => NodeKind::ArrayIndex,
+            Expr::Call(_) => NodeKind::Call,
+            Expr::Sizeof(_) => NodeKind::Sizeof,
+            Expr::Tagof(_) => NodeKind::Tagof,
+            Expr::Char(_) => NodeKind::Char,
+            Expr::Defined(_) => NodeKind::Defined,
+            Expr::Nameof(_) => NodeKind::Nameof,
+            Expr::Addressof(_) => NodeKind::Addressof,
+        }
+    }
+
+    fn span(&self) -> Span {
+        Expr::span(self)
+    }
+}
+
+macro_rules! ast_node {
+    ($($node:ty => $kind:ident,)*) => {
+        $(
+            impl AstNode for $node {
+                fn kind(&self) -> NodeKind {
+                    NodeKind::$kind
+                }
+
+                fn span(&self) -> Span {
+                    self.span
+                }
+            }
+        )*
+    };
+}
+
+ast_node! {
+    Declaration => Declaration,
+    Declarator => Declarator,
+    FunctionDecl => Function,
+    OperatorDecl => Operator,
+    Parameter => Parameter,
+    StateSpec => StateSpec,
+    EnumDecl => Enum,
+    EnumMember => EnumMember,
+    Emit => Emit,
+    EmitInstruction => EmitInstruction,
+    Block => Block,
+    If => If,
+    While => While,
+    DoWhile => DoWhile,
+    For => For,
+    Switch => Switch,
+    Case => Case,
+    Return => Return,
+    Exit => Exit,
+    Sleep => Sleep,
+    Assert => Assert,
+    Label => Label,
+    Goto => Goto,
+    State => State,
+    Literal => Literal,
+    Symbol => Symbol,
+    ArrayInitializer => ArrayInitializer,
+    BinaryOperation => BinaryOperation,
+    UnaryOperation => UnaryOperation,
+    PostfixOperation => PostfixOperation,
+    TernaryOperation => TernaryOperation,
+    TagOverride => TagOverride,
+    ArrayIndex => ArrayIndex,
+    Call => Call,
+    Argument => Argument,
+    Sizeof => Sizeof,
+    Tagof => Tagof,
+    Char => Char,
+    Defined => Defined,
+    Nameof => Nameof,
+    Addressof => Addressof,
+    Tag => Tag,
+}
+
+/// Builder walks the AST in source order, opening a syntax node for each
+/// AST node and handing it the tokens that fall inside its span.
+struct Builder {
+    tokens: Peekable<vec::IntoIter<SyntaxToken>>,
+    stack: Vec<SyntaxNode>,
+}
+
+impl Builder {
+    fn node<T: AstNode + ?Sized>(&mut self, node: &T, walk: impl FnOnce(&mut Builder)) {
+        self.open(node.kind(), node.span(), walk)
+    }
+
+    /// open builds a node of `kind` over `span`, for nodes such as `break`
+    /// that are only a span in the AST.
+    fn open(&mut self, kind: NodeKind, span: Span, walk: impl FnOnce(&mut Builder)) {
+        // nodes made up by the parser, such as the default enum increment,
+        // cover no text
+        if span.start == span.end {
+            return walk(self);
+        }
+
+        self.take_until(Some(span.start));
+        self.stack.push(SyntaxNode {
+            kind,
+            span,
+            children: vec![],
+        });
+        walk(self);
+        self.take_until(Some(span.end));
+        let node = self.stack.pop().unwrap();
+        self.push(SyntaxElement::Node(node));
+    }
+
+    /// take_until moves the tokens starting before `position`, or all that
+    /// are left, into the innermost open node.
+    fn take_until(&mut self, position: Option<Position>) {
+        while let Some(token) = self
+            .tokens
+            .next_if(|t| position.is_none_or(|p| t.span.start < p))
+        {
+            self.push(SyntaxElement::Token(token));
+        }
+    }
+
+    fn push(&mut self, element: SyntaxElement) {
+        self.stack.last_mut().unwrap().children.push(element);
+    }
+}
+
+impl Visitor for Builder {
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        self.node(declaration, |b| visit::walk_declaration(b, declaration))
+    }
+
+    fn visit_declarator(&mut self, declarator: &Declarator) {
+        self.node(declarator, |b| visit::walk_declarator(b, declarator))
+    }
+
+    fn visit_function(&mut self, function: &FunctionDecl) {
+        self.node(function, |b| visit::walk_function(b, function))
+    }
+
+    fn visit_operator(&mut self, operator: &OperatorDecl) {
+        self.node(operator, |b| visit::walk_operator(b, operator))
+    }
+
+    fn visit_parameter(&mut self, parameter: &Parameter) {
+        self.node(parameter, |b| visit::walk_parameter(b, parameter))
+    }
+
+    fn visit_enum(&mut self, e: &EnumDecl) {
+        self.node(e, |b| visit::walk_enum(b, e))
+    }
+
+    fn visit_enum_member(&mut self, member: &EnumMember) {
+        self.node(member, |b| visit::walk_enum_member(b, member))
+    }
+
+    fn visit_state_spec(&mut self, states: &StateSpec) {
+        self.node(states, |_| ())
+    }
+
+    fn visit_emit(&mut self, emit: &Emit) {
+        self.node(emit, |b| visit::walk_emit(b, emit))
+    }
+
+    fn visit_emit_instruction(&mut self, instruction: &EmitInstruction) {
+        self.node(instruction, |b| {
+            visit::walk_emit_instruction(b, instruction)
+        })
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.node(block, |b| visit::walk_block(b, block))
+    }
+
+    fn visit_if(&mut self, i: &If) {
+        self.node(i, |b| visit::walk_if(b, i))
+    }
+
+    fn visit_while(&mut self, w: &While) {
+        self.node(w, |b| visit::walk_while(b, w))
+    }
+
+    fn visit_do_while(&mut self, d: &DoWhile) {
+        self.node(d, |b| visit::walk_do_while(b, d))
+    }
+
+    fn visit_for(&mut self, f: &For) {
+        self.node(f, |b| visit::walk_for(b, f))
+    }
+
+    fn visit_switch(&mut self, s: &Switch) {
+        self.node(s, |b| visit::walk_switch(b, s))
+    }
+
+    fn visit_case(&mut self, case: &Case) {
+        self.node(case, |b| visit::walk_case(b, case))
+    }
+
+    fn visit_empty(&mut self, span: &Span) {
+        self.open(NodeKind::Empty, *span, |_| ())
+    }
+
+    fn visit_break(&mut self, span: &Span) {
+        self.open(NodeKind::Break, *span, |_| ())
+    }
+
+    fn visit_continue(&mut self, span: &Span) {
+        self.open(NodeKind::Continue, *span, |_| ())
+    }
+
+    fn visit_return(&mut self, r: &Return) {
+        self.node(r, |b| visit::walk_return(b, r))
+    }
+
+    fn visit_exit(&mut self, e: &Exit) {
+        self.node(e, |b| visit::walk_exit(b, e))
+    }
+
+    fn visit_sleep(&mut self, s: &Sleep) {
+        self.node(s, |b| visit::walk_sleep(b, s))
+    }
+
+    fn visit_assert(&mut self, a: &Assert) {
+        self.node(a, |b| visit::walk_assert(b, a))
+    }
+
+    fn visit_label(&mut self, label: &Label) {
+        self.node(label, |_| ())
+    }
+
+    fn visit_goto(&mut self, goto: &Goto) {
+        self.node(goto, |_| ())
+    }
+
+    fn visit_state(&mut self, state: &State) {
+        self.node(state, |b| visit::walk_state(b, state))
+    }
+
+    fn visit_binary(&mut self, binary: &BinaryOperation) {
+        self.node(binary, |b| visit::walk_binary(b, binary))
+    }
+
+    fn visit_unary(&mut self, u: &UnaryOperation) {
+        self.node(u, |b| visit::walk_unary(b, u))
+    }
+
+    fn visit_postfix(&mut self, p: &PostfixOperation) {
+        self.node(p, |b| visit::walk_postfix(b, p))
+    }
+
+    fn visit_ternary(&mut self, t: &TernaryOperation) {
+        self.node(t, |b| visit::walk_ternary(b, t))
+    }
+
+    fn visit_tag_override(&mut self, t: &TagOverride) {
+        self.node(t, |b| visit::walk_tag_override(b, t))
+    }
+
+    fn visit_array_index(&mut self, a: &ArrayIndex) {
+        self.node(a, |b| visit::walk_array_index(b, a))
+    }
+
+    fn visit_array_initializer(&mut self, a: &ArrayInitializer) {
+        self.node(a, |b| visit::walk_array_initializer(b, a))
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        self.node(call, |b| visit::walk_call(b, call))
+    }
+
+    fn visit_argument(&mut self, argument: &Argument) {
+        self.node(argument, |b| visit::walk_argument(b, argument))
+    }
+
+    fn visit_sizeof(&mut self, s: &Sizeof) {
+        self.node(s, |b| visit::walk_sizeof(b, s))
+    }
+
+    fn visit_tagof(&mut self, t: &Tagof) {
+        self.node(t, |b| visit::walk_tagof(b, t))
+    }
+
+    fn visit_char(&mut self, c: &Char) {
+        self.node(c, |b| visit::walk_char(b, c))
+    }
+
+    fn visit_defined(&mut self, d: &Defined) {
+        self.node(d, |b| visit::walk_defined(b, d))
+    }
+
+    fn visit_nameof(&mut self, n: &Nameof) {
+        self.node(n, |b| visit::walk_nameof(b, n))
+    }
+
+    fn visit_addressof(&mut self, a: &Addressof) {
+        self.node(a, |b| visit::walk_addressof(b, a))
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        // an implicit tag shares the value's token
+        self.node(literal, |b| match &literal.tag {
+            Some(tag) if !literal.implicit_tag => b.visit_tag(tag),
+            _ => (),
+        })
+    }
+
+    fn visit_symbol(&mut self, symbol: &Symbol) {
+        self.node(symbol, |_| ())
+    }
+
+    fn visit_tag(&mut self, tag: &Tag) {
+        self.node(tag, |_| ())
+    }
+}
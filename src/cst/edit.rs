@@ -0,0 +1,508 @@
+use crate::ast::visit_mut;
+use crate::ast::visit_mut::VisitMut;
+use crate::ast::Addressof;
+use crate::ast::Argument;
+use crate::ast::ArrayIndex;
+use crate::ast::ArrayInitializer;
+use crate::ast::Assert;
+use crate::ast::BinaryOperation;
+use crate::ast::Block;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::Char;
+use crate::ast::CompilationUnit;
+use crate::ast::Declaration;
+use crate::ast::Declarator;
+use crate::ast::Defined;
+use crate::ast::DoWhile;
+use crate::ast::Emit;
+use crate::ast::EmitInstruction;
+use crate::ast::EnumDecl;
+use crate::ast::EnumMember;
+use crate::ast::Exit;
+use crate::ast::For;
+use crate::ast::FunctionDecl;
+use crate::ast::Goto;
+use crate::ast::If;
+use crate::ast::Item;
+use crate::ast::Label;
+use crate::ast::Literal;
+use crate::ast::Nameof;
+use crate::ast::OperatorDecl;
+use crate::ast::Parameter;
+use crate::ast::PostfixOperation;
+use crate::ast::Return;
+use crate::ast::Sizeof;
+use crate::ast::Sleep;
+use crate::ast::State;
+use crate::ast::StateSpec;
+use crate::ast::Switch;
+use crate::ast::Symbol;
+use crate::ast::Tag;
+use crate::ast::TagOverride;
+use crate::ast::Tagof;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::While;
+use crate::cst::Cst;
+use crate::cst::SyntaxElement;
+use crate::cst::SyntaxNode;
+use crate::error::ParseError;
+use crate::token::Position;
+use crate::token::Span;
+use crate::token::Token;
+use crate::token::TokenType;
+use crate::token::TokenValue;
+
+/// TextEdit replaces the source text in `span` with `text`. An empty span
+/// inserts, and empty text deletes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl Cst {
+    /// edit applies `edit` to the source the tree was parsed from and returns
+    /// the tree for the result. An edit inside a function body only reparses
+    /// that function: every other item is moved over from the old tree, with
+    /// the spans after the edit shifted to match. Edits anywhere else, or
+    /// ones that could change how the rest of the file parses, reparse the
+    /// whole file.
+    pub fn edit(mut self, edit: &TextEdit) -> Cst {
+        if self.reparse_function(edit) {
+            return self;
+        }
+        let source = self.to_string();
+        let start = offset(&source, Position { line: 1, column: 1 }, edit.span.start);
+        let end = offset(&source, Position { line: 1, column: 1 }, edit.span.end);
+        Cst::parse(&format!(
+            "{}{}{}",
+            &source[..start],
+            edit.text,
+            &source[end..]
+        ))
+    }
+
+    /// reparse_function reparses the function containing `edit` on its own
+    /// and splices it into the tree, along with its errors in place of the
+    /// ones it had. It returns false, leaving the tree as it was, if the edit
+    /// isn't inside one function, or the edited text could parse differently
+    /// as part of the file.
+    fn reparse_function(&mut self, edit: &TextEdit) -> bool {
+        let Some(index) = self.root.children.iter().position(|child| {
+            matches!(child, SyntaxElement::Node(n)
+                if n.span.start < edit.span.start && edit.span.end < n.span.end)
+        }) else {
+            return false;
+        };
+        let item = self.root.children[..index]
+            .iter()
+            .filter(|child| matches!(child, SyntaxElement::Node(_)))
+            .count();
+        if !matches!(self.unit.items[item], Item::Function(_) | Item::Operator(_)) {
+            return false;
+        }
+
+        let old_span = self.unit.items[item].span();
+        let old_text = self.root.children[index].to_string();
+        let start = offset(&old_text, old_span.start, edit.span.start);
+        let end = offset(&old_text, old_span.start, edit.span.end);
+        let text = format!("{}{}{}", &old_text[..start], edit.text, &old_text[end..]);
+
+        let semicolons_required = self
+            .semicolon_pragmas
+            .iter()
+            .rfind(|(position, _)| *position < old_span.start)
+            .is_some_and(|(_, required)| *required);
+        let mut function = Cst::parse_with(&text, semicolons_required);
+        // `#pragma semicolon` changes how everything after it parses
+        let inside = |position: &Position| old_span.start <= *position && *position < old_span.end;
+        if self.semicolon_pragmas.iter().any(|(p, _)| inside(p))
+            || !function.semicolon_pragmas.is_empty()
+        {
+            return false;
+        }
+        // an error at the end of the text, like an unterminated block, could
+        // have gone on into the items after it
+        if function.unit.items.len() != 1
+            || function
+                .errors
+                .iter()
+                .any(|e| e.found == Some(TokenType::End))
+        {
+            return false;
+        }
+        let (Some(mut new_item), Some(SyntaxElement::Node(mut new_node)), None) = (
+            function.unit.items.pop(),
+            function.root.children.pop(),
+            function.root.children.first(),
+        ) else {
+            return false;
+        };
+        if !matches!(new_item, Item::Function(_) | Item::Operator(_)) {
+            return false;
+        }
+
+        // the function was parsed from line 1, column 1
+        let mut into_place = Shift {
+            from: Position { line: 1, column: 1 },
+            to: old_span.start,
+        };
+        into_place.visit_item_mut(&mut new_item);
+        into_place.node(&mut new_node);
+        for error in &mut function.errors {
+            into_place.error(error);
+        }
+
+        let mut after = Shift {
+            from: old_span.end,
+            to: new_item.span().end,
+        };
+        for item in &mut self.unit.items[item + 1..] {
+            after.visit_item_mut(item);
+        }
+        for child in &mut self.root.children[index + 1..] {
+            after.element(child);
+        }
+        for (position, _) in &mut self.semicolon_pragmas {
+            after.position(position);
+        }
+        after.span(&mut self.unit.span);
+        after.span(&mut self.root.span);
+
+        // the old function's errors are the ones pointing into it, which
+        // includes an unterminated block reported at the next item
+        let (mut errors, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.errors)
+            .into_iter()
+            .filter(|e| {
+                !inside(&e.span.start) && !e.related.iter().any(|(span, _)| inside(&span.start))
+            })
+            .partition(|e| e.span.start < old_span.start);
+        errors.append(&mut function.errors);
+        for mut error in rest {
+            after.error(&mut error);
+            errors.push(error);
+        }
+        self.errors = errors;
+
+        self.unit.items[item] = new_item;
+        self.root.children[index] = SyntaxElement::Node(new_node);
+        true
+    }
+}
+
+/// semicolon_pragmas returns where each `#pragma semicolon` is, and whether
+/// it requires semicolons, so an edit can reparse a function under the right
+/// one without looking through the whole file.
+pub(super) fn semicolon_pragmas(tokens: &[Token]) -> Vec<(Position, bool)> {
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !matches!(t.token_type, TokenType::Whitespace | TokenType::Comment))
+        .collect();
+    let symbol = |t: &Token, name: &str| matches!(&t.value, TokenValue::Name(n) if *n == name);
+
+    let mut pragmas = vec![];
+    for window in tokens.windows(4) {
+        if let [directive, pragma, semicolon, value] = window {
+            if directive.token_type == TokenType::Directive
+                && symbol(pragma, "pragma")
+                && symbol(semicolon, "semicolon")
+                && value.span.start.line == directive.span.start.line
+            {
+                if let TokenValue::Integer(v) = value.value {
+                    pragmas.push((directive.span.start, v != 0));
+                }
+            }
+        }
+    }
+    pragmas
+}
+
+/// offset returns the byte offset of `position` in `text`, which starts at
+/// `origin`, or the length of `text` if it ends first.
+fn offset(text: &str, origin: Position, position: Position) -> usize {
+    let mut current = origin;
+    for (i, c) in text.char_indices() {
+        if current >= position {
+            return i;
+        }
+        if c == '\n' {
+            current.line += 1;
+            current.column = 1;
+        } else {
+            current.column += 1;
+        }
+    }
+    text.len()
+}
+
+/// Shift moves every position at or after `from` so that `from` lands on
+/// `to`. Positions on the same line as `from` move by the column difference
+/// as well as the line difference.
+struct Shift {
+    from: Position,
+    to: Position,
+}
+
+impl Shift {
+    fn position(&self, p: &mut Position) {
+        if *p < self.from {
+            return;
+        }
+        if p.line == self.from.line {
+            p.column += self.to.column - self.from.column;
+        }
+        p.line += self.to.line - self.from.line;
+    }
+
+    fn span(&self, span: &mut Span) {
+        self.position(&mut span.start);
+        self.position(&mut span.end);
+    }
+
+    fn error(&self, error: &mut ParseError) {
+        self.span(&mut error.span);
+        for (span, _) in &mut error.related {
+            self.span(span);
+        }
+    }
+
+    fn node(&self, node: &mut SyntaxNode) {
+        self.span(&mut node.span);
+        for child in &mut node.children {
+            self.element(child);
+        }
+    }
+
+    fn element(&self, element: &mut SyntaxElement) {
+        match element {
+            SyntaxElement::Node(n) => self.node(n),
+            SyntaxElement::Token(t) => self.span(&mut t.span),
+        }
+    }
+}
+
+impl VisitMut for Shift {
+    fn visit_unit_mut(&mut self, unit: &mut CompilationUnit) {
+        self.span(&mut unit.span);
+        visit_mut::walk_unit_mut(self, unit)
+    }
+
+    fn visit_declaration_mut(&mut self, declaration: &mut Declaration) {
+        self.span(&mut declaration.span);
+        visit_mut::walk_declaration_mut(self, declaration)
+    }
+
+    fn visit_declarator_mut(&mut self, declarator: &mut Declarator) {
+        self.span(&mut declarator.span);
+        visit_mut::walk_declarator_mut(self, declarator)
+    }
+
+    fn visit_function_mut(&mut self, function: &mut FunctionDecl) {
+        self.span(&mut function.span);
+        visit_mut::walk_function_mut(self, function)
+    }
+
+    fn visit_operator_mut(&mut self, operator: &mut OperatorDecl) {
+        self.span(&mut operator.span);
+        visit_mut::walk_operator_mut(self, operator)
+    }
+
+    fn visit_parameter_mut(&mut self, parameter: &mut Parameter) {
+        self.span(&mut parameter.span);
+        visit_mut::walk_parameter_mut(self, parameter)
+    }
+
+    fn visit_enum_mut(&mut self, e: &mut EnumDecl) {
+        self.span(&mut e.span);
+        visit_mut::walk_enum_mut(self, e)
+    }
+
+    fn visit_enum_member_mut(&mut self, member: &mut EnumMember) {
+        self.span(&mut member.span);
+        visit_mut::walk_enum_member_mut(self, member)
+    }
+
+    fn visit_state_spec_mut(&mut self, states: &mut StateSpec) {
+        self.span(&mut states.span);
+    }
+
+    fn visit_emit_mut(&mut self, emit: &mut Emit) {
+        self.span(&mut emit.span);
+        visit_mut::walk_emit_mut(self, emit)
+    }
+
+    fn visit_emit_instruction_mut(&mut self, instruction: &mut EmitInstruction) {
+        self.span(&mut instruction.span);
+        visit_mut::walk_emit_instruction_mut(self, instruction)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        self.span(&mut block.span);
+        visit_mut::walk_block_mut(self, block)
+    }
+
+    fn visit_if_mut(&mut self, i: &mut If) {
+        self.span(&mut i.span);
+        visit_mut::walk_if_mut(self, i)
+    }
+
+    fn visit_while_mut(&mut self, w: &mut While) {
+        self.span(&mut w.span);
+        visit_mut::walk_while_mut(self, w)
+    }
+
+    fn visit_do_while_mut(&mut self, d: &mut DoWhile) {
+        self.span(&mut d.span);
+        visit_mut::walk_do_while_mut(self, d)
+    }
+
+    fn visit_for_mut(&mut self, f: &mut For) {
+        self.span(&mut f.span);
+        visit_mut::walk_for_mut(self, f)
+    }
+
+    fn visit_switch_mut(&mut self, s: &mut Switch) {
+        self.span(&mut s.span);
+        visit_mut::walk_switch_mut(self, s)
+    }
+
+    fn visit_case_mut(&mut self, case: &mut Case) {
+        self.span(&mut case.span);
+        visit_mut::walk_case_mut(self, case)
+    }
+
+    fn visit_empty_mut(&mut self, span: &mut Span) {
+        self.span(span);
+    }
+
+    fn visit_break_mut(&mut self, span: &mut Span) {
+        self.span(span);
+    }
+
+    fn visit_continue_mut(&mut self, span: &mut Span) {
+        self.span(span);
+    }
+
+    fn visit_return_mut(&mut self, r: &mut Return) {
+        self.span(&mut r.span);
+        visit_mut::walk_return_mut(self, r)
+    }
+
+    fn visit_exit_mut(&mut self, e: &mut Exit) {
+        self.span(&mut e.span);
+        visit_mut::walk_exit_mut(self, e)
+    }
+
+    fn visit_sleep_mut(&mut self, s: &mut Sleep) {
+        self.span(&mut s.span);
+        visit_mut::walk_sleep_mut(self, s)
+    }
+
+    fn visit_assert_mut(&mut self, a: &mut Assert) {
+        self.span(&mut a.span);
+        visit_mut::walk_assert_mut(self, a)
+    }
+
+    fn visit_label_mut(&mut self, label: &mut Label) {
+        self.span(&mut label.span);
+    }
+
+    fn visit_goto_mut(&mut self, goto: &mut Goto) {
+        self.span(&mut goto.span);
+    }
+
+    fn visit_state_mut(&mut self, state: &mut State) {
+        self.span(&mut state.span);
+        visit_mut::walk_state_mut(self, state)
+    }
+
+    fn visit_binary_mut(&mut self, b: &mut BinaryOperation) {
+        self.span(&mut b.span);
+        visit_mut::walk_binary_mut(self, b)
+    }
+
+    fn visit_unary_mut(&mut self, u: &mut UnaryOperation) {
+        self.span(&mut u.span);
+        visit_mut::walk_unary_mut(self, u)
+    }
+
+    fn visit_postfix_mut(&mut self, p: &mut PostfixOperation) {
+        self.span(&mut p.span);
+        visit_mut::walk_postfix_mut(self, p)
+    }
+
+    fn visit_ternary_mut(&mut self, t: &mut TernaryOperation) {
+        self.span(&mut t.span);
+        visit_mut::walk_ternary_mut(self, t)
+    }
+
+    fn visit_tag_override_mut(&mut self, t: &mut TagOverride) {
+        self.span(&mut t.span);
+        visit_mut::walk_tag_override_mut(self, t)
+    }
+
+    fn visit_array_index_mut(&mut self, a: &mut ArrayIndex) {
+        self.span(&mut a.span);
+        visit_mut::walk_array_index_mut(self, a)
+    }
+
+    fn visit_array_initializer_mut(&mut self, a: &mut ArrayInitializer) {
+        self.span(&mut a.span);
+        visit_mut::walk_array_initializer_mut(self, a)
+    }
+
+    fn visit_call_mut(&mut self, call: &mut Call) {
+        self.span(&mut call.span);
+        visit_mut::walk_call_mut(self, call)
+    }
+
+    fn visit_argument_mut(&mut self, argument: &mut Argument) {
+        self.span(&mut argument.span);
+        visit_mut::walk_argument_mut(self, argument)
+    }
+
+    fn visit_sizeof_mut(&mut self, s: &mut Sizeof) {
+        self.span(&mut s.span);
+        visit_mut::walk_sizeof_mut(self, s)
+    }
+
+    fn visit_tagof_mut(&mut self, t: &mut Tagof) {
+        self.span(&mut t.span);
+        visit_mut::walk_tagof_mut(self, t)
+    }
+
+    fn visit_char_mut(&mut self, c: &mut Char) {
+        self.span(&mut c.span);
+        visit_mut::walk_char_mut(self, c)
+    }
+
+    fn visit_defined_mut(&mut self, d: &mut Defined) {
+        self.span(&mut d.span);
+        visit_mut::walk_defined_mut(self, d)
+    }
+
+    fn visit_nameof_mut(&mut self, n: &mut Nameof) {
+        self.span(&mut n.span);
+        visit_mut::walk_nameof_mut(self, n)
+    }
+
+    fn visit_addressof_mut(&mut self, a: &mut Addressof) {
+        self.span(&mut a.span);
+        visit_mut::walk_addressof_mut(self, a)
+    }
+
+    fn visit_literal_mut(&mut self, literal: &mut Literal) {
+        self.span(&mut literal.span);
+        visit_mut::walk_literal_mut(self, literal)
+    }
+
+    fn visit_symbol_mut(&mut self, symbol: &mut Symbol) {
+        self.span(&mut symbol.span);
+    }
+
+    fn visit_tag_mut(&mut self, tag: &mut Tag) {
+        self.span(&mut tag.span);
+    }
+}
@@ -0,0 +1,355 @@
+#[cfg(test)]
+use crate::ast::Expr;
+#[cfg(test)]
+use crate::ast::FunctionDecl;
+#[cfg(test)]
+use crate::ast::Item;
+#[cfg(test)]
+use crate::ast::Stmt;
+#[cfg(test)]
+use crate::cst::edit::TextEdit;
+#[cfg(test)]
+use crate::cst::Cst;
+#[cfg(test)]
+use crate::cst::NodeKind;
+#[cfg(test)]
+use crate::cst::SyntaxNode;
+#[cfg(test)]
+use crate::gamemode::gamemode;
+#[cfg(test)]
+use crate::token::Position;
+#[cfg(test)]
+use crate::token::Span;
+#[cfg(test)]
+use crate::token::TokenType;
+
+#[cfg(test)]
+const SOURCE: &str = r#"#include <a_samp>
+
+// the player's score
+new score = 0 ;
+
+/* adds to the score */
+stock Float:Add(value,   bonus = 2)
+{
+    score += value * bonus; // keep it
+    return Float:score;
+}
+
+enum E { A = 1, B[4] }
+"#;
+
+#[cfg(test)]
+fn kinds(node: &SyntaxNode) -> Vec<NodeKind> {
+    node.nodes().map(|n| n.kind).collect()
+}
+
+#[test]
+fn test_cst_lossless() {
+    let cst = Cst::parse(SOURCE);
+    assert!(cst.errors.is_empty(), "{:?}", cst.errors);
+    assert_eq!(cst.to_string(), SOURCE);
+
+    // text the parser gives up on is kept as well
+    let statement = "main() return 1; // no braces\n";
+    let cst = Cst::parse(statement);
+    assert!(cst.errors.is_empty(), "{:?}", cst.errors);
+    assert_eq!(cst.to_string(), statement);
+
+    let broken = "main() {\n    new = ;\n    x++; /* fine */\n}\n";
+    let cst = Cst::parse(broken);
+    assert!(!cst.errors.is_empty());
+    assert_eq!(cst.to_string(), broken);
+
+    let multibyte = "/* Ъ/ */\nmain() {}\n";
+    let cst = Cst::parse(multibyte);
+    assert!(cst.errors.is_empty(), "{:?}", cst.errors);
+    assert_eq!(cst.to_string(), multibyte);
+}
+
+#[test]
+fn test_cst_structure() {
+    let cst = Cst::parse(SOURCE);
+    assert_eq!(
+        kinds(&cst.root),
+        vec![NodeKind::Declaration, NodeKind::Function, NodeKind::Enum]
+    );
+
+    // trivia around a node belongs to its parent
+    let function = cst.root.get(&cst.unit.items[1]).unwrap();
+    assert_eq!(
+        function.to_string(),
+        "stock Float:Add(value,   bonus = 2)\n{\n    score += value * bonus; // keep it\n    return Float:score;\n}"
+    );
+    assert_eq!(
+        kinds(function),
+        vec![
+            NodeKind::Tag,
+            NodeKind::Parameter,
+            NodeKind::Parameter,
+            NodeKind::Block
+        ]
+    );
+    let comments: Vec<&str> = cst
+        .root
+        .tokens()
+        .into_iter()
+        .filter(|t| t.token_type == TokenType::Comment)
+        .map(|t| t.text.as_str())
+        .collect();
+    assert_eq!(
+        comments,
+        vec![
+            "// the player's score\n",
+            "/* adds to the score */",
+            "// keep it\n"
+        ]
+    );
+
+    let body = match &cst.unit.items[1] {
+        Item::Function(f) => &f.body.as_ref().unwrap().statements,
+        item => panic!("unexpected item {:?}", item),
+    };
+    let assignment = cst.root.get(&body[0]).unwrap();
+    assert_eq!(assignment.kind, NodeKind::BinaryOperation);
+    assert_eq!(assignment.to_string(), "score += value * bonus");
+    assert_eq!(
+        cst.root.get(&body[1]).unwrap().to_string(),
+        "return Float:score;"
+    );
+}
+
+#[test]
+fn test_cst_edit() {
+    let mut cst = Cst::parse(SOURCE);
+    let body = match &cst.unit.items[1] {
+        Item::Function(f) => &f.body.as_ref().unwrap().statements,
+        item => panic!("unexpected item {:?}", item),
+    };
+    let value = match &body[0] {
+        Stmt::Expr(Expr::BinaryOperation(b)) => &b.right,
+        statement => panic!("unexpected statement {:?}", statement),
+    };
+    cst.root
+        .get_mut(value)
+        .unwrap()
+        .replace("(value + 1) * bonus");
+
+    let edited = SOURCE.replace("value * bonus", "(value + 1) * bonus");
+    assert_eq!(cst.to_string(), edited);
+
+    let reparsed = Cst::parse(&cst.to_string());
+    assert!(reparsed.errors.is_empty());
+    assert_eq!(reparsed.to_string(), edited);
+}
+
+/// edit applies an edit from `start` to `end` both incrementally and by
+/// parsing the edited source from scratch, and checks that the trees agree.
+#[cfg(test)]
+fn edit(source: &str, start: (i32, i32), end: (i32, i32), text: &str) -> Cst {
+    let at = |(line, column)| Position { line, column };
+    let edit = TextEdit {
+        span: Span {
+            start: at(start),
+            end: at(end),
+        },
+        text: String::from(text),
+    };
+    let edited = Cst::parse(source).edit(&edit);
+    let parsed = Cst::parse(&edited.to_string());
+    assert_eq!(format!("{:?}", edited), format!("{:?}", parsed));
+    edited
+}
+
+/// functions returns the address of each function, to tell which ones an
+/// edit moved over rather than parsed again.
+#[cfg(test)]
+fn functions(cst: &Cst) -> Vec<*const FunctionDecl> {
+    cst.unit
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(f) => Some(&**f as *const FunctionDecl),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_cst_edit_reparse() {
+    let source = "#pragma semicolon 1\nnew a;\n\nf() {\n    a++;\n}\n\ng() {\n    a--;\n}\n\nh() { a = 1; } i() { a = 2; }\n";
+    let cst = Cst::parse(source);
+    let before = functions(&cst);
+
+    // lines added inside g move everything after it down
+    let edit_g = TextEdit {
+        span: Span {
+            start: Position { line: 9, column: 9 },
+            end: Position { line: 9, column: 9 },
+        },
+        text: String::from("\n    a *= 2;\n    a /= 2;"),
+    };
+    let cst = cst.edit(&edit_g);
+    let after = functions(&cst);
+    assert_eq!(after[0], before[0]);
+    assert_ne!(after[1], before[1]);
+    assert_eq!(after[2..], before[2..]);
+    assert_eq!(
+        format!("{:?}", cst),
+        format!("{:?}", Cst::parse(&cst.to_string()))
+    );
+
+    // so does a wider h on the same line as i
+    edit(source, (13, 11), (13, 12), "12345");
+    // the pragma applies to the reparsed function too
+    let cst = edit(source, (5, 8), (5, 9), "");
+    assert!(!cst.errors.is_empty());
+    // edits outside a function, or that break it, parse the whole file
+    edit(source, (2, 5), (2, 6), "b");
+    edit(source, (9, 8), (10, 1), "");
+    edit(source, (5, 5), (5, 5), "/*");
+}
+
+#[test]
+fn test_cst_edit_reparse_errors() {
+    let source = "new a = ;\n\nf() {\n    a++;\n}\n\ng() {\n    a = ;\n}\n\nh() { a--; }\n";
+    let at = |line, column| Position { line, column };
+    let insert = |position, text: &str| TextEdit {
+        span: Span {
+            start: position,
+            end: position,
+        },
+        text: String::from(text),
+    };
+    let cst = Cst::parse(source);
+    assert_eq!(cst.errors.len(), 2);
+    let before = functions(&cst);
+
+    // an edit that breaks f only reparses f, keeping the other errors
+    let cst = cst.edit(&insert(at(4, 9), "\n    b = ;"));
+    let after = functions(&cst);
+    assert_ne!(after[0], before[0]);
+    assert_eq!(after[1..], before[1..]);
+    assert_eq!(
+        format!("{:?}", cst),
+        format!("{:?}", Cst::parse(&cst.to_string()))
+    );
+    let lines: Vec<i32> = cst.errors.iter().map(|e| e.span.start.line).collect();
+    assert_eq!(lines, vec![1, 5, 9]);
+
+    // and fixing g drops its error
+    let before = after;
+    let cst = cst.edit(&insert(at(9, 9), "1"));
+    let after = functions(&cst);
+    assert_eq!(after[0], before[0]);
+    assert_ne!(after[1], before[1]);
+    assert_eq!(after[2], before[2]);
+    assert_eq!(
+        format!("{:?}", cst),
+        format!("{:?}", Cst::parse(&cst.to_string()))
+    );
+    assert_eq!(cst.errors.len(), 2);
+
+    // only a `#pragma` directive stops an edit from being incremental
+    let before = after;
+    let cst = cst.edit(&insert(at(4, 9), " // pragmatic"));
+    let after = functions(&cst);
+    assert_ne!(after[0], before[0]);
+    assert_eq!(after[1..], before[1..]);
+    edit(source, (4, 9), (4, 9), "\n#pragma semicolon 1\n    a++");
+    edit(source, (8, 5), (8, 5), "#pragma unused a\n    ");
+    // a function left unterminated could swallow what follows
+    edit(source, (5, 1), (5, 2), "");
+    edit(source, (9, 1), (9, 2), "");
+}
+
+#[test]
+fn test_cst_statements() {
+    let source = "f() {\n    a++;\n}\n\ng() <auto:idle> {\n    #emit load.pri a\n    __emit(stor.pri b, jump .);\n    for (;;) { break; continue; }\n    ;\nagain:\n    goto again;\n    assert a;\n    sleep 5;\n    exit;\n    return 1;\n}\n";
+    let cst = Cst::parse(source);
+    assert!(cst.errors.is_empty());
+    let g = cst.root.get(&cst.unit.items[1]).unwrap();
+    assert_eq!(kinds(g), vec![NodeKind::StateSpec, NodeKind::Block]);
+    assert_eq!(g.nodes().next().unwrap().to_string(), "<auto:idle>");
+
+    let body = match &cst.unit.items[1] {
+        Item::Function(f) => f.body.as_ref().unwrap(),
+        item => panic!("unexpected item {:?}", item),
+    };
+    assert_eq!(
+        kinds(cst.root.get(body).unwrap()),
+        vec![
+            NodeKind::Emit,
+            NodeKind::Emit,
+            NodeKind::For,
+            NodeKind::Empty,
+            NodeKind::Label,
+            NodeKind::Goto,
+            NodeKind::Assert,
+            NodeKind::Sleep,
+            NodeKind::Exit,
+            NodeKind::Return
+        ]
+    );
+    let emit = cst.root.get(&body.statements[1]).unwrap();
+    assert_eq!(
+        kinds(emit),
+        vec![NodeKind::EmitInstruction, NodeKind::EmitInstruction]
+    );
+    let store = emit.nodes().next().unwrap();
+    assert_eq!(store.to_string(), "stor.pri b");
+    assert_eq!(kinds(store), vec![NodeKind::Symbol]);
+    let lp = cst.root.get(&body.statements[2]).unwrap();
+    assert_eq!(
+        kinds(lp.nodes().next().unwrap()),
+        vec![NodeKind::Break, NodeKind::Continue]
+    );
+
+    // lines added to f move every node in g down without parsing it again
+    let before = functions(&cst);
+    let cst = cst.edit(&TextEdit {
+        span: Span {
+            start: Position { line: 2, column: 9 },
+            end: Position { line: 2, column: 9 },
+        },
+        text: String::from("\n    a--;"),
+    });
+    assert!(cst.errors.is_empty());
+    assert_eq!(functions(&cst)[1], before[1]);
+    assert_eq!(
+        format!("{:?}", cst),
+        format!("{:?}", Cst::parse(&cst.to_string()))
+    );
+}
+
+#[test]
+fn test_cst_edit_large() {
+    let source = gamemode(1250);
+    let cst = Cst::parse(&source);
+    assert!(cst.errors.is_empty(), "{:?}", cst.errors);
+    assert_eq!(cst.to_string().lines().count(), 20010);
+    let before = functions(&cst);
+
+    // `health` on the first line of GetHealth1000's body
+    let edit = TextEdit {
+        span: Span {
+            start: Position {
+                line: 16012,
+                column: 15,
+            },
+            end: Position {
+                line: 16012,
+                column: 16,
+            },
+        },
+        text: String::from("H"),
+    };
+    let cst = cst.edit(&edit);
+    let after = functions(&cst);
+    assert_eq!(after[..1000], before[..1000]);
+    assert_ne!(after[1000], before[1000]);
+    assert_eq!(after[1001..], before[1001..]);
+    assert!(cst
+        .to_string()
+        .contains("GetHealth1000(playerid, Float:offset = 0.0) {\n    new Float:Health,"));
+}
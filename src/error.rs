@@ -0,0 +1,86 @@
+use std::fmt;
+
+use crate::token::Span;
+use crate::token::TokenType;
+
+/// ErrorCode identifies the kind of a ParseError. Codes are stable so tools
+/// can match on them, and display as `E001` and so on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    UnexpectedToken = 1,    // a token that can't appear here
+    UnexpectedEnd = 2,      // input ended in the middle of a construct
+    UnterminatedBlock = 3,  // a block with no closing `}`
+    InvalidDeclaration = 4, // a variable, array or enum that can't be declared
+    InvalidFunction = 5,    // bad qualifiers, parameters or state specifiers
+    InvalidOperator = 6,    // an operator overload with the wrong shape
+    InvalidArgument = 7,    // a call argument in the wrong place
+    InvalidStatement = 8,   // a statement that is well-formed but not allowed
+    InvalidLiteral = 9,     // a literal with no valid value
+    InvalidEmit = 10,       // an unknown opcode or mismatched operand
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "E{:03}", *self as u8)
+    }
+}
+
+/// ParseError is a syntax error. `span` is where it was found and `related`
+/// points at other source, such as the `{` of an unterminated block. For
+/// unexpected tokens, `expected` lists the tokens that would have been
+/// accepted and `found` the token that was there instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+    pub related: Vec<(Span, String)>,
+    pub expected: Vec<TokenType>,
+    pub found: Option<TokenType>,
+    pub notes: Vec<String>,
+}
+
+impl ParseError {
+    pub fn new(code: ErrorCode, span: Span, message: String) -> ParseError {
+        ParseError {
+            code,
+            message,
+            span,
+            related: vec![],
+            expected: vec![],
+            found: None,
+            notes: vec![],
+        }
+    }
+
+    pub fn with_related(mut self, span: Span, message: &str) -> ParseError {
+        self.related.push((span, String::from(message)));
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> ParseError {
+        self.notes.push(String::from(note));
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: error[{}]: {}",
+            self.span.start.line, self.span.start.column, self.code, self.message
+        )?;
+        for (span, message) in &self.related {
+            write!(
+                f,
+                "\n{}:{}: {}",
+                span.start.line, span.start.column, message
+            )?;
+        }
+        for note in &self.notes {
+            write!(f, "\nnote: {}", note)?;
+        }
+        Ok(())
+    }
+}
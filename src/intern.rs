@@ -0,0 +1,183 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::sync::atomic;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Name is an interned identifier: a symbol, tag, label or state name. An
+/// Interner numbers the distinct names of its session, so two names from the
+/// same session compare by number, an integer compare, and cloning one only
+/// counts a reference to its text. Names from separate sessions, or made
+/// with `new`, fall back to comparing text, so equality never depends on
+/// where a name came from. Hashing and ordering follow the text, and the
+/// text is freed with the last Name that uses it.
+#[derive(Clone)]
+pub struct Name {
+    text: Arc<str>,
+    session: u64, // the Interner that numbered the name, or 0 for none
+    id: u32,
+}
+
+impl Name {
+    /// new makes a Name that isn't numbered by any session. Use an Interner
+    /// to make many.
+    pub fn new(name: &str) -> Name {
+        Name {
+            text: Arc::from(name),
+            session: 0,
+            id: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Default for Name {
+    fn default() -> Name {
+        Name::new("")
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Name {
+        Name::new(name)
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.text
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        if self.session != 0 && self.session == other.session {
+            self.id == other.id
+        } else {
+            self.text == other.text
+        }
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        &*self.text == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.text == *other
+    }
+}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // the same as the text's hash, which Borrow<str> relies on
+        self.text.hash(state)
+    }
+}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Name) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Name) -> Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.text, f)
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.text, f)
+    }
+}
+
+impl Serialize for Name {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.text)
+    }
+}
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Name, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Name::new(&name))
+    }
+}
+
+/// SESSIONS counts the Interners made so far, to number each one.
+static SESSIONS: AtomicU64 = AtomicU64::new(1);
+
+/// Interner hands out the Names of one parse session, allocating each
+/// distinct name once and numbering it. It belongs to whoever reads the
+/// source, such as a Lexer, and shares no table with other sessions, so
+/// sessions on different threads never wait on each other. Dropping it keeps
+/// the names it made alive for as long as a token or node still holds them.
+pub struct Interner {
+    session: u64,
+    names: HashSet<Name>,
+}
+
+impl Default for Interner {
+    fn default() -> Interner {
+        Interner {
+            session: SESSIONS.fetch_add(1, atomic::Ordering::Relaxed),
+            names: HashSet::new(),
+        }
+    }
+}
+
+impl Interner {
+    pub fn intern(&mut self, name: &str) -> Name {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+        let interned = Name {
+            text: Arc::from(name),
+            session: self.session,
+            id: u32::try_from(self.names.len()).expect("too many names in one session"),
+        };
+        self.names.insert(interned.clone());
+        interned
+    }
+}
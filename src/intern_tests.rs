@@ -0,0 +1,60 @@
+#[cfg(test)]
+use std::collections::HashSet;
+#[cfg(test)]
+use std::thread;
+
+#[cfg(test)]
+use crate::gamemode::gamemode;
+#[cfg(test)]
+use crate::intern::Interner;
+#[cfg(test)]
+use crate::intern::Name;
+#[cfg(test)]
+use crate::json;
+#[cfg(test)]
+use crate::lexer::Lexer;
+
+#[test]
+fn test_intern() {
+    let mut names = Interner::default();
+    let a = names.intern("player");
+    let b = names.intern(&String::from("player"));
+    assert_eq!(a, b);
+    assert_eq!(a.as_str().as_ptr(), b.as_str().as_ptr());
+    assert_ne!(a, names.intern("Player"));
+    assert_eq!(a, "player");
+    assert_eq!(a.to_string(), "player");
+    assert_eq!(format!("{:?}", a), "\"player\"");
+
+    // names from another interner are still equal, and hash the same
+    let other = Interner::default().intern("player");
+    assert_ne!(a.as_str().as_ptr(), other.as_str().as_ptr());
+    assert_eq!(a, other);
+    assert_eq!(a, Name::new("player"));
+    assert!(HashSet::from([a.clone()]).contains(&other));
+    assert_eq!(Name::default(), Name::new(""));
+    assert_ne!(Name::default(), names.intern("x"));
+
+    // names sort by text rather than by when they were interned
+    let mut sorted = vec![
+        names.intern("zeta"),
+        names.intern("alpha"),
+        names.intern("mid"),
+    ];
+    sorted.sort();
+    assert_eq!(sorted, vec!["alpha", "mid", "zeta"]);
+
+    let long = "x".repeat(10_000);
+    assert_eq!(names.intern(&long), names.intern(&long));
+    assert_eq!(names.intern(&long).len(), 10_000);
+
+    let decoded: Name = json::from_json(&json::to_json(&a)).unwrap();
+    assert_eq!(decoded, a);
+
+    // each thread lexes with an interner of its own
+    let source = gamemode(10);
+    let tokens = thread::spawn(move || Lexer::new(&source).lex())
+        .join()
+        .unwrap();
+    assert_eq!(tokens, Lexer::new(&gamemode(10)).lex());
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// VERSION is the version of the JSON encoding described on `to_json`. A
+/// release that encodes a token or AST node differently bumps it, so that a
+/// reader holding an older document, such as a cached AST, can tell instead
+/// of misreading it.
+pub const VERSION: u32 = 1;
+
+/// to_json encodes tokens, AST nodes or anything else the crate can
+/// serialise as a versioned JSON document: `{"version": 1, "value": ...}`.
+///
+/// Structs are objects keyed by field name, spans included, and enums of
+/// plain values, such as `TokenType`, are snake_case strings like
+/// `"plus_assign"`. Enums holding data, such as `Expr`, are objects naming
+/// the variant in `kind` with its data in `value`:
+/// `{"kind": "symbol", "value": {"name": "a", "span": ...}}`. Opcodes are
+/// their mnemonics, and floats are numbers unless they are infinite or NaN,
+/// which JSON has no numbers for and are `"inf"`, `"-inf"` and `"nan"`.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    let document = Document {
+        version: VERSION,
+        value,
+    };
+    // every type in the crate serialises, floats included, so the document
+    // always decodes again
+    serde_json::to_string(&document).unwrap()
+}
+
+/// from_json decodes a document written by `to_json`.
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, JsonError> {
+    // the version is checked first, since a document of another version may
+    // not decode at all
+    let header: Header = serde_json::from_str(json).map_err(JsonError::Invalid)?;
+    if header.version != VERSION {
+        return Err(JsonError::Version(header.version));
+    }
+    let document: Document<T> = serde_json::from_str(json).map_err(JsonError::Invalid)?;
+    Ok(document.value)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document<T> {
+    version: u32,
+    value: T,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// JsonError is why a document couldn't be decoded.
+#[derive(Debug)]
+pub enum JsonError {
+    Version(u32),               // written by another version of the encoding
+    Invalid(serde_json::Error), // not JSON, or not the expected shape
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Version(v) => write!(
+                f,
+                "unsupported encoding version {}, expected {}",
+                v, VERSION
+            ),
+            JsonError::Invalid(e) => write!(f, "invalid document: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// float is the `#[serde(with)]` encoding of token and literal floats, which
+/// keeps infinities and NaN that a plain f32 would write as `null`.
+pub(crate) mod float {
+    use serde::de;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_str("nan")
+        } else if value.is_infinite() && *value > 0.0 {
+            serializer.serialize_str("inf")
+        } else if value.is_infinite() {
+            serializer.serialize_str("-inf")
+        } else {
+            serializer.serialize_f32(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        match Encoded::deserialize(deserializer)? {
+            Encoded::Number(v) => Ok(v),
+            Encoded::Name(name) => match name.as_str() {
+                "inf" => Ok(f32::INFINITY),
+                "-inf" => Ok(f32::NEG_INFINITY),
+                "nan" => Ok(f32::NAN),
+                _ => Err(de::Error::custom(format!("invalid float `{}`", name))),
+            },
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Encoded {
+        Number(f32),
+        Name(String),
+    }
+}
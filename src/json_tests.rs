@@ -0,0 +1,145 @@
+#[cfg(test)]
+use crate::ast::visit_mut::VisitMut;
+#[cfg(test)]
+use crate::ast::CompilationUnit;
+#[cfg(test)]
+use crate::ast::EmitInstruction;
+#[cfg(test)]
+use crate::ast::Expr;
+#[cfg(test)]
+use crate::ast::Literal;
+#[cfg(test)]
+use crate::ast::LiteralValue;
+#[cfg(test)]
+use crate::ast::Parser;
+#[cfg(test)]
+use crate::json;
+#[cfg(test)]
+use crate::json::JsonError;
+#[cfg(test)]
+use crate::lexer::Lexer;
+#[cfg(test)]
+use crate::token::Token;
+#[cfg(test)]
+use crate::token::TokenType;
+#[cfg(test)]
+use crate::token::TokenValue;
+
+#[cfg(test)]
+fn parse(input: &str) -> CompilationUnit {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    p.parse().expect("failed to parse");
+    p.unit
+}
+
+#[test]
+fn test_json_tokens() {
+    let tokens = Lexer::new("new Float:x = 1.5; // x\nx += 'a';").lex();
+    let json = json::to_json(&tokens);
+    assert!(
+        json.starts_with(r#"{"version":1,"value":[{"token_type":"new","value":{"kind":"none"},"span":{"start":{"line":1,"column":1},"end":{"line":1,"column":4}}},"#)
+    );
+    assert!(json.contains(r#""token_type":"plus_assign""#));
+    assert!(json.contains(r#""value":{"kind":"name","value":"Float"}"#));
+    assert!(json.contains(r#""value":{"kind":"float","value":1.5}"#));
+
+    let decoded: Vec<Token> = json::from_json(&json).unwrap();
+    assert_eq!(decoded, tokens);
+    assert_eq!(decoded[0].token_type, TokenType::New);
+}
+
+#[test]
+fn test_json_ast() {
+    let unit = parse(
+        r#"
+#pragma semicolon 1
+new const a[2][] = {{1, 2}, {3, ...}}, Float:b = -1.5;
+enum E (<<= 1) { A, B[4] = 'x' }
+native Print(const s[], ...) = -1;
+native Float:operator*(Float:a, Float:b) = floatmul;
+
+main() <auto:idle> {
+    switch (a[0][1]) {
+        case 1, 2..3:
+            b = Float:a[0][0] ? b : -b;
+        default:
+            Print(_, .s = !"packed\n");
+    }
+    #emit load.s.pri 12
+    __emit(push.c 0, pop.pri);
+    state (a) idle;
+    return sizeof a + tagof b;
+}
+"#,
+    );
+    let json = json::to_json(&unit);
+    assert!(json.contains(r#"{"kind":"function","value":{"name":"main","#));
+    assert!(json.contains(r#""opcode":"load.s.pri""#));
+    assert!(json.contains(r#""span":{"start":{"line":3,"column":1},"end":{"line":3,"column":55}}"#));
+
+    // encoding the decoded AST gives back the same document
+    let decoded: CompilationUnit = json::from_json(&json).unwrap();
+    assert_eq!(format!("{:?}", decoded), format!("{:?}", unit));
+    assert_eq!(json::to_json(&decoded), json);
+
+    // any node can be encoded on its own
+    let expr: Expr = json::from_json(r#"{"version":1,"value":{"kind":"symbol","value":{"name":"a","span":{"start":{"line":1,"column":1},"end":{"line":1,"column":2}}}}}"#).unwrap();
+    assert_eq!(
+        json::to_json(&expr),
+        r#"{"version":1,"value":{"kind":"symbol","value":{"name":"a","span":{"start":{"line":1,"column":1},"end":{"line":1,"column":2}}}}}"#
+    );
+}
+
+#[test]
+fn test_json_floats() {
+    // the lexer only reads finite floats, but an AST built or rewritten by
+    // hand can hold any
+    struct Replace(Vec<f32>);
+    impl VisitMut for Replace {
+        fn visit_literal_mut(&mut self, literal: &mut Literal) {
+            literal.value = LiteralValue::Float(self.0.remove(0));
+        }
+    }
+
+    let mut unit = parse("new Float:a = 1.0, Float:b = 2.0, Float:c = 3.0, Float:d = 4.0;");
+    Replace(vec![f32::INFINITY, f32::NEG_INFINITY, f32::NAN, 0.1]).visit_unit_mut(&mut unit);
+    let json = json::to_json(&unit);
+    assert!(json.contains(r#""value":{"kind":"float","value":"inf"}"#));
+    assert!(json.contains(r#""value":{"kind":"float","value":"-inf"}"#));
+    assert!(json.contains(r#""value":{"kind":"float","value":"nan"}"#));
+    assert!(json.contains(r#""value":{"kind":"float","value":0.1}"#));
+
+    let decoded: CompilationUnit = json::from_json(&json).unwrap();
+    assert_eq!(json::to_json(&decoded), json);
+
+    let tokens: Vec<Token> = json::from_json(
+        r#"{"version":1,"value":[{"token_type":"float","value":{"kind":"float","value":"-inf"},"span":{"start":{"line":1,"column":1},"end":{"line":1,"column":1}}}]}"#,
+    )
+    .unwrap();
+    assert_eq!(tokens[0].value, TokenValue::Float(f32::NEG_INFINITY));
+    assert!(json::from_json::<Vec<Token>>(
+        r#"{"version":1,"value":[{"token_type":"float","value":{"kind":"float","value":"infinity"},"span":{"start":{"line":1,"column":1},"end":{"line":1,"column":1}}}]}"#,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_json_errors() {
+    assert!(matches!(
+        json::from_json::<CompilationUnit>(r#"{"version":2,"value":null}"#),
+        Err(JsonError::Version(2))
+    ));
+    assert!(matches!(
+        json::from_json::<CompilationUnit>(r#"{"version":1,"value":{"items":[]}}"#),
+        Err(JsonError::Invalid(_))
+    ));
+    let err = json::from_json::<Vec<Token>>(r#"{"version":1,"value":[{"opcode":"load.x"}]}"#)
+        .unwrap_err();
+    assert!(err.to_string().starts_with("invalid document: "));
+    assert!(json::from_json::<EmitInstruction>(
+        r#"{"version":1,"value":{"opcode":"load.x","operands":[],"span":{"start":{"line":1,"column":1},"end":{"line":1,"column":1}}}}"#
+    )
+    .unwrap_err()
+    .to_string()
+    .contains("unknown opcode `load.x`"));
+}
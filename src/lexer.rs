@@ -1,8 +1,11 @@
 use std::iter::Peekable;
 use std::str::Chars;
 
+use crate::intern::Interner;
 use crate::ring::Ring;
 use crate::token;
+use crate::token::Position;
+use crate::token::Span;
 use crate::token::Token;
 use crate::token::TokenType;
 use crate::token::TokenValue;
@@ -11,17 +14,32 @@ pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
     line: i32,
     column: i32,
+    start: Position,
+    trivia: bool,
+    symbol: String,  // reused to read each symbol, which is then interned
+    names: Interner, // the names read so far, shared by their tokens
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &str) -> Lexer {
+    pub fn new(input: &str) -> Lexer<'_> {
         Lexer {
             input: input.chars().peekable(),
             line: 1,
             column: 1,
+            start: Position { line: 1, column: 1 },
+            trivia: false,
+            symbol: String::new(),
+            names: Interner::default(),
         }
     }
 
+    /// with_trivia makes the lexer emit whitespace as `Whitespace` tokens
+    /// instead of skipping it, so that the tokens cover the whole input.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
     pub fn lex(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
         loop {
@@ -38,17 +56,18 @@ impl<'a> Lexer<'a> {
         Token {
             token_type: t,
             value: v,
-            line: self.line,
-            column: self.column,
-            range: v.len() as i32,
+            span: Span {
+                start: self.start,
+                end: Position {
+                    line: self.line,
+                    column: self.column,
+                },
+            },
         }
     }
 
     fn read_char(&mut self) -> Option<char> {
-        let next = match self.input.next() {
-            Some(v) => v,
-            None => return None,
-        };
+        let next = self.input.next()?;
         if next == '\n' {
             self.line += 1;
             self.column = 1;
@@ -71,43 +90,48 @@ impl<'a> Lexer<'a> {
 
     fn peek_char_eq_consume(&mut self, ch: char) -> bool {
         match self.peek_char() {
-            Some(&peek_ch) => {
-                if peek_ch == ch {
-                    self.read_char();
-                    true
-                } else {
-                    false
-                }
+            Some(&peek_ch) if peek_ch == ch => {
+                self.read_char();
+                true
             }
-            None => false,
+            _ => false,
         }
     }
 
     fn skip_whitespace(&mut self) {
+        while self.peek_char().is_some_and(|c| c.is_whitespace()) {
+            self.read_char();
+        }
+    }
+
+    fn read_whitespace(&mut self) -> String {
+        let mut whitespace = String::new();
         while let Some(&c) = self.peek_char() {
             if !c.is_whitespace() {
                 break;
             }
+            whitespace.push(c);
             self.read_char();
         }
+        whitespace
     }
 
-    fn peek_is_letter(&mut self) -> bool {
+    fn peek_is_symbol_char(&mut self) -> bool {
         match self.peek_char() {
-            Some(&ch) => is_letter(ch),
+            Some(&ch) => is_letter(ch) || ch.is_ascii_digit(),
             None => false,
         }
     }
 
     fn read_string_until(&mut self, until: &str) -> String {
         let mut result = String::new();
-        let until_len = until.len();
-        let mut recent_chars = Ring::new(until_len);
+        let mut recent_chars = Ring::new(until.chars().count());
 
         while let Some(c) = self.read_char() {
-            recent_chars.insert(c as u8);
-            if String::from(until) == String::from_utf8(recent_chars.unroll()).unwrap() {
-                result.truncate(result.len() - until_len);
+            recent_chars.insert(c);
+            if recent_chars.unroll().into_iter().eq(until.chars()) {
+                // the last char of `until` was never pushed
+                result.truncate(result.len() - (until.len() - c.len_utf8()));
                 break;
             }
             result.push(c);
@@ -127,53 +151,187 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_symbol(&mut self, first: char) -> Token {
-        let mut ident = String::new();
+        let mut ident = std::mem::take(&mut self.symbol);
+        ident.clear();
         ident.push(first);
 
-        while self.peek_is_letter() {
+        while self.peek_is_symbol_char() {
             ident.push(self.read_char().unwrap());
         }
 
-        Token {
-            token_type: token::lookup_keyword(&ident),
-            value: TokenValue::String(ident),
-            line: 0,
-            column: 0,
-            range: 0,
+        let token = match token::lookup_keyword(&ident) {
+            TokenType::Symbol => {
+                let name = self.names.intern(&ident);
+                self.gen_token(TokenType::Symbol, TokenValue::Name(name))
+            }
+            keyword => self.gen_token(keyword, TokenValue::None),
+        };
+        self.symbol = ident;
+        token
+    }
+
+    fn peek_second_char(&self) -> Option<char> {
+        self.input.clone().nth(1)
+    }
+
+    fn read_digits(&mut self, number: &mut String, radix: u32) {
+        while let Some(&c) = self.peek_char() {
+            if c == '_' {
+                self.read_char();
+            } else if c.is_digit(radix) {
+                number.push(c);
+                self.read_char();
+            } else {
+                break;
+            }
         }
     }
 
     fn read_number(&mut self, first: char) -> Token {
         let mut number = String::new();
-        number.push(first);
 
-        let mut has_decimal = false;
-        while let Some(&c) = self.peek_char() {
-            if !c.is_numeric() {
-                if c == '.' {
-                    has_decimal = true
-                } else if c == '_' {
-                    continue;
-                } else {
-                    break;
-                }
+        if first == '0' {
+            let radix = match self.peek_char() {
+                Some('x') => 16,
+                Some('b') => 2,
+                _ => 10,
+            };
+            if radix != 10 {
+                let prefix = self.read_char().unwrap();
+                self.read_digits(&mut number, radix);
+                // cells are 32 bits wide, so 0xFFFFFFFF is -1
+                return match u32::from_str_radix(&number, radix) {
+                    Ok(v) => self.gen_token(TokenType::Integer, TokenValue::Integer(v as i32)),
+                    Err(_) if number.is_empty() => self.gen_token(
+                        TokenType::Illegal,
+                        TokenValue::Malformed(format!("0{}", prefix)),
+                    ),
+                    Err(_) => self.gen_token(
+                        TokenType::Illegal,
+                        TokenValue::OutOfRange(format!("0{}{}", prefix, number)),
+                    ),
+                };
             }
+        }
+
+        number.push(first);
+        self.read_digits(&mut number, 10);
 
+        // a `.` only starts a fraction when a digit follows, so `1..3` stays
+        // a range
+        let mut is_float = false;
+        if self.peek_char() == Some(&'.')
+            && self.peek_second_char().is_some_and(|c| c.is_ascii_digit())
+        {
+            is_float = true;
             number.push(self.read_char().unwrap());
+            self.read_digits(&mut number, 10);
+
+            if self.peek_char() == Some(&'e') {
+                number.push(self.read_char().unwrap());
+                if self.peek_char() == Some(&'-') {
+                    number.push(self.read_char().unwrap());
+                }
+                self.read_digits(&mut number, 10);
+            }
         }
 
-        if has_decimal {
-            self.gen_token(TokenType::Float, TokenValue::Float(number.parse().unwrap()))
+        if is_float {
+            match number.parse::<f32>() {
+                Ok(v) if v.is_finite() => self.gen_token(TokenType::Float, TokenValue::Float(v)),
+                Ok(_) => self.gen_token(TokenType::Illegal, TokenValue::OutOfRange(number)),
+                // an exponent without digits, like `1.0e` in `1.0e+5`
+                Err(_) => self.gen_token(TokenType::Illegal, TokenValue::Malformed(number)),
+            }
         } else {
-            self.gen_token(
-                TokenType::Integer,
-                TokenValue::Integer(number.parse().unwrap()),
-            )
+            // decimal literals must fit in a cell; the parser lets
+            // 2147483648 through after a unary `-`
+            match number.parse::<i32>() {
+                Ok(v) => self.gen_token(TokenType::Integer, TokenValue::Integer(v)),
+                Err(_) => self.gen_token(TokenType::Illegal, TokenValue::OutOfRange(number)),
+            }
+        }
+    }
+
+    /// read_escape reads the character after a `\`. Numeric escapes take an
+    /// optional terminating `;`, as in `\x41;` or `\65;`.
+    fn read_escape(&mut self) -> Option<char> {
+        let c = self.read_char()?;
+        let radix = match c {
+            'a' => return Some('\x07'),
+            'b' => return Some('\x08'),
+            'e' => return Some('\x1b'),
+            'f' => return Some('\x0c'),
+            'n' => return Some('\n'),
+            'r' => return Some('\r'),
+            't' => return Some('\t'),
+            'v' => return Some('\x0b'),
+            'x' => 16,
+            '0'..='9' => 10,
+            c => return Some(c),
+        };
+
+        let mut number = String::new();
+        if radix == 10 {
+            number.push(c);
+        }
+        while let Some(&d) = self.peek_char() {
+            if !d.is_digit(radix) {
+                break;
+            }
+            number.push(d);
+            self.read_char();
+        }
+        self.peek_char_eq_consume(';');
+
+        u32::from_str_radix(&number, radix)
+            .ok()
+            .and_then(std::char::from_u32)
+    }
+
+    /// read_quoted reads a string or character literal body up to the
+    /// closing quote, resolving escapes. Returns None if the literal is
+    /// unterminated or contains an invalid escape.
+    fn read_quoted(&mut self, quote: char) -> Option<String> {
+        let mut result = String::new();
+        loop {
+            match self.read_char()? {
+                c if c == quote => return Some(result),
+                '\n' => return None,
+                '\\' => result.push(self.read_escape()?),
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn read_string(&mut self, t: TokenType) -> Token {
+        match self.read_quoted('"') {
+            Some(s) => self.gen_token(t, TokenValue::String(s)),
+            None => self.gen_token(TokenType::Illegal, TokenValue::None),
+        }
+    }
+
+    fn read_character(&mut self) -> Token {
+        let s = self.read_quoted('\'').unwrap_or_default();
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.gen_token(TokenType::Character, TokenValue::Integer(c as i32)),
+            _ => self.gen_token(TokenType::Illegal, TokenValue::String(s)),
         }
     }
 
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        if !self.trivia {
+            self.skip_whitespace();
+        }
+        self.start = Position {
+            line: self.line,
+            column: self.column,
+        };
+        if self.trivia && self.peek_char().is_some_and(|c| c.is_whitespace()) {
+            let whitespace = self.read_whitespace();
+            return self.gen_token(TokenType::Whitespace, TokenValue::String(whitespace));
+        }
 
         match self.read_char() {
             Some('=') => {
@@ -212,14 +370,16 @@ impl<'a> Lexer<'a> {
                 if self.peek_char_eq_consume('=') {
                     self.gen_token(TokenType::SlashAssign, TokenValue::None)
                 } else if self.peek_char_eq_consume('/') {
+                    let comment = self.read_until_eol();
                     self.gen_token(
                         TokenType::Comment,
-                        TokenValue::String(self.read_until_eol().trim_left().into()),
+                        TokenValue::String(comment.trim().into()),
                     )
                 } else if self.peek_char_eq_consume('*') {
+                    let comment = self.read_string_until("*/");
                     self.gen_token(
                         TokenType::Comment,
-                        TokenValue::String(self.read_string_until("*/").trim_left().into()),
+                        TokenValue::String(comment.trim().into()),
                     )
                 } else {
                     self.gen_token(TokenType::Slash, TokenValue::None)
@@ -276,6 +436,12 @@ impl<'a> Lexer<'a> {
                 } else if self.peek_char_eq_consume('>') {
                     if self.peek_char_eq_consume('=') {
                         self.gen_token(TokenType::BitRightAssign, TokenValue::None)
+                    } else if self.peek_char_eq_consume('>') {
+                        if self.peek_char_eq_consume('=') {
+                            self.gen_token(TokenType::BitRightUnsignedAssign, TokenValue::None)
+                        } else {
+                            self.gen_token(TokenType::BitRightUnsigned, TokenValue::None)
+                        }
                     } else {
                         self.gen_token(TokenType::BitRight, TokenValue::None)
                     }
@@ -286,10 +452,16 @@ impl<'a> Lexer<'a> {
             Some('!') => {
                 if self.peek_char_eq_consume('=') {
                     self.gen_token(TokenType::NotEqual, TokenValue::None)
+                } else if self.peek_char_eq_consume('"') {
+                    self.read_string(TokenType::PackedLiteral)
                 } else {
                     self.gen_token(TokenType::Bang, TokenValue::None)
                 }
             }
+            Some('"') => self.read_string(TokenType::Literal),
+            Some('\'') => self.read_character(),
+            Some('~') => self.gen_token(TokenType::Tilde, TokenValue::None),
+            Some('?') => self.gen_token(TokenType::Question, TokenValue::None),
             Some(';') => self.gen_token(TokenType::Semicolon, TokenValue::None),
             Some(':') => self.gen_token(TokenType::Colon, TokenValue::None),
             Some(',') => self.gen_token(TokenType::Comma, TokenValue::None),
@@ -307,15 +479,15 @@ impl<'a> Lexer<'a> {
                         self.gen_token(TokenType::Range, TokenValue::None)
                     }
                 } else {
-                    self.gen_token(TokenType::Illegal, TokenValue::None)
+                    self.gen_token(TokenType::Dot, TokenValue::None)
                 }
             }
             Some('#') => self.gen_token(TokenType::Directive, TokenValue::None),
 
-            Some(ch @ _) => {
+            Some(ch) => {
                 if is_letter(ch) {
                     self.read_symbol(ch)
-                } else if ch.is_numeric() {
+                } else if ch.is_ascii_digit() {
                     self.read_number(ch)
                 } else {
                     self.gen_token(TokenType::Illegal, TokenValue::None)
@@ -328,5 +500,5 @@ impl<'a> Lexer<'a> {
 }
 
 fn is_letter(ch: char) -> bool {
-    ch.is_alphabetic() || ch == '_'
+    ch.is_alphabetic() || ch == '_' || ch == '@'
 }
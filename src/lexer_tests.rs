@@ -1,48 +1,89 @@
 #[cfg(test)]
+use crate::intern::Name;
+#[cfg(test)]
 use crate::lexer::Lexer;
 #[cfg(test)]
-use crate::token::Token;
+use crate::token::TokenType;
+#[cfg(test)]
+use crate::token::TokenValue;
+
+#[cfg(test)]
+fn lex(input: &str) -> Vec<(TokenType, TokenValue)> {
+    Lexer::new(input)
+        .lex()
+        .into_iter()
+        .map(|t| (t.token_type, t.value))
+        .collect()
+}
 
 #[test]
 fn lex_comment_line() {
     assert_eq!(
-        Lexer::new("// comment").lex(),
-        vec![Token::Comment(String::from("comment")),],
+        lex("// comment"),
+        vec![(
+            TokenType::Comment,
+            TokenValue::String(String::from("comment"))
+        ),],
     );
 }
 
 #[test]
 fn lex_comment_block() {
     assert_eq!(
-        Lexer::new("/* comment */").lex(),
-        vec![Token::Comment(String::from("comment")),],
+        lex("/* comment */"),
+        vec![(
+            TokenType::Comment,
+            TokenValue::String(String::from("comment"))
+        ),],
     );
 }
 
 #[test]
 fn lex_comment_block_multi() {
     assert_eq!(
-        Lexer::new(
-            "/*
+        lex("/*
 comment on
 multiple lines
-*/"
-        )
-        .lex(),
-        vec![Token::Comment(String::from("comment on\nmultiple lines")),],
+*/"),
+        vec![(
+            TokenType::Comment,
+            TokenValue::String(String::from("comment on\nmultiple lines"))
+        ),],
+    );
+}
+
+#[test]
+fn lex_comment_block_multibyte() {
+    // `Ъ` and `Ī` both end in the byte of `*`
+    assert_eq!(
+        lex("/* Ъ/ Ī/ */ x"),
+        vec![
+            (
+                TokenType::Comment,
+                TokenValue::String(String::from("Ъ/ Ī/"))
+            ),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+        ],
+    );
+    assert_eq!(
+        lex("/* ünïcödé */"),
+        vec![(
+            TokenType::Comment,
+            TokenValue::String(String::from("ünïcödé"))
+        ),],
     );
 }
 
 #[test]
 fn lex_cell_declaration() {
     assert_eq!(
-        Lexer::new("new x = 5;").lex(),
+        lex("new x = 5;"),
         vec![
-            Token::New,
-            Token::Symbol(String::from("x")),
-            Token::Assign,
-            Token::Integer(5),
-            Token::Semicolon,
+            (TokenType::New, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::Assign, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(5)),
+            (TokenType::Semicolon, TokenValue::None),
         ],
     );
 }
@@ -50,15 +91,15 @@ fn lex_cell_declaration() {
 #[test]
 fn lex_float_decl() {
     assert_eq!(
-        Lexer::new("new Float:x = 5.5;").lex(),
+        lex("new Float:x = 5.5;"),
         vec![
-            Token::New,
-            Token::Symbol(String::from("Float")),
-            Token::Colon,
-            Token::Symbol(String::from("x")),
-            Token::Assign,
-            Token::Float(5.5),
-            Token::Semicolon,
+            (TokenType::New, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("Float"))),
+            (TokenType::Colon, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::Assign, TokenValue::None),
+            (TokenType::Float, TokenValue::Float(5.5)),
+            (TokenType::Semicolon, TokenValue::None),
         ],
         "float declaration"
     );
@@ -67,21 +108,21 @@ fn lex_float_decl() {
 #[test]
 fn lex_array_decl_autosize() {
     assert_eq!(
-        Lexer::new("new x[] = {1, 2, 3};").lex(),
+        lex("new x[] = {1, 2, 3};"),
         vec![
-            Token::New,
-            Token::Symbol(String::from("x")),
-            Token::LeftSquare,
-            Token::RightSquare,
-            Token::Assign,
-            Token::LeftBrace,
-            Token::Integer(1),
-            Token::Comma,
-            Token::Integer(2),
-            Token::Comma,
-            Token::Integer(3),
-            Token::RightBrace,
-            Token::Semicolon,
+            (TokenType::New, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::LeftSquare, TokenValue::None),
+            (TokenType::RightSquare, TokenValue::None),
+            (TokenType::Assign, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(1)),
+            (TokenType::Comma, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(2)),
+            (TokenType::Comma, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(3)),
+            (TokenType::RightBrace, TokenValue::None),
+            (TokenType::Semicolon, TokenValue::None),
         ]
     );
 }
@@ -89,22 +130,22 @@ fn lex_array_decl_autosize() {
 #[test]
 fn lex_array_decl() {
     assert_eq!(
-        Lexer::new("new x[4] = {1, 2, 3};").lex(),
+        lex("new x[4] = {1, 2, 3};"),
         vec![
-            Token::New,
-            Token::Symbol(String::from("x")),
-            Token::LeftSquare,
-            Token::Integer(4),
-            Token::RightSquare,
-            Token::Assign,
-            Token::LeftBrace,
-            Token::Integer(1),
-            Token::Comma,
-            Token::Integer(2),
-            Token::Comma,
-            Token::Integer(3),
-            Token::RightBrace,
-            Token::Semicolon,
+            (TokenType::New, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::LeftSquare, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(4)),
+            (TokenType::RightSquare, TokenValue::None),
+            (TokenType::Assign, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(1)),
+            (TokenType::Comma, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(2)),
+            (TokenType::Comma, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(3)),
+            (TokenType::RightBrace, TokenValue::None),
+            (TokenType::Semicolon, TokenValue::None),
         ],
         "array declaration specific size"
     );
@@ -113,8 +154,7 @@ fn lex_array_decl() {
 #[test]
 fn lex_basic_script() {
     assert_eq!(
-        Lexer::new(
-            "
+        lex("
 // Comment
 #include <a_samp>
 
@@ -128,54 +168,177 @@ main() {
         a = 0;
     }
 }
-"
-        )
-        .lex(),
+"),
+        vec![
+            (
+                TokenType::Comment,
+                TokenValue::String(String::from("Comment"))
+            ),
+            (TokenType::Directive, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("include"))),
+            (TokenType::LowerThan, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a_samp"))),
+            (TokenType::GreaterThan, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("main"))),
+            (TokenType::LeftBracket, TokenValue::None),
+            (TokenType::RightBracket, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::New, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::Semicolon, TokenValue::None),
+            (TokenType::If, TokenValue::None),
+            (TokenType::LeftBracket, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::Equal, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(3)),
+            (TokenType::RightBracket, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::PlusPlus, TokenValue::None),
+            (TokenType::Semicolon, TokenValue::None),
+            (TokenType::RightBrace, TokenValue::None),
+            (TokenType::Else, TokenValue::None),
+            (TokenType::If, TokenValue::None),
+            (TokenType::LeftBracket, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::NotEqual, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(3)),
+            (TokenType::RightBracket, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::MinusMinus, TokenValue::None),
+            (TokenType::Semicolon, TokenValue::None),
+            (TokenType::RightBrace, TokenValue::None),
+            (TokenType::Else, TokenValue::None),
+            (TokenType::LeftBrace, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::Assign, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(0)),
+            (TokenType::Semicolon, TokenValue::None),
+            (TokenType::RightBrace, TokenValue::None),
+            (TokenType::RightBrace, TokenValue::None),
+        ]
+    );
+}
+
+#[test]
+fn lex_operators() {
+    assert_eq!(
+        lex("a >>>= ~b ? c >>> 1 : d1"),
+        vec![
+            (TokenType::Symbol, TokenValue::Name(Name::from("a"))),
+            (TokenType::BitRightUnsignedAssign, TokenValue::None),
+            (TokenType::Tilde, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("b"))),
+            (TokenType::Question, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("c"))),
+            (TokenType::BitRightUnsigned, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(1)),
+            (TokenType::Colon, TokenValue::None),
+            (TokenType::Symbol, TokenValue::Name(Name::from("d1"))),
+        ]
+    );
+}
+
+#[test]
+fn lex_literals() {
+    assert_eq!(
+        lex("0x1F 0b101 1_000 0xFFFFFFFF 1.5e3 1..3 'a' '\\n' '\\x41;'"),
+        vec![
+            (TokenType::Integer, TokenValue::Integer(31)),
+            (TokenType::Integer, TokenValue::Integer(5)),
+            (TokenType::Integer, TokenValue::Integer(1000)),
+            (TokenType::Integer, TokenValue::Integer(-1)),
+            (TokenType::Float, TokenValue::Float(1500.0)),
+            (TokenType::Integer, TokenValue::Integer(1)),
+            (TokenType::Range, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(3)),
+            (TokenType::Character, TokenValue::Integer(97)),
+            (TokenType::Character, TokenValue::Integer(10)),
+            (TokenType::Character, TokenValue::Integer(65)),
+        ]
+    );
+}
+
+#[test]
+fn lex_literals_out_of_range() {
+    let number = |s: &str| (TokenType::Illegal, TokenValue::OutOfRange(String::from(s)));
+    let malformed = |s: &str| (TokenType::Illegal, TokenValue::Malformed(String::from(s)));
+    assert_eq!(
+        lex("2147483647 2147483648 4294967295 0x1FFFFFFFF 0x 1.0e99 1.0e+5"),
+        vec![
+            (TokenType::Integer, TokenValue::Integer(i32::MAX)),
+            number("2147483648"),
+            number("4294967295"),
+            number("0x1FFFFFFFF"),
+            malformed("0x"),
+            number("1.0e99"),
+            malformed("1.0e"),
+            (TokenType::Plus, TokenValue::None),
+            (TokenType::Integer, TokenValue::Integer(5)),
+        ]
+    );
+
+    // a `-` is not part of the number, so the parser decides on i32::MIN
+    assert_eq!(
+        lex("- /* min */ 2147483648"),
+        vec![
+            (TokenType::Minus, TokenValue::None),
+            (TokenType::Comment, TokenValue::String(String::from("min"))),
+            number("2147483648"),
+        ]
+    );
+}
+
+#[test]
+fn lex_strings() {
+    assert_eq!(
+        lex("\"hello\\tworld\\\"\" !\"packed\" \"\\65;\\x42;C\""),
+        vec![
+            (
+                TokenType::Literal,
+                TokenValue::String(String::from("hello\tworld\""))
+            ),
+            (
+                TokenType::PackedLiteral,
+                TokenValue::String(String::from("packed"))
+            ),
+            (TokenType::Literal, TokenValue::String(String::from("ABC"))),
+        ]
+    );
+    assert_eq!(
+        lex("\"unterminated\n\""),
+        vec![
+            (TokenType::Illegal, TokenValue::None),
+            (TokenType::Illegal, TokenValue::None),
+        ]
+    );
+}
+
+#[test]
+fn lex_trivia() {
+    let tokens: Vec<(TokenType, TokenValue)> = Lexer::new("new x; // x\n\tx++;")
+        .with_trivia()
+        .lex()
+        .into_iter()
+        .map(|t| (t.token_type, t.value))
+        .collect();
+    assert_eq!(
+        tokens,
         vec![
-            Token::Comment(String::from("Comment")),
-            Token::Directive,
-            Token::Symbol(String::from("include")),
-            Token::LowerThan,
-            Token::Symbol(String::from("a_samp")),
-            Token::GreaterThan,
-            Token::Symbol(String::from("main")),
-            Token::LeftBracket,
-            Token::RightBracket,
-            Token::LeftBrace,
-            Token::New,
-            Token::Symbol(String::from("a")),
-            Token::Semicolon,
-            Token::If,
-            Token::LeftBracket,
-            Token::Symbol(String::from("a")),
-            Token::Equal,
-            Token::Integer(3),
-            Token::RightBracket,
-            Token::LeftBrace,
-            Token::Symbol(String::from("a")),
-            Token::PlusPlus,
-            Token::Semicolon,
-            Token::RightBrace,
-            Token::Else,
-            Token::If,
-            Token::LeftBracket,
-            Token::Symbol(String::from("a")),
-            Token::NotEqual,
-            Token::Integer(3),
-            Token::RightBracket,
-            Token::LeftBrace,
-            Token::Symbol(String::from("a")),
-            Token::MinusMinus,
-            Token::Semicolon,
-            Token::RightBrace,
-            Token::Else,
-            Token::LeftBrace,
-            Token::Symbol(String::from("a")),
-            Token::Assign,
-            Token::Integer(0),
-            Token::Semicolon,
-            Token::RightBrace,
-            Token::RightBrace,
+            (TokenType::New, TokenValue::None),
+            (TokenType::Whitespace, TokenValue::String(String::from(" "))),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::Semicolon, TokenValue::None),
+            (TokenType::Whitespace, TokenValue::String(String::from(" "))),
+            (TokenType::Comment, TokenValue::String(String::from("x"))),
+            (
+                TokenType::Whitespace,
+                TokenValue::String(String::from("\t"))
+            ),
+            (TokenType::Symbol, TokenValue::Name(Name::from("x"))),
+            (TokenType::PlusPlus, TokenValue::None),
+            (TokenType::Semicolon, TokenValue::None),
         ]
     );
 }
@@ -1,6 +1,21 @@
 pub mod ast;
 mod ast_tests;
+pub mod cst;
+mod cst_tests;
+pub mod error;
+#[cfg(test)]
+#[path = "../benches/gamemode.rs"]
+mod gamemode;
+pub mod intern;
+mod intern_tests;
+pub mod json;
+mod json_tests;
 pub mod lexer;
 mod lexer_tests;
+pub mod opcode;
+mod print_tests;
 mod ring;
+pub mod states;
+mod states_tests;
 pub mod token;
+mod visit_tests;
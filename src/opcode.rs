@@ -0,0 +1,363 @@
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// OperandKind describes what an AMX instruction expects in each operand slot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperandKind {
+    Number,   // a plain constant
+    Address,  // a constant or a global symbol, resolved to its address
+    Offset,   // a constant or a local symbol, resolved to its frame offset
+    Function, // a function or native symbol
+    Label,    // a jump target
+}
+
+/// Opcode is an AMX instruction as named by its `#emit` mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    LoadPri,
+    LoadAlt,
+    LoadSPri,
+    LoadSAlt,
+    LrefPri,
+    LrefAlt,
+    LrefSPri,
+    LrefSAlt,
+    LoadI,
+    LodbI,
+    ConstPri,
+    ConstAlt,
+    AddrPri,
+    AddrAlt,
+    StorPri,
+    StorAlt,
+    StorSPri,
+    StorSAlt,
+    SrefPri,
+    SrefAlt,
+    SrefSPri,
+    SrefSAlt,
+    StorI,
+    StrbI,
+    Lidx,
+    LidxB,
+    Idxaddr,
+    IdxaddrB,
+    AlignPri,
+    AlignAlt,
+    Lctrl,
+    Sctrl,
+    MovePri,
+    MoveAlt,
+    Xchg,
+    PushPri,
+    PushAlt,
+    PushR,
+    PushC,
+    Push,
+    PushS,
+    PopPri,
+    PopAlt,
+    Stack,
+    Heap,
+    Proc,
+    Ret,
+    Retn,
+    Call,
+    CallPri,
+    Jump,
+    Jrel,
+    Jzer,
+    Jnz,
+    Jeq,
+    Jneq,
+    Jless,
+    Jleq,
+    Jgrtr,
+    Jgeq,
+    Jsless,
+    Jsleq,
+    Jsgrtr,
+    Jsgeq,
+    Shl,
+    Shr,
+    Sshr,
+    ShlCPri,
+    ShlCAlt,
+    ShrCPri,
+    ShrCAlt,
+    Smul,
+    Sdiv,
+    SdivAlt,
+    Umul,
+    Udiv,
+    UdivAlt,
+    Add,
+    Sub,
+    SubAlt,
+    And,
+    Or,
+    Xor,
+    Not,
+    Neg,
+    Invert,
+    AddC,
+    SmulC,
+    ZeroPri,
+    ZeroAlt,
+    Zero,
+    ZeroS,
+    SignPri,
+    SignAlt,
+    Eq,
+    Neq,
+    Less,
+    Leq,
+    Grtr,
+    Geq,
+    Sless,
+    Sleq,
+    Sgrtr,
+    Sgeq,
+    EqCPri,
+    EqCAlt,
+    IncPri,
+    IncAlt,
+    Inc,
+    IncS,
+    IncI,
+    DecPri,
+    DecAlt,
+    Dec,
+    DecS,
+    DecI,
+    Movs,
+    Cmps,
+    Fill,
+    Halt,
+    Bounds,
+    SysreqPri,
+    SysreqC,
+    File,
+    Line,
+    Symbol,
+    Srange,
+    JumpPri,
+    Switch,
+    Casetbl,
+    SwapPri,
+    SwapAlt,
+    PushAdr,
+    Nop,
+    SysreqN,
+    Symtag,
+    Break,
+}
+
+use OperandKind::*;
+
+const NONE: &[OperandKind] = &[];
+
+/// The full AMX instruction set, in opcode number order.
+const OPCODES: &[(Opcode, &str, &[OperandKind])] = &[
+    (Opcode::LoadPri, "load.pri", &[Address]),
+    (Opcode::LoadAlt, "load.alt", &[Address]),
+    (Opcode::LoadSPri, "load.s.pri", &[Offset]),
+    (Opcode::LoadSAlt, "load.s.alt", &[Offset]),
+    (Opcode::LrefPri, "lref.pri", &[Address]),
+    (Opcode::LrefAlt, "lref.alt", &[Address]),
+    (Opcode::LrefSPri, "lref.s.pri", &[Offset]),
+    (Opcode::LrefSAlt, "lref.s.alt", &[Offset]),
+    (Opcode::LoadI, "load.i", NONE),
+    (Opcode::LodbI, "lodb.i", &[Number]),
+    (Opcode::ConstPri, "const.pri", &[Address]),
+    (Opcode::ConstAlt, "const.alt", &[Address]),
+    (Opcode::AddrPri, "addr.pri", &[Offset]),
+    (Opcode::AddrAlt, "addr.alt", &[Offset]),
+    (Opcode::StorPri, "stor.pri", &[Address]),
+    (Opcode::StorAlt, "stor.alt", &[Address]),
+    (Opcode::StorSPri, "stor.s.pri", &[Offset]),
+    (Opcode::StorSAlt, "stor.s.alt", &[Offset]),
+    (Opcode::SrefPri, "sref.pri", &[Address]),
+    (Opcode::SrefAlt, "sref.alt", &[Address]),
+    (Opcode::SrefSPri, "sref.s.pri", &[Offset]),
+    (Opcode::SrefSAlt, "sref.s.alt", &[Offset]),
+    (Opcode::StorI, "stor.i", NONE),
+    (Opcode::StrbI, "strb.i", &[Number]),
+    (Opcode::Lidx, "lidx", NONE),
+    (Opcode::LidxB, "lidx.b", &[Number]),
+    (Opcode::Idxaddr, "idxaddr", NONE),
+    (Opcode::IdxaddrB, "idxaddr.b", &[Number]),
+    (Opcode::AlignPri, "align.pri", &[Number]),
+    (Opcode::AlignAlt, "align.alt", &[Number]),
+    (Opcode::Lctrl, "lctrl", &[Number]),
+    (Opcode::Sctrl, "sctrl", &[Number]),
+    (Opcode::MovePri, "move.pri", NONE),
+    (Opcode::MoveAlt, "move.alt", NONE),
+    (Opcode::Xchg, "xchg", NONE),
+    (Opcode::PushPri, "push.pri", NONE),
+    (Opcode::PushAlt, "push.alt", NONE),
+    (Opcode::PushR, "push.r", &[Number]),
+    (Opcode::PushC, "push.c", &[Address]),
+    (Opcode::Push, "push", &[Address]),
+    (Opcode::PushS, "push.s", &[Offset]),
+    (Opcode::PopPri, "pop.pri", NONE),
+    (Opcode::PopAlt, "pop.alt", NONE),
+    (Opcode::Stack, "stack", &[Number]),
+    (Opcode::Heap, "heap", &[Number]),
+    (Opcode::Proc, "proc", NONE),
+    (Opcode::Ret, "ret", NONE),
+    (Opcode::Retn, "retn", NONE),
+    (Opcode::Call, "call", &[Function]),
+    (Opcode::CallPri, "call.pri", NONE),
+    (Opcode::Jump, "jump", &[Label]),
+    (Opcode::Jrel, "jrel", &[Number]),
+    (Opcode::Jzer, "jzer", &[Label]),
+    (Opcode::Jnz, "jnz", &[Label]),
+    (Opcode::Jeq, "jeq", &[Label]),
+    (Opcode::Jneq, "jneq", &[Label]),
+    (Opcode::Jless, "jless", &[Label]),
+    (Opcode::Jleq, "jleq", &[Label]),
+    (Opcode::Jgrtr, "jgrtr", &[Label]),
+    (Opcode::Jgeq, "jgeq", &[Label]),
+    (Opcode::Jsless, "jsless", &[Label]),
+    (Opcode::Jsleq, "jsleq", &[Label]),
+    (Opcode::Jsgrtr, "jsgrtr", &[Label]),
+    (Opcode::Jsgeq, "jsgeq", &[Label]),
+    (Opcode::Shl, "shl", NONE),
+    (Opcode::Shr, "shr", NONE),
+    (Opcode::Sshr, "sshr", NONE),
+    (Opcode::ShlCPri, "shl.c.pri", &[Number]),
+    (Opcode::ShlCAlt, "shl.c.alt", &[Number]),
+    (Opcode::ShrCPri, "shr.c.pri", &[Number]),
+    (Opcode::ShrCAlt, "shr.c.alt", &[Number]),
+    (Opcode::Smul, "smul", NONE),
+    (Opcode::Sdiv, "sdiv", NONE),
+    (Opcode::SdivAlt, "sdiv.alt", NONE),
+    (Opcode::Umul, "umul", NONE),
+    (Opcode::Udiv, "udiv", NONE),
+    (Opcode::UdivAlt, "udiv.alt", NONE),
+    (Opcode::Add, "add", NONE),
+    (Opcode::Sub, "sub", NONE),
+    (Opcode::SubAlt, "sub.alt", NONE),
+    (Opcode::And, "and", NONE),
+    (Opcode::Or, "or", NONE),
+    (Opcode::Xor, "xor", NONE),
+    (Opcode::Not, "not", NONE),
+    (Opcode::Neg, "neg", NONE),
+    (Opcode::Invert, "invert", NONE),
+    (Opcode::AddC, "add.c", &[Number]),
+    (Opcode::SmulC, "smul.c", &[Number]),
+    (Opcode::ZeroPri, "zero.pri", NONE),
+    (Opcode::ZeroAlt, "zero.alt", NONE),
+    (Opcode::Zero, "zero", &[Address]),
+    (Opcode::ZeroS, "zero.s", &[Offset]),
+    (Opcode::SignPri, "sign.pri", NONE),
+    (Opcode::SignAlt, "sign.alt", NONE),
+    (Opcode::Eq, "eq", NONE),
+    (Opcode::Neq, "neq", NONE),
+    (Opcode::Less, "less", NONE),
+    (Opcode::Leq, "leq", NONE),
+    (Opcode::Grtr, "grtr", NONE),
+    (Opcode::Geq, "geq", NONE),
+    (Opcode::Sless, "sless", NONE),
+    (Opcode::Sleq, "sleq", NONE),
+    (Opcode::Sgrtr, "sgrtr", NONE),
+    (Opcode::Sgeq, "sgeq", NONE),
+    (Opcode::EqCPri, "eq.c.pri", &[Number]),
+    (Opcode::EqCAlt, "eq.c.alt", &[Number]),
+    (Opcode::IncPri, "inc.pri", NONE),
+    (Opcode::IncAlt, "inc.alt", NONE),
+    (Opcode::Inc, "inc", &[Address]),
+    (Opcode::IncS, "inc.s", &[Offset]),
+    (Opcode::IncI, "inc.i", NONE),
+    (Opcode::DecPri, "dec.pri", NONE),
+    (Opcode::DecAlt, "dec.alt", NONE),
+    (Opcode::Dec, "dec", &[Address]),
+    (Opcode::DecS, "dec.s", &[Offset]),
+    (Opcode::DecI, "dec.i", NONE),
+    (Opcode::Movs, "movs", &[Number]),
+    (Opcode::Cmps, "cmps", &[Number]),
+    (Opcode::Fill, "fill", &[Number]),
+    (Opcode::Halt, "halt", &[Number]),
+    (Opcode::Bounds, "bounds", &[Number]),
+    (Opcode::SysreqPri, "sysreq.pri", NONE),
+    (Opcode::SysreqC, "sysreq.c", &[Function]),
+    (Opcode::File, "file", &[Number]),
+    (Opcode::Line, "line", &[Number]),
+    (Opcode::Symbol, "symbol", &[Number]),
+    (Opcode::Srange, "srange", &[Number]),
+    (Opcode::JumpPri, "jump.pri", NONE),
+    (Opcode::Switch, "switch", &[Label]),
+    (Opcode::Casetbl, "casetbl", NONE),
+    (Opcode::SwapPri, "swap.pri", NONE),
+    (Opcode::SwapAlt, "swap.alt", NONE),
+    (Opcode::PushAdr, "push.adr", &[Offset]),
+    (Opcode::Nop, "nop", NONE),
+    (Opcode::SysreqN, "sysreq.n", &[Function, Number]),
+    (Opcode::Symtag, "symtag", &[Number]),
+    (Opcode::Break, "break", NONE),
+];
+
+impl Opcode {
+    pub fn mnemonic(&self) -> &'static str {
+        match OPCODES.iter().find(|(op, _, _)| op == self) {
+            Some((_, mnemonic, _)) => mnemonic,
+            None => unreachable!(),
+        }
+    }
+
+    pub fn operands(&self) -> &'static [OperandKind] {
+        match OPCODES.iter().find(|(op, _, _)| op == self) {
+            Some((_, _, operands)) => operands,
+            None => unreachable!(),
+        }
+    }
+}
+
+// opcodes are encoded as their mnemonics, which are stable where the variant
+// names are not
+impl Serialize for Opcode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.mnemonic())
+    }
+}
+
+impl<'de> Deserialize<'de> for Opcode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Opcode, D::Error> {
+        let mnemonic = String::deserialize(deserializer)?;
+        lookup_opcode(&mnemonic)
+            .ok_or_else(|| de::Error::custom(format!("unknown opcode `{}`", mnemonic)))
+    }
+}
+
+/// lookup_opcode resolves a mnemonic, ignoring case as pawncc does.
+pub fn lookup_opcode(mnemonic: &str) -> Option<Opcode> {
+    let mnemonic = mnemonic.to_lowercase();
+    OPCODES
+        .iter()
+        .find(|(_, m, _)| *m == mnemonic)
+        .map(|(op, _, _)| *op)
+}
+
+/// is_mnemonic_prefix reports whether some mnemonic starts with `prefix.`,
+/// used to decide whether a `.` after a mnemonic part continues it.
+pub fn is_mnemonic_prefix(prefix: &str) -> bool {
+    let prefix = format!("{}.", prefix.to_lowercase());
+    OPCODES.iter().any(|(_, m, _)| m.starts_with(&prefix))
+}
+
+#[test]
+fn test_opcode_table() {
+    assert_eq!(OPCODES.len(), 137);
+    assert_eq!(lookup_opcode("LOAD.S.pri"), Some(Opcode::LoadSPri));
+    assert_eq!(lookup_opcode("load.s"), None);
+    assert!(is_mnemonic_prefix("load.s"));
+    assert!(!is_mnemonic_prefix("load.pri"));
+    for (op, mnemonic, _) in OPCODES {
+        assert_eq!(op.mnemonic(), *mnemonic);
+        assert_eq!(lookup_opcode(mnemonic), Some(*op));
+    }
+}
@@ -0,0 +1,244 @@
+#[cfg(test)]
+use crate::ast::print::to_source;
+#[cfg(test)]
+use crate::ast::CompilationUnit;
+#[cfg(test)]
+use crate::ast::Item;
+#[cfg(test)]
+use crate::ast::Parser;
+#[cfg(test)]
+use crate::ast::Stmt;
+#[cfg(test)]
+use crate::lexer::Lexer;
+
+#[cfg(test)]
+fn parse(input: &str) -> CompilationUnit {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    if let Err(errors) = p.parse() {
+        panic!("failed to parse {:?}: {:?}", input, errors);
+    }
+    p.unit
+}
+
+/// without_spans renders a unit with every span removed, so two parses of
+/// differently laid out source compare equal.
+#[cfg(test)]
+fn without_spans(unit: &CompilationUnit) -> String {
+    let mut s = format!("{:?}", unit);
+    while let Some(start) = s.find("Span {") {
+        let mut depth = 0;
+        let mut end = start;
+        for (i, c) in s[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => continue,
+            }
+            if depth == 0 {
+                end = start + i + 1;
+                break;
+            }
+        }
+        s.replace_range(start..end, "Span");
+    }
+    s
+}
+
+/// round_trip prints a parsed script, checks that parsing the output gives
+/// the same AST and printing it again the same text, and returns the text.
+#[cfg(test)]
+fn round_trip(input: &str) -> String {
+    let unit = parse(input);
+    let printed = to_source(&unit);
+    let reparsed = parse(&printed);
+    assert_eq!(
+        without_spans(&unit),
+        without_spans(&reparsed),
+        "{}",
+        printed
+    );
+    assert_eq!(printed, to_source(&reparsed));
+    printed
+}
+
+/// expr prints the expression in `main() { <input>; }`.
+#[cfg(test)]
+fn expr(input: &str) -> String {
+    let unit = parse(&format!("main() {{\n{};\n}}", input));
+    let printed = match &unit.items[..] {
+        [Item::Function(f)] => match &f.body.as_ref().unwrap().statements[..] {
+            [Stmt::Expr(e)] => to_source(e),
+            s => panic!("unexpected statements {:?}", s),
+        },
+        items => panic!("unexpected items {:?}", items),
+    };
+    round_trip(&format!("main() {{\n{};\n}}", input));
+    printed
+}
+
+#[test]
+fn test_print_parentheses() {
+    assert_eq!(expr("(a + b) * c"), "(a + b) * c");
+    assert_eq!(expr("a + (b * c)"), "a + b * c");
+    assert_eq!(expr("(a - b) - c"), "a - b - c");
+    assert_eq!(expr("a - (b - c)"), "a - (b - c)");
+    assert_eq!(expr("a = (b = c)"), "a = b = c");
+    assert_eq!(expr("(a = b) = c"), "(a = b) = c");
+    assert_eq!(expr("(a & b) == c"), "a & b == c");
+    assert_eq!(expr("a & (b == c)"), "a & (b == c)");
+    assert_eq!(expr("a ? b : (c ? d : e)"), "a ? b : c ? d : e");
+    assert_eq!(expr("(a ? b : c) ? d : e"), "(a ? b : c) ? d : e");
+    assert_eq!(expr("a ? (b, c) : d"), "a ? (b, c) : d");
+    assert_eq!(expr("f((a, b), c)"), "f((a, b), c)");
+    assert_eq!(expr("(-a)++"), "(-a)++");
+    assert_eq!(expr("-(a++)"), "-a++");
+    assert_eq!(expr("-(-a)"), "-(-a)");
+    assert_eq!(expr("-(5)"), "-(5)");
+    assert_eq!(expr("!(!a)"), "!!a");
+    assert_eq!(expr("a[(b, c)]"), "a[b, c]");
+    assert_eq!(expr("(sizeof a) + 1"), "sizeof a + 1");
+    assert_eq!(expr("sizeof(a[][0])"), "sizeof a[][0]");
+    assert_eq!(expr("tagof(Float:)"), "tagof(Float:)");
+    assert_eq!(expr("(a + 1) char"), "(a + 1) char");
+    assert_eq!(
+        expr("__nameof(a) + __addressof(b)"),
+        "__nameof(a) + __addressof(b)"
+    );
+}
+
+#[test]
+fn test_print_tags() {
+    assert_eq!(expr("(Float:a) + b"), "Float:a + b");
+    assert_eq!(
+        round_trip("main() {\n(Float:a) + b;\n}"),
+        "main() {\n    (Float:a + b);\n}\n"
+    );
+    assert_eq!(expr("x = Float:a + b"), "x = Float:a + b");
+    assert_eq!(expr("x = Float:(5)"), "x = Float:(5)");
+    assert_eq!(expr("x = Float:5"), "x = Float:5");
+    assert_eq!(expr("x = Float:-5.0"), "x = Float:-5.0");
+    assert_eq!(
+        expr("x = a ? (Float:b) : Float:c"),
+        "x = a ? (Float:b) : Float:c"
+    );
+    assert_eq!(expr("x = a ? f(Float:b) : c"), "x = a ? f(Float:b) : c");
+    assert_eq!(expr("x = (Float:a)++"), "x = (Float:a)++");
+}
+
+#[test]
+fn test_print_literals() {
+    assert_eq!(expr("x = 0xFFFFFFFF"), "x = 0xFFFFFFFF");
+    assert_eq!(expr("x = 0.000_000_1"), "x = 1.0e-7");
+    assert_eq!(expr("x = 1.5"), "x = 1.5");
+    assert_eq!(expr("x = -2147483648"), "x = -2147483648");
+    assert_eq!(expr("x = -0x80000000"), "x = -2147483648");
+    assert_eq!(expr("x = '\\''"), "x = '\\''");
+    assert_eq!(
+        expr("x = \"a\\\"b\\\\c\\n\\x1;\""),
+        "x = \"a\\\"b\\\\c\\n\\x1;\""
+    );
+    assert_eq!(expr("x = !\"packed\""), "x = !\"packed\"");
+    assert_eq!(expr("x = !(\"a\")"), "x = !(\"a\")");
+    assert_eq!(expr("x = true"), "x = true");
+}
+
+#[test]
+fn test_print_statements() {
+    let printed =
+        round_trip("main(){if(a)b();else if(c){d();}else e();while(a)a--;do{a++;}while(a<10);}");
+    assert_eq!(
+        printed,
+        r#"main() {
+    if (a)
+        b();
+    else if (c) {
+        d();
+    } else
+        e();
+    while (a)
+        a--;
+    do {
+        a++;
+    } while (a < 10);
+}
+"#
+    );
+    assert_eq!(
+        round_trip("main() return 1;"),
+        "main() {\n    return 1;\n}\n"
+    );
+}
+
+#[test]
+fn test_print_round_trip() {
+    round_trip(
+        r#"
+#pragma semicolon 1
+#include <a_samp>
+
+new a, Float:b = 1.0, c[3][] = {{1, 2}, {3, ...}, {}};
+static const d = -5;
+stock const e = 'x', f[] = "str", g[] = !"packed";
+const h = 4;
+
+enum Float:Pos (<<= 1) {
+    Float:X = 1.0,
+    Name[24],
+    Y,
+}
+
+enum {
+    A,
+    B = A + 3,
+}
+
+forward OnGameModeInit();
+native SetTimer(const name[], interval, bool:repeat) = -1;
+native Float:operator+(Float:a, Float:b) = floatadd;
+stock Float:operator-(Float:a) {
+    return Float:(_:a ^ 0x80000000);
+}
+stock operator~(File:f[], count) {
+    fclose(f[0]);
+}
+
+public OnGameModeInit() <auto:idle, busy> {
+    new i = 0, j;
+    for (new k = 0; k < 10; k++) {
+        continue;
+    }
+    for (i = 0, j = 1; ; ) break;
+    for (;;) {}
+    switch (i) {
+        case 1, 2 .. 4:
+            j = i;
+        case 'a', -1: {
+            j = sizeof c + tagof b;
+        }
+        default: {}
+    }
+    state (i > 0) auto:busy;
+    state idle;
+    assert i == 0;
+    sleep 10;
+    exit;
+loop:
+    goto loop;
+    #emit load.s.pri 12
+    #emit jrel .-8
+    __emit(push.c 0, pop.pri);
+    SetTimer(.interval = 5, .name = "a", .repeat = true);
+    Format(_, sizeof c, _:b, c[0][1], "a" char);
+    return defined a ? 1 : 0;
+}
+
+stock Format(output[] = "", len = sizeof output, {Float, _}:...) {
+    return len;
+}
+
+Get(&a, const &b = 0, Float:c[][2]) {
+    return a++ + ++b + _:c[0][0];
+}
+"#,
+    );
+}
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::ast::visit::Visitor;
+use crate::ast::CompilationUnit;
+use crate::ast::Expr;
+use crate::ast::FunctionDecl;
+use crate::ast::Item;
+use crate::ast::State;
+use crate::ast::StateSpec;
+use crate::ast::Tag;
+use crate::error::ErrorCode;
+use crate::error::ParseError;
+use crate::intern::Name;
+use crate::token::Span;
+
+/// check verifies the state-dispatched functions in a parsed script. Every
+/// implementation of a function must share one automaton and one signature,
+/// no state may be implemented twice, and a function that doesn't cover
+/// every state its automaton can enter needs a `<>` fallback. `entry`
+/// functions are exempt from the fallback rule since a state without one
+/// simply has no entry code. Every problem found is reported, each pointing
+/// at the implementation it conflicts with.
+pub fn check(unit: &CompilationUnit) -> Result<(), Vec<ParseError>> {
+    let mut collector = StateCollector::default();
+    collector.visit_unit(unit);
+    let automata = collector.automata;
+
+    let mut functions: Vec<(&Name, Vec<&FunctionDecl>)> = vec![];
+    for item in &unit.items {
+        if let Item::Function(f) = item {
+            if f.body.is_none() {
+                continue;
+            }
+            match functions.iter_mut().find(|(name, _)| **name == f.name) {
+                Some((_, implementations)) => implementations.push(f),
+                None => functions.push((&f.name, vec![f])),
+            }
+        }
+    }
+
+    let mut errors = vec![];
+    for (name, implementations) in &functions {
+        let with_states: Vec<(&FunctionDecl, &StateSpec)> = implementations
+            .iter()
+            .filter_map(|f| f.states.as_ref().map(|spec| (*f, spec)))
+            .collect();
+        let Some(&(first, first_spec)) = with_states.first() else {
+            continue;
+        };
+        if let Some(f) = implementations.iter().find(|f| f.states.is_none()) {
+            errors.push(
+                error(
+                    f.span,
+                    format!(
+                        "function `{}` is defined both with and without states",
+                        name
+                    ),
+                )
+                .with_related(first_spec.span, "implemented with states here"),
+            );
+            continue;
+        }
+
+        let automaton = &first_spec.automaton;
+        let mut implemented: BTreeMap<&Name, Span> = BTreeMap::new();
+        let mut fallback: Option<Span> = None;
+        for &(f, spec) in &with_states {
+            if spec.automaton != *automaton {
+                errors.push(
+                    error(
+                        spec.span,
+                        format!(
+                            "function `{}` is implemented for more than one automaton",
+                            name
+                        ),
+                    )
+                    .with_related(first_spec.span, "first implemented here"),
+                );
+                continue;
+            }
+            if !same_signature(first, f) {
+                errors.push(
+                    error(
+                        f.span,
+                        format!(
+                            "function `{}` has a different signature in state {}",
+                            name,
+                            describe(&spec.states)
+                        ),
+                    )
+                    .with_related(first.span, "first implemented here"),
+                );
+            }
+            if spec.states.is_empty() {
+                match fallback {
+                    Some(previous) => errors.push(
+                        error(
+                            spec.span,
+                            format!("function `{}` has more than one fallback", name),
+                        )
+                        .with_related(previous, "first fallback here"),
+                    ),
+                    None => fallback = Some(spec.span),
+                }
+            }
+            for state in &spec.states {
+                match implemented.get(state) {
+                    Some(&previous) => errors.push(
+                        error(
+                            spec.span,
+                            format!(
+                                "function `{}` is implemented twice for state `{}`",
+                                name, state
+                            ),
+                        )
+                        .with_related(previous, "first implemented here"),
+                    ),
+                    None => {
+                        implemented.insert(state, spec.span);
+                    }
+                }
+            }
+        }
+
+        if fallback.is_some() || *name == "entry" {
+            continue;
+        }
+        let Some(states) = automata.get(automaton) else {
+            continue;
+        };
+        for (state, entered) in states {
+            if !implemented.contains_key(state) {
+                errors.push(
+                    error(
+                        first_spec.span,
+                        format!(
+                            "function `{}` has no implementation for state `{}` and no fallback",
+                            name, state
+                        ),
+                    )
+                    .with_related(*entered, "state used here"),
+                );
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn error(span: Span, message: String) -> ParseError {
+    ParseError::new(ErrorCode::InvalidFunction, span, message)
+}
+
+/// StateCollector records every state each automaton can be in, from both
+/// function specifiers and `state` statements, with where each first
+/// appears.
+#[derive(Default)]
+struct StateCollector {
+    automata: HashMap<Option<Name>, BTreeMap<Name, Span>>,
+}
+
+impl Visitor for StateCollector {
+    fn visit_state_spec(&mut self, spec: &StateSpec) {
+        let states = self.automata.entry(spec.automaton.clone()).or_default();
+        for state in &spec.states {
+            states.entry(state.clone()).or_insert(spec.span);
+        }
+    }
+
+    fn visit_state(&mut self, state: &State) {
+        let states = self.automata.entry(state.automaton.clone()).or_default();
+        states.entry(state.name.clone()).or_insert(state.span);
+    }
+
+    // states can't change in the middle of an expression
+    fn visit_expr(&mut self, _expr: &Expr) {}
+}
+
+fn same_signature(a: &FunctionDecl, b: &FunctionDecl) -> bool {
+    let same_tags = |a: &[Tag], b: &[Tag]| a.iter().map(|t| &t.name).eq(b.iter().map(|t| &t.name));
+    a.tag.as_ref().map(|t| &t.name) == b.tag.as_ref().map(|t| &t.name)
+        && a.parameters.len() == b.parameters.len()
+        && a.parameters.iter().zip(&b.parameters).all(|(a, b)| {
+            same_tags(&a.tags, &b.tags)
+                && a.reference == b.reference
+                && a.constant == b.constant
+                && a.dimensions.len() == b.dimensions.len()
+                && a.variadic == b.variadic
+        })
+}
+
+fn describe(states: &[Name]) -> String {
+    if states.is_empty() {
+        String::from("<>")
+    } else {
+        let states: Vec<&str> = states.iter().map(|s| s.as_str()).collect();
+        format!("<{}>", states.join(", "))
+    }
+}
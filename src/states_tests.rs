@@ -0,0 +1,139 @@
+#[cfg(test)]
+use crate::ast::Parser;
+#[cfg(test)]
+use crate::error::ErrorCode;
+#[cfg(test)]
+use crate::error::ParseError;
+#[cfg(test)]
+use crate::lexer::Lexer;
+#[cfg(test)]
+use crate::states;
+#[cfg(test)]
+use crate::token::Span;
+
+#[cfg(test)]
+fn errors(input: &str) -> Vec<ParseError> {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    if let Err(e) = p.parse() {
+        panic!("failed to parse {:?}: {:?}", input, e);
+    }
+    states::check(&p.unit).err().unwrap_or_default()
+}
+
+/// check returns the message of each state error, joined by `; `.
+#[cfg(test)]
+fn check(input: &str) -> Result<(), String> {
+    let errors = errors(input);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(errors
+        .iter()
+        .map(|e| e.message.clone())
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+/// at returns the line and column a span starts at.
+#[cfg(test)]
+fn at(span: Span) -> (i32, i32) {
+    (span.start.line, span.start.column)
+}
+
+#[test]
+fn test_states_consistent() {
+    assert_eq!(
+        check(
+            "
+main() { state idle; }
+Foo() <idle> {}
+Foo() <running> {}
+Foo() <> {}
+Bar(a) <idle, running> { state running; }
+entry() <idle> {}
+"
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_states_fallback() {
+    assert_eq!(
+        check("main() { state running; } Foo() <idle> {}"),
+        Err(String::from(
+            "function `Foo` has no implementation for state `running` and no fallback"
+        ))
+    );
+    assert_eq!(
+        check("main() { state auto:running; } Foo() <idle> {}"),
+        Ok(())
+    );
+    assert_eq!(check("main() { state running; } entry() <idle> {}"), Ok(()));
+}
+
+#[test]
+fn test_states_errors() {
+    assert_eq!(
+        check("Foo(a) <idle> {} Foo(&a) <running> {}"),
+        Err(String::from(
+            "function `Foo` has a different signature in state <running>"
+        ))
+    );
+    assert_eq!(
+        check("Float:Foo() <idle> {} Foo() <> {}"),
+        Err(String::from(
+            "function `Foo` has a different signature in state <>"
+        ))
+    );
+    assert_eq!(
+        check("Foo() <idle> {} Foo() <idle> {}"),
+        Err(String::from(
+            "function `Foo` is implemented twice for state `idle`"
+        ))
+    );
+    assert_eq!(
+        check("Foo() <> {} Foo() <> {}"),
+        Err(String::from("function `Foo` has more than one fallback"))
+    );
+    assert_eq!(
+        check("Foo() <a:idle> {} Foo() <b:idle> {}"),
+        Err(String::from(
+            "function `Foo` is implemented for more than one automaton"
+        ))
+    );
+    assert_eq!(
+        check("Foo() {} Foo() <idle> {}"),
+        Err(String::from(
+            "function `Foo` is defined both with and without states"
+        ))
+    );
+}
+
+#[test]
+fn test_states_error_details() {
+    // every problem is reported, not just the first
+    assert_eq!(
+        check("Foo() <idle> {} Foo() <idle> {} Foo() <> {} Foo() <> {}"),
+        Err(String::from(
+            "function `Foo` is implemented twice for state `idle`; function `Foo` has more than one fallback"
+        ))
+    );
+
+    let found = errors("main() {\n    state busy;\n}\nFoo() <idle> {}\nFoo() <idle> {}\n");
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].code, ErrorCode::InvalidFunction);
+    assert_eq!(at(found[0].span), (5, 7));
+    assert_eq!(at(found[0].related[0].0), (4, 7));
+    assert_eq!(found[0].related[0].1, "first implemented here");
+    assert_eq!(
+        found[1].message,
+        "function `Foo` has no implementation for state `busy` and no fallback"
+    );
+    assert_eq!(at(found[1].span), (4, 7));
+    assert_eq!(at(found[1].related[0].0), (2, 5));
+
+    let found = errors("Foo() <a:idle> {}\nFoo() <b:idle> {}");
+    assert_eq!(at(found[0].span), (2, 7));
+    assert_eq!(at(found[0].related[0].0), (1, 7));
+}
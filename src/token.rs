@@ -1,83 +1,128 @@
-#[derive(Debug)]
+use std::fmt;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::intern::Name;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub value: TokenValue,
+    pub span: Span,
+}
+
+/// Position is a place in the source. Lines and columns start at 1.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Position {
     pub line: i32,
     pub column: i32,
-    pub range: i32,
 }
 
-#[derive(Debug)]
+/// Span is the source text from `start` up to, but not including, `end`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// to returns the span from the start of this one to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
 pub enum TokenValue {
     None,
+    Name(Name), // the interned name of a symbol
     String(String),
     Integer(i32),
-    Float(f32),
+    Float(#[serde(with = "crate::json::float")] f32),
+    Malformed(String),  // an Illegal number that isn't spelled right, like `0x`
+    OutOfRange(String), // an Illegal number too large for a cell or a float
 }
 
 impl TokenValue {
     pub fn len(&self) -> usize {
         match self {
             TokenValue::None => 0,
-            TokenValue::String(v) => v.len(),
+            TokenValue::Name(v) => v.len(),
+            TokenValue::String(v) | TokenValue::Malformed(v) | TokenValue::OutOfRange(v) => v.len(),
             TokenValue::Integer(v) => v.to_string().len(),
             TokenValue::Float(v) => v.to_string().len(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TokenType {
+    #[default]
     Illegal,
     End,
 
     // -
     // Symbols
     // -
-    Equal,            // ==
-    Assign,           // =
-    Plus,             // +
-    PlusPlus,         // ++
-    PlusAssign,       // +=
-    Minus,            // -
-    MinusMinus,       // --
-    MinusAssign,      // -=
-    Asterisk,         // *
-    AsteriskAssign,   // *=
-    Slash,            // /
-    SlashAssign,      // /=
-    Percent,          // %
-    PercentAssign,    // %=
-    And,              // &&
-    BitAnd,           // &
-    BitAndAssign,     // &=
-    Or,               // ||
-    BitOr,            // |
-    BitOrAssign,      // |=
-    BitXor,           // ^
-    BitXorAssign,     // ^=
-    LowerThan,        // <
-    LowerThanEqual,   // <=
-    BitLeft,          // <<
-    BitLeftAssign,    // <<=
-    GreaterThan,      // >
-    GreaterThanEqual, // >=
-    BitRight,         // >>
-    BitRightAssign,   // >>=
-    NotEqual,         // !=
-    Bang,             // !
-    Colon,            // :
-    Semicolon,        // ;
-    Comma,            // ,
-    LeftBrace,        // {
-    RightBrace,       // }
-    LeftBracket,      // (
-    RightBracket,     // )
-    LeftSquare,       // [
-    RightSquare,      // ]
-    Elipsis,          // ...
-    Range,            // ..
-    Directive,        // #
+    Equal,                  // ==
+    Assign,                 // =
+    Plus,                   // +
+    PlusPlus,               // ++
+    PlusAssign,             // +=
+    Minus,                  // -
+    MinusMinus,             // --
+    MinusAssign,            // -=
+    Asterisk,               // *
+    AsteriskAssign,         // *=
+    Slash,                  // /
+    SlashAssign,            // /=
+    Percent,                // %
+    PercentAssign,          // %=
+    And,                    // &&
+    BitAnd,                 // &
+    BitAndAssign,           // &=
+    Or,                     // ||
+    BitOr,                  // |
+    BitOrAssign,            // |=
+    BitXor,                 // ^
+    BitXorAssign,           // ^=
+    LowerThan,              // <
+    LowerThanEqual,         // <=
+    BitLeft,                // <<
+    BitLeftAssign,          // <<=
+    GreaterThan,            // >
+    GreaterThanEqual,       // >=
+    BitRight,               // >>
+    BitRightAssign,         // >>=
+    BitRightUnsigned,       // >>>
+    BitRightUnsignedAssign, // >>>=
+    NotEqual,               // !=
+    Bang,                   // !
+    Tilde,                  // ~
+    Question,               // ?
+    Colon,                  // :
+    Semicolon,              // ;
+    Comma,                  // ,
+    LeftBrace,              // {
+    RightBrace,             // }
+    LeftBracket,            // (
+    RightBracket,           // )
+    LeftSquare,             // [
+    RightSquare,            // ]
+    Elipsis,                // ...
+    Range,                  // ..
+    Dot,                    // .
+    Directive,              // #
 
     // -
     // Keywords - declaration/definition
@@ -97,121 +142,145 @@ pub enum TokenType {
     // -
     // Keywords - control flow
     // -
-    If,       // if
-    Else,     // else
-    Switch,   // switch
-    Case,     // case
-    Default,  // default
-    For,      // for
-    While,    // while
-    Do,       // do
-    Break,    // break
-    Continue, // continue
-    Goto,     // goto
-    Return,   // return
-    Sizeof,   // sizeof
-    Tagof,    // tagof
-    Emit,     // __emit
+    If,        // if
+    Else,      // else
+    Switch,    // switch
+    Case,      // case
+    Default,   // default
+    For,       // for
+    While,     // while
+    Do,        // do
+    Break,     // break
+    Continue,  // continue
+    Goto,      // goto
+    Return,    // return
+    Exit,      // exit
+    Sleep,     // sleep
+    Assert,    // assert
+    Sizeof,    // sizeof
+    Tagof,     // tagof
+    Defined,   // defined
+    Emit,      // __emit
+    Nameof,    // __nameof
+    Addressof, // __addressof
 
     // -
     // Patterns
     // -
-    Integer, // integer number
-    Float,   // floating point number
-    Symbol,  // a-zA-Z0-9_@
-    Label,   // a-zA-Z0-9_
-    Literal, // ".*"
+    Integer,       // integer number
+    Float,         // floating point number
+    Symbol,        // a-zA-Z0-9_@
+    Label,         // a-zA-Z0-9_
+    Literal,       // ".*"
+    PackedLiteral, // !".*"
+    Character,     // '.'
     Comment,
+    Whitespace,
 }
 
-impl Default for TokenType {
-    fn default() -> TokenType {
-        TokenType::Illegal
+impl TokenType {
+    pub fn is_keyword(&self) -> bool {
+        *self != TokenType::Symbol && lookup_keyword(&self.to_string()) == *self
     }
 }
 
-impl ToString for TokenType {
-    fn to_string(&self) -> String {
-        match self {
-            TokenType::Illegal => String::from("Illegal"),
-            TokenType::End => String::from("End"),
-            TokenType::Equal => String::from("=="),
-            TokenType::Assign => String::from("="),
-            TokenType::Plus => String::from("+"),
-            TokenType::PlusPlus => String::from("++"),
-            TokenType::PlusAssign => String::from("+="),
-            TokenType::Minus => String::from("-"),
-            TokenType::MinusMinus => String::from("--"),
-            TokenType::MinusAssign => String::from("-="),
-            TokenType::Asterisk => String::from("*"),
-            TokenType::AsteriskAssign => String::from("*="),
-            TokenType::Slash => String::from("/"),
-            TokenType::SlashAssign => String::from("/="),
-            TokenType::Percent => String::from("%"),
-            TokenType::PercentAssign => String::from("%="),
-            TokenType::And => String::from("&&"),
-            TokenType::BitAnd => String::from("&"),
-            TokenType::BitAndAssign => String::from("&="),
-            TokenType::Or => String::from("||"),
-            TokenType::BitOr => String::from("|"),
-            TokenType::BitOrAssign => String::from("|="),
-            TokenType::BitXor => String::from("^"),
-            TokenType::BitXorAssign => String::from("^="),
-            TokenType::LowerThan => String::from("<"),
-            TokenType::LowerThanEqual => String::from("<="),
-            TokenType::BitLeft => String::from("<<"),
-            TokenType::BitLeftAssign => String::from("<<="),
-            TokenType::GreaterThan => String::from(">"),
-            TokenType::GreaterThanEqual => String::from(">="),
-            TokenType::BitRight => String::from(">>"),
-            TokenType::BitRightAssign => String::from(">>="),
-            TokenType::NotEqual => String::from("!="),
-            TokenType::Bang => String::from("!"),
-            TokenType::Colon => String::from(":"),
-            TokenType::Semicolon => String::from(";"),
-            TokenType::Comma => String::from(","),
-            TokenType::LeftBrace => String::from("{"),
-            TokenType::RightBrace => String::from("}"),
-            TokenType::LeftBracket => String::from("("),
-            TokenType::RightBracket => String::from(")"),
-            TokenType::LeftSquare => String::from("["),
-            TokenType::RightSquare => String::from("]"),
-            TokenType::Elipsis => String::from("..."),
-            TokenType::Range => String::from(".."),
-            TokenType::Directive => String::from("#"),
-            TokenType::Const => String::from("const"),
-            TokenType::New => String::from("new"),
-            TokenType::Static => String::from("static"),
-            TokenType::Stock => String::from("stock"),
-            TokenType::Forward => String::from("forward"),
-            TokenType::Public => String::from("public"),
-            TokenType::Native => String::from("native"),
-            TokenType::Operator => String::from("operator"),
-            TokenType::Char => String::from("char"),
-            TokenType::Enum => String::from("enum"),
-            TokenType::State => String::from("state"),
-            TokenType::If => String::from("if"),
-            TokenType::Else => String::from("else"),
-            TokenType::Switch => String::from("switch"),
-            TokenType::Case => String::from("case"),
-            TokenType::Default => String::from("default"),
-            TokenType::For => String::from("for"),
-            TokenType::While => String::from("while"),
-            TokenType::Do => String::from("do"),
-            TokenType::Break => String::from("break"),
-            TokenType::Continue => String::from("continue"),
-            TokenType::Goto => String::from("goto"),
-            TokenType::Return => String::from("return"),
-            TokenType::Sizeof => String::from("sizeof"),
-            TokenType::Tagof => String::from("tagof"),
-            TokenType::Emit => String::from("__emit"),
-            TokenType::Integer => String::from("Integer"),
-            TokenType::Float => String::from("Float"),
-            TokenType::Symbol => String::from("Symbol"),
-            TokenType::Label => String::from("Label"),
-            TokenType::Literal => String::from("Literal"),
-            TokenType::Comment => String::from("Comment"),
-        }
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            TokenType::Illegal => "Illegal",
+            TokenType::End => "End",
+            TokenType::Equal => "==",
+            TokenType::Assign => "=",
+            TokenType::Plus => "+",
+            TokenType::PlusPlus => "++",
+            TokenType::PlusAssign => "+=",
+            TokenType::Minus => "-",
+            TokenType::MinusMinus => "--",
+            TokenType::MinusAssign => "-=",
+            TokenType::Asterisk => "*",
+            TokenType::AsteriskAssign => "*=",
+            TokenType::Slash => "/",
+            TokenType::SlashAssign => "/=",
+            TokenType::Percent => "%",
+            TokenType::PercentAssign => "%=",
+            TokenType::And => "&&",
+            TokenType::BitAnd => "&",
+            TokenType::BitAndAssign => "&=",
+            TokenType::Or => "||",
+            TokenType::BitOr => "|",
+            TokenType::BitOrAssign => "|=",
+            TokenType::BitXor => "^",
+            TokenType::BitXorAssign => "^=",
+            TokenType::LowerThan => "<",
+            TokenType::LowerThanEqual => "<=",
+            TokenType::BitLeft => "<<",
+            TokenType::BitLeftAssign => "<<=",
+            TokenType::GreaterThan => ">",
+            TokenType::GreaterThanEqual => ">=",
+            TokenType::BitRight => ">>",
+            TokenType::BitRightAssign => ">>=",
+            TokenType::BitRightUnsigned => ">>>",
+            TokenType::BitRightUnsignedAssign => ">>>=",
+            TokenType::NotEqual => "!=",
+            TokenType::Bang => "!",
+            TokenType::Tilde => "~",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            TokenType::Semicolon => ";",
+            TokenType::Comma => ",",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "(",
+            TokenType::RightBracket => ")",
+            TokenType::LeftSquare => "[",
+            TokenType::RightSquare => "]",
+            TokenType::Elipsis => "...",
+            TokenType::Range => "..",
+            TokenType::Dot => ".",
+            TokenType::Directive => "#",
+            TokenType::Const => "const",
+            TokenType::New => "new",
+            TokenType::Static => "static",
+            TokenType::Stock => "stock",
+            TokenType::Forward => "forward",
+            TokenType::Public => "public",
+            TokenType::Native => "native",
+            TokenType::Operator => "operator",
+            TokenType::Char => "char",
+            TokenType::Enum => "enum",
+            TokenType::State => "state",
+            TokenType::If => "if",
+            TokenType::Else => "else",
+            TokenType::Switch => "switch",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+            TokenType::For => "for",
+            TokenType::While => "while",
+            TokenType::Do => "do",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Goto => "goto",
+            TokenType::Return => "return",
+            TokenType::Exit => "exit",
+            TokenType::Sleep => "sleep",
+            TokenType::Assert => "assert",
+            TokenType::Sizeof => "sizeof",
+            TokenType::Tagof => "tagof",
+            TokenType::Defined => "defined",
+            TokenType::Emit => "__emit",
+            TokenType::Nameof => "__nameof",
+            TokenType::Addressof => "__addressof",
+            TokenType::Integer => "Integer",
+            TokenType::Float => "Float",
+            TokenType::Symbol => "Symbol",
+            TokenType::Label => "Label",
+            TokenType::Literal => "Literal",
+            TokenType::PackedLiteral => "PackedLiteral",
+            TokenType::Character => "Character",
+            TokenType::Comment => "Comment",
+            TokenType::Whitespace => "Whitespace",
+        };
+        f.write_str(s)
     }
 }
 
@@ -241,9 +310,15 @@ pub fn lookup_keyword(kw: &str) -> TokenType {
         "continue" => TokenType::Continue,
         "goto" => TokenType::Goto,
         "return" => TokenType::Return,
+        "exit" => TokenType::Exit,
+        "sleep" => TokenType::Sleep,
+        "assert" => TokenType::Assert,
         "sizeof" => TokenType::Sizeof,
         "tagof" => TokenType::Tagof,
+        "defined" => TokenType::Defined,
         "__emit" => TokenType::Emit,
+        "__nameof" => TokenType::Nameof,
+        "__addressof" => TokenType::Addressof,
 
         _ => TokenType::Symbol,
     }
@@ -0,0 +1,219 @@
+#[cfg(test)]
+use crate::ast::visit;
+#[cfg(test)]
+use crate::ast::visit::Visitor;
+#[cfg(test)]
+use crate::ast::visit_mut;
+#[cfg(test)]
+use crate::ast::visit_mut::VisitMut;
+#[cfg(test)]
+use crate::ast::Call;
+#[cfg(test)]
+use crate::ast::CompilationUnit;
+#[cfg(test)]
+use crate::ast::EmitInstruction;
+#[cfg(test)]
+use crate::ast::FunctionDecl;
+#[cfg(test)]
+use crate::ast::Goto;
+#[cfg(test)]
+use crate::ast::Item;
+#[cfg(test)]
+use crate::ast::Label;
+#[cfg(test)]
+use crate::ast::NativeBinding;
+#[cfg(test)]
+use crate::ast::Parser;
+#[cfg(test)]
+use crate::ast::Return;
+#[cfg(test)]
+use crate::ast::StateSpec;
+#[cfg(test)]
+use crate::ast::Symbol;
+#[cfg(test)]
+use crate::ast::Tag;
+#[cfg(test)]
+use crate::intern::Name;
+#[cfg(test)]
+use crate::lexer::Lexer;
+
+#[cfg(test)]
+fn parse(input: &str) -> CompilationUnit {
+    let mut p = Parser::new(Lexer::new(input).lex());
+    p.parse().expect("failed to parse");
+    p.unit
+}
+
+/// Names records every symbol, call and tag it visits, in order.
+#[cfg(test)]
+#[derive(Default)]
+struct Names(Vec<String>);
+
+#[cfg(test)]
+impl Visitor for Names {
+    fn visit_call(&mut self, call: &Call) {
+        self.0.push(format!("{}()", call.function.name));
+        for argument in &call.arguments {
+            self.visit_argument(argument);
+        }
+    }
+
+    fn visit_symbol(&mut self, symbol: &Symbol) {
+        self.0.push(symbol.name.to_string());
+    }
+
+    fn visit_tag(&mut self, tag: &Tag) {
+        self.0.push(format!("{}:", tag.name));
+    }
+}
+
+#[cfg(test)]
+fn names(unit: &CompilationUnit) -> String {
+    let mut names = Names::default();
+    names.visit_unit(unit);
+    names.0.join(" ")
+}
+
+#[test]
+fn test_visitor() {
+    let unit = parse(
+        "
+enum E_DATA { Float:E_X = BASE }
+new a[MAX] = {b, c};
+Float:Foo(Float:x = y) {
+    for (new i = 0; i < len; i++) {
+        switch (Bar(i, .z = w)) {
+            case ONE: return Float:x;
+            default: state s;
+        }
+    }
+    return _:d;
+}
+",
+    );
+    assert_eq!(
+        names(&unit),
+        "Float: BASE MAX b c Float: Float: y i len i Bar() i w ONE Float: x _: d"
+    );
+}
+
+#[test]
+fn test_visitor_statements() {
+    /// Hooks records the statement and declaration hooks it is called for,
+    /// along with every symbol.
+    #[derive(Default)]
+    struct Hooks(Vec<String>);
+    impl Visitor for Hooks {
+        fn visit_native_binding(&mut self, native: &NativeBinding) {
+            self.0.push(format!("native {:?}", native));
+        }
+
+        fn visit_state_spec(&mut self, states: &StateSpec) {
+            self.0.push(format!("<{}>", states.states.len()));
+        }
+
+        fn visit_emit_instruction(&mut self, instruction: &EmitInstruction) {
+            self.0.push(instruction.opcode.mnemonic().to_string());
+            visit::walk_emit_instruction(self, instruction);
+        }
+
+        fn visit_return(&mut self, r: &Return) {
+            self.0.push(String::from("return"));
+            visit::walk_return(self, r);
+        }
+
+        fn visit_label(&mut self, label: &Label) {
+            self.0.push(format!("{}:", label.name));
+        }
+
+        fn visit_goto(&mut self, goto: &Goto) {
+            self.0.push(format!("goto {}", goto.label));
+        }
+
+        fn visit_symbol(&mut self, symbol: &Symbol) {
+            self.0.push(symbol.name.to_string());
+        }
+    }
+
+    let unit = parse(
+        "
+native Foo() = -1;
+Bar() <idle, busy> {
+    #emit load.pri a
+    __emit(stor.pri b, jump .);
+again:
+    goto again;
+    assert c;
+    return d;
+}
+",
+    );
+    let mut hooks = Hooks::default();
+    hooks.visit_unit(&unit);
+    assert_eq!(
+        hooks.0,
+        vec![
+            "native Index(-1)",
+            "<2>",
+            "load.pri",
+            "a",
+            "stor.pri",
+            "b",
+            "jump",
+            "again:",
+            "goto again",
+            "c",
+            "return",
+            "d"
+        ]
+    );
+}
+
+#[test]
+fn test_visitor_override() {
+    // a visitor that doesn't walk a node's children never sees them
+    struct Functions(Vec<String>);
+    impl Visitor for Functions {
+        fn visit_function(&mut self, function: &FunctionDecl) {
+            self.0.push(function.name.to_string());
+        }
+
+        fn visit_symbol(&mut self, symbol: &Symbol) {
+            self.0.push(symbol.name.to_string());
+        }
+    }
+
+    let unit = parse("new a = b;\nFoo() { c; }\nBar() { d; }");
+    let mut functions = Functions(vec![]);
+    visit::walk_unit(&mut functions, &unit);
+    assert_eq!(functions.0, vec!["b", "Foo", "Bar"]);
+}
+
+#[test]
+fn test_visit_mut() {
+    struct Rename<'a>(&'a str, &'a str);
+    impl VisitMut for Rename<'_> {
+        fn visit_symbol_mut(&mut self, symbol: &mut Symbol) {
+            if symbol.name == self.0 {
+                symbol.name = Name::new(self.1);
+            }
+        }
+
+        fn visit_tag_mut(&mut self, tag: &mut Tag) {
+            if tag.name == self.0 {
+                tag.name = Name::new(self.1);
+            }
+        }
+    }
+
+    let mut unit = parse("Foo(Old:x) { Old(x, old); return Old:x + Old; }");
+    Rename("Old", "New").visit_unit_mut(&mut unit);
+    assert_eq!(names(&unit), "New: New() x old New: x New");
+
+    // walk functions can be called directly on any node
+    let mut unit = parse("Foo() { Old = Old; }");
+    if let Item::Function(f) = &mut unit.items[0] {
+        visit_mut::walk_block_mut(&mut Rename("Old", "New"), f.body.as_mut().unwrap());
+    }
+    assert_eq!(names(&unit), "New New");
+}